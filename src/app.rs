@@ -1,3 +1,29 @@
+use clap::ArgMatches;
+use recall::{config::{Color, Config, Layer}, error::Result, store::DirStore};
+
+/// The decks all commands work on.
+pub fn store(config: &Config) -> DirStore {
+  DirStore::new(config.deck_dir())
+}
+
+/// The settings of all commands, including `--deck-dir` and `--color`. Fails
+/// if a config file or environment variable is invalid.
+pub fn config(matches: &ArgMatches) -> Result<Config> {
+  let flags = [("deck_dir", "deck-dir"), ("color", "color")].iter()
+    .filter_map(|&(key, flag)| matches.value_of(flag).map(|value| Layer::flag(key, flag, value)))
+    .collect::<Result<Vec<_>>>()?;
+
+  Config::load(flags)
+}
+
+pub fn apply_color(config: &Config) {
+  match config.color() {
+    Color::Always => colored::control::set_override(true),
+    Color::Never => colored::control::set_override(false),
+    Color::Auto => {}
+  }
+}
+
 pub fn name() -> &'static str {
   env!("CARGO_PKG_NAME")
 }
@@ -1,7 +1,10 @@
-use chrono::{offset::Utc, DateTime, Duration, TimeZone};
-use crate::list::List;
-use std::{path::PathBuf, fmt::Display, collections::HashMap, io::Result};
+use chrono::{offset::{Local, Utc}, DateTime, Duration, TimeZone};
+use crate::ladder::Ladder;
+use crate::store::DeckStore;
+use std::{fmt::Display, collections::{HashMap, HashSet}};
+use crate::error::Result;
 use colored::{ColoredString, Colorize};
+use serde_json::{Map, Value};
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum Proficiency {
@@ -52,7 +55,16 @@ pub fn list_proficiencies() -> Vec<Proficiency> {
   ]
 }
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Outcome {
+  Remembered,
+  Forgotten,
+  Suspended,
+  Buried
+}
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct Card {
   pub line_number: Option<usize>,
   pub front: String,
@@ -62,7 +74,12 @@ pub struct Card {
   pub due_time: Option<DateTime<Utc>>,
   pub correct_count: u32,
   pub total_count: u32,
-  phantom: ()
+  pub suspended: bool,
+  pub buried_until: Option<DateTime<Utc>>,
+  /// The learning step an Inactive card waits on before it is shown again.
+  pub learning_step: Option<usize>,
+  /// Stored fields unknown to this version, which are kept as they are.
+  pub extra: Map<String, Value>
 }
 
 impl Card {
@@ -76,19 +93,25 @@ impl Card {
       due_time: None,
       correct_count: 0,
       total_count: 0,
-      phantom: ()
+      suspended: false,
+      buried_until: None,
+      learning_step: None,
+      extra: Map::new()
     }
   }
 
-  pub fn proficiency(&self) -> Proficiency {
-    match self.level {
-      x if x <= 0 => Proficiency::Inactive,
-      x if x <= 4 => Proficiency::Apprentice,
-      x if x <= 6 => Proficiency::Guru,
-      x if x <= 7 => Proficiency::Master,
-      x if x <= 8 => Proficiency::Enlightened,
-      _ => Proficiency::Burned
-    }
+  pub fn proficiency(&self, ladder: &Ladder) -> Proficiency {
+    ladder.proficiency(self.level)
+  }
+
+  /// Whether the card is going through the learning steps.
+  pub fn is_learning(&self) -> bool {
+    self.learning_step.is_some()
+  }
+
+  /// Whether the card was never shown, unlike Inactive cards in learning.
+  pub fn is_new(&self, ladder: &Ladder) -> bool {
+    self.proficiency(ladder) == Proficiency::Inactive && !self.is_learning()
   }
 
   pub fn correctness(&self) -> Option<f64> {
@@ -99,99 +122,459 @@ impl Card {
     }
   }
 
-  pub fn critical(&self) -> bool {
-    self.proficiency() == Proficiency::Apprentice
+  /// The `#hashtags` in the notes, without the `#`.
+  pub fn tags(&self) -> Vec<&str> {
+    self.notes.split_whitespace()
+    .filter_map(|word| word.strip_prefix('#'))
+    .map(|tag| tag.trim_end_matches(|c: char| c.is_ascii_punctuation()))
+    .filter(|tag| !tag.is_empty())
+    .collect()
+  }
+
+  /// Whether this card got further than the other one, by level and then by
+  /// number of reviews.
+  pub fn has_more_progress(&self, other: &Card) -> bool {
+    (self.level, self.total_count, self.correct_count)
+    > (other.level, other.total_count, other.correct_count)
+  }
+
+  /// Takes over the level, due time, counts and suspension of the other card,
+  /// keeping the own front, back and notes.
+  pub fn copy_schedule(&mut self, other: &Card) -> &mut Card {
+    self.level = other.level;
+    self.due_time = other.due_time;
+    self.correct_count = other.correct_count;
+    self.total_count = other.total_count;
+    self.suspended = other.suspended;
+    self.buried_until = other.buried_until;
+    self.learning_step = other.learning_step;
+    self
+  }
+
+  /// Puts the card back to Inactive, as if it was never learned.
+  pub fn reset(&mut self) -> &mut Card {
+    self.level = 0;
+    self.due_time = None;
+    self.correct_count = 0;
+    self.total_count = 0;
+    self.buried_until = None;
+    self.learning_step = None;
+    self
+  }
+
+  pub fn critical(&self, ladder: &Ladder) -> bool {
+    self.proficiency(ladder) == Proficiency::Apprentice
     && self.total_count > 0
     && self.correctness() < Some(0.75)
   }
 
-  pub fn is_due(&self) -> bool {
-    self.is_due_at(Utc::now())
+  pub fn is_due_at<T: TimeZone>(&self, date_time: DateTime<T>) -> bool {
+    self.is_available_at(date_time.clone())
+    && self.due_time.map(|x| x <= date_time).unwrap_or(false)
   }
 
-  pub fn is_due_at<T: TimeZone>(&self, date_time: DateTime<T>) -> bool {
-    self.due_time.map(|x| x <= date_time).unwrap_or(false)
+  pub fn is_available_at<T: TimeZone>(&self, date_time: DateTime<T>) -> bool {
+    !self.suspended
+    && self.buried_until.map(|x| x <= date_time).unwrap_or(true)
+  }
+
+  pub fn suspend(&mut self) -> &mut Card {
+    self.suspended = true;
+    self
+  }
+
+  pub fn unsuspend(&mut self) -> &mut Card {
+    self.suspended = false;
+    self.buried_until = None;
+    self
+  }
+
+  /// Takes the card out of rotation until the start of the local day after
+  /// `now`.
+  pub fn bury(&mut self, now: DateTime<Utc>) -> &mut Card {
+    let tomorrow = now.with_timezone(&Local).date_naive().succ_opt()
+      .and_then(|date| date.and_hms_opt(0, 0, 0))
+      .and_then(|date_time| Local.from_local_datetime(&date_time).earliest());
+
+    self.buried_until = Some(
+      tomorrow.map(|x| x.with_timezone(&Utc))
+      .unwrap_or_else(|| now + Duration::days(1))
+    );
+    self
+  }
+
+  pub fn apply(&mut self, outcome: Outcome, ladder: &Ladder, now: DateTime<Utc>) -> &mut Card {
+    match outcome {
+      Outcome::Remembered => self.review(ladder, true, now),
+      Outcome::Forgotten => self.review(ladder, false, now),
+      Outcome::Suspended => self.suspend(),
+      Outcome::Buried => self.bury(now)
+    }
   }
 
-  pub fn review(&mut self, remembered: bool) -> &mut Card {
-    if self.proficiency() == Proficiency::Inactive {
-      self.level = 1;
-      self.due_time = Some(Utc::now() + Duration::hours(4));
+  /// A card remembered long after it was due proved to be known for longer
+  /// than its interval. If the time since it was last scheduled reaches the
+  /// interval of the next level, it moves up one extra level.
+  fn overdue_bonus(&self, ladder: &Ladder, now: DateTime<Utc>) -> i8 {
+    let due_time = match self.due_time {
+      Some(due_time) if due_time < now => due_time,
+      _ => return 0
+    };
+
+    let elapsed = now - (due_time - ladder.interval(self.level));
+
+    if self.level < ladder.max_level() && elapsed >= ladder.interval(self.level + 1) {
+      1
+    } else {
+      0
+    }
+  }
+
+  /// Moves an Inactive card through the learning steps. New and forgotten
+  /// cards start at the first step, remembered cards go to the next one and
+  /// graduate to level 1 after the last.
+  fn learn(&mut self, ladder: &Ladder, remembered: bool, now: DateTime<Utc>) {
+    let step = match (self.learning_step, remembered) {
+      (Some(step), true) => step + 1,
+      _ => 0
+    };
+
+    match ladder.learning_steps().get(step) {
+      Some(&interval) => {
+        self.learning_step = Some(step);
+        self.due_time = Some(now + interval);
+      },
+      None => {
+        self.learning_step = None;
+        self.level = 1;
+        self.due_time = Some(now + ladder.interval(self.level));
+      }
+    }
+  }
+
+  /// Moves the card up or down the level ladder and schedules the next
+  /// review relative to `now`. Inactive cards go through the learning steps
+  /// first.
+  pub fn review(&mut self, ladder: &Ladder, remembered: bool, now: DateTime<Utc>) -> &mut Card {
+    if self.proficiency(ladder) == Proficiency::Inactive {
+      self.learn(ladder, remembered, now);
     } else {
       if remembered {
-        self.level = 9.min(self.level + 1);
+        self.level = ladder.max_level().min(self.level + 1 + self.overdue_bonus(ladder, now));
         self.correct_count += 1;
       } else {
-        self.level = 1.max(self.level - 2);
+        self.level = 1.max(self.level - ladder.penalty());
       }
 
       self.total_count += 1;
-      self.due_time = Some(Utc::now() + match self.level {
-        x if x <= 0 => panic!(),
-        x if x == 1 => Duration::hours(4),
-        x if x == 2 => Duration::hours(8),
-        x if x == 3 => Duration::days(1),
-        x if x == 4 => Duration::days(3),
-        x if x == 5 => Duration::days(7),
-        x if x == 6 => Duration::days(14),
-        x if x == 7 => Duration::days(30),
-        x if x == 8 => Duration::days(122),
-        _ => Duration::days(182)
-      });
+      self.due_time = Some(now + ladder.interval(self.level));
     }
 
     self
   }
 }
 
-pub fn get_cards<I: IntoIterator<Item = List>>(lists: I) -> impl Iterator<Item = (PathBuf, Card)> {
-  lists.into_iter()
-  .filter_map(|list| list.cards().ok().map(|cards| (list, cards)))
-  .map(|(list, cards)| {
-    let name = list.path().to_path_buf();
-    cards.map(move|card| (name.clone(), card))
+/// Reads the cards of the given decks, together with their deck name. Decks
+/// that cannot be read are skipped.
+pub fn get_cards<S, I>(store: &S, decks: I) -> Vec<(String, Card)>
+where
+  S: DeckStore + ?Sized,
+  I: IntoIterator<Item = String>
+{
+  decks.into_iter()
+  .filter_map(|deck| store.cards(&deck).ok().map(|cards| (deck, cards)))
+  .flat_map(|(deck, cards)| {
+    cards.into_iter().map(move|card| (deck.clone(), card))
   })
-  .flatten()
+  .collect()
 }
 
-pub fn update_cards<I: IntoIterator<Item = (PathBuf, Card)>>(cards: I) -> Result<()> {
-  let mut lists = HashMap::new();
-
-  for (path, card) in cards {
-    let list_entry = {
-      if !lists.contains_key(&path) {
-        let list = List::new(&path);
-        let cards = if let Some(list) = list.as_ref() {
-          Some(
-            list.cards()?
-            .filter_map(|card| card.line_number.map(|x| (x, card)))
-            .collect::<HashMap<_, _>>()
-          )
-        } else {
-          None
-        };
-
-        if let (Some(list), Some(cards)) = (list, cards) {
-          lists.insert(path.clone(), (list, cards));
-        }
-      }
+/// Writes the given cards back to their decks, matching them by line number,
+/// and returns the replaced cards as they were before the update.
+pub fn update_cards<S, I>(store: &S, cards: I) -> Result<Vec<(String, Card)>>
+where
+  S: DeckStore + ?Sized,
+  I: IntoIterator<Item = (String, Card)>
+{
+  let mut decks = HashMap::<String, Vec<Card>>::new();
+  let mut previous = Vec::new();
 
-      lists.get_mut(&path)
-    };
+  for (deck, card) in cards {
+    decks.entry(deck).or_default().push(card);
+  }
 
-    if let (Some((_, cards)), Some(line_number)) = (list_entry, card.line_number) {
-      if cards.contains_key(&line_number) {
-        cards.insert(line_number, card);
-      }
+  for (deck, cards) in decks {
+    if !store.deck_exists(&deck) {
+      continue;
     }
+
+    previous.extend(
+      store.update_cards(&deck, cards)?
+      .into_iter()
+      .map(|card| (deck.clone(), card))
+    );
+  }
+
+  Ok(previous)
+}
+
+/// Finds cards remembered by an earlier session in their decks again, since
+/// lines may have moved or changed in between. A card stays on its line if the
+/// front there still matches, otherwise it moves to another card with the same
+/// front in the deck. Returns the current line number of every card in order,
+/// or `None` for cards that are gone. Copies of the same card, e.g. answers
+/// to a card in learning, are found on the same line.
+pub fn locate_cards<S: DeckStore + ?Sized>(store: &S, cards: &[(String, Card)]) -> Vec<Option<usize>> {
+  let mut decks = HashMap::<&str, Vec<(usize, String)>>::new();
+  let mut claimed = HashSet::<(&str, usize)>::new();
+  let mut located = HashMap::<(&str, Option<usize>, &str), Option<usize>>::new();
+
+  for (deck, _) in cards.iter() {
+    decks.entry(deck).or_insert_with(|| {
+      store.cards(deck).ok()
+      .map(|cards| {
+        cards.into_iter()
+        .filter_map(|card| card.line_number.map(|i| (i, card.front)))
+        .collect()
+      })
+      .unwrap_or_default()
+    });
+  }
+
+  // Cards still on their line go first, so that they are not taken by cards
+  // with the same front that moved.
+
+  for (deck, card) in cards.iter() {
+    let key = (deck.as_str(), card.line_number, card.front.as_str());
+    let same_line = card.line_number
+      .filter(|i| decks[key.0].iter().any(|(j, front)| i == j && *front == card.front));
+
+    if let Some(i) = same_line {
+      claimed.insert((key.0, i));
+      located.insert(key, Some(i));
+    }
+  }
+
+  cards.iter()
+  .map(|(deck, card)| {
+    let key = (deck.as_str(), card.line_number, card.front.as_str());
+
+    *located.entry(key).or_insert_with(|| {
+      let line = decks[key.0].iter()
+        .find(|(i, front)| *front == card.front && !claimed.contains(&(key.0, *i)))
+        .map(|&(i, _)| i);
+
+      if let Some(i) = line {
+        claimed.insert((key.0, i));
+      }
+
+      line
+    })
+  })
+  .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+  }
+
+  fn card(level: i8, due_time: Option<DateTime<Utc>>) -> Card {
+    let mut card = Card::new("a".to_string(), "b".to_string(), String::new());
+
+    card.level = level;
+    card.due_time = due_time;
+    card
+  }
+
+  #[test]
+  fn review_moves_along_ladder() {
+    let ladder = Ladder::default();
+    let mut remembered = card(3, Some(now()));
+    let mut forgotten = card(5, Some(now()));
+    let mut lowest = card(1, Some(now()));
+    let mut new = card(0, None);
+
+    remembered.review(&ladder, true, now());
+    forgotten.review(&ladder, false, now());
+    lowest.review(&ladder, false, now());
+    new.review(&ladder, false, now());
+
+    assert_eq!(remembered.level, 4);
+    assert_eq!(remembered.due_time, Some(now() + ladder.interval(4)));
+    assert_eq!((remembered.correct_count, remembered.total_count), (1, 1));
+    assert_eq!(forgotten.level, 3);
+    assert_eq!((forgotten.correct_count, forgotten.total_count), (0, 1));
+    assert_eq!(lowest.level, 1);
+    assert_eq!((new.level, new.total_count), (1, 0));
+    assert_eq!(new.due_time, Some(now() + ladder.interval(1)));
+  }
+
+  #[test]
+  fn review_stops_at_max_level() {
+    let ladder = Ladder::default();
+    let mut card = card(ladder.max_level(), Some(now()));
+
+    card.review(&ladder, true, now());
+
+    assert_eq!(card.level, ladder.max_level());
+    assert_eq!(card.proficiency(&ladder), Proficiency::Burned);
+  }
+
+  #[test]
+  fn review_follows_given_ladder() {
+    let ladder = Ladder::new(vec![Duration::hours(1), Duration::hours(2), Duration::hours(3)], 2, [1, 1, 2, 2])
+      .unwrap();
+    let mut remembered = card(2, Some(now()));
+    let mut forgotten = card(3, Some(now()));
+
+    remembered.review(&ladder, true, now());
+    forgotten.review(&ladder, false, now());
+
+    assert_eq!(remembered.level, 3);
+    assert_eq!(remembered.due_time, Some(now() + Duration::hours(3)));
+    assert_eq!(forgotten.level, 1);
+    assert_eq!(forgotten.due_time, Some(now() + Duration::hours(1)));
+  }
+
+  #[test]
+  fn suspended_and_buried_cards_are_not_due() {
+    let mut suspended = card(1, Some(now()));
+    let mut buried = card(1, Some(now()));
+
+    suspended.suspend();
+    buried.bury(now());
+
+    assert!(!suspended.is_due_at(now()));
+    assert!(!buried.is_due_at(now()));
+    assert!(buried.is_due_at(now() + Duration::days(2)));
+    assert!(buried.unsuspend().is_due_at(now()));
   }
 
-  for (_, (list, cards)) in lists.into_iter() {
-    let mut cards = cards.into_iter().collect::<Vec<_>>();
-    cards.sort_by_key(|(i, _)| *i);
+  #[test]
+  fn tags_come_from_notes() {
+    let card = Card::new("a".to_string(), String::new(), "#verb, irregular #past. # x#y".to_string());
 
-    list.save_cards(cards.into_iter().map(|(_, card)| card))?;
+    assert_eq!(card.tags(), vec!["verb", "past"]);
   }
 
-  Ok(())
+  #[test]
+  fn progress_compares_level_then_reviews() {
+    let mut a = card(2, None);
+    let mut b = card(2, None);
+
+    a.total_count = 3;
+    b.total_count = 2;
+
+    assert!(a.has_more_progress(&b));
+    assert!(!b.has_more_progress(&a));
+
+    b.level = 3;
+
+    assert!(b.has_more_progress(&a));
+    assert!(!a.has_more_progress(&a));
+  }
+
+  #[test]
+  fn reset_forgets_progress() {
+    let mut card = card(6, Some(now()));
+
+    card.correct_count = 4;
+    card.total_count = 5;
+    card.bury(now());
+    card.reset();
+
+    assert_eq!(card.level, 0);
+    assert_eq!(card.due_time, None);
+    assert_eq!((card.correct_count, card.total_count), (0, 0));
+    assert!(card.buried_until.is_none());
+  }
+
+  #[test]
+  fn long_overdue_cards_move_up_an_extra_level() {
+    let ladder = Ladder::default();
+    let mut late = card(3, Some(now() - Duration::hours(1)));
+    let mut very_late = card(3, Some(now() - Duration::days(3)));
+    let mut forgotten = card(3, Some(now() - Duration::days(3)));
+
+    late.review(&ladder, true, now());
+    very_late.review(&ladder, true, now());
+    forgotten.review(&ladder, false, now());
+
+    assert_eq!(late.level, 4);
+    assert_eq!(very_late.level, 5);
+    assert_eq!(very_late.due_time, Some(now() + ladder.interval(5)));
+    assert_eq!(forgotten.level, 1);
+  }
+
+  #[test]
+  fn new_cards_go_through_learning_steps() {
+    let ladder = Ladder::default().with_learning_steps(vec![Duration::minutes(1), Duration::minutes(10)]).unwrap();
+    let mut card = card(0, None);
+
+    card.review(&ladder, true, now());
+
+    assert_eq!((card.level, card.learning_step), (0, Some(0)));
+    assert_eq!(card.due_time, Some(now() + Duration::minutes(1)));
+    assert!(!card.is_new(&ladder));
+
+    card.review(&ladder, true, now());
+
+    assert_eq!((card.level, card.learning_step), (0, Some(1)));
+
+    card.review(&ladder, false, now());
+
+    assert_eq!((card.level, card.learning_step), (0, Some(0)));
+
+    card.review(&ladder, true, now());
+    card.review(&ladder, true, now());
+
+    assert_eq!((card.level, card.learning_step), (1, None));
+    assert_eq!(card.due_time, Some(now() + ladder.interval(1)));
+    assert_eq!(card.total_count, 0);
+  }
+
+  #[test]
+  fn new_cards_reach_level_one_without_learning_steps() {
+    let ladder = Ladder::default();
+    let mut card = card(0, None);
+
+    card.review(&ladder, false, now());
+
+    assert_eq!((card.level, card.learning_step), (1, None));
+    assert_eq!(card.due_time, Some(now() + ladder.interval(1)));
+  }
+
+  fn session_card(front: &str, line_number: usize) -> (String, Card) {
+    let mut card = Card::new(front.to_string(), String::new(), String::new());
+
+    card.line_number = Some(line_number);
+    ("deck".to_string(), card)
+  }
+
+  fn memory_store(fronts: &[&str]) -> crate::memory::MemoryStore {
+    let store = crate::memory::MemoryStore::new();
+    let cards = fronts.iter().map(|front| Card::new(front.to_string(), String::new(), String::new())).collect();
+
+    store.create_deck("deck").unwrap();
+    store.save_cards("deck", cards).unwrap();
+    store
+  }
+
+  #[test]
+  fn locates_cards_by_front() {
+    let store = memory_store(&["x", "a", "b", "a"]);
+    let cards = vec![
+      session_card("a", 1),
+      session_card("a", 4),
+      session_card("b", 3),
+      session_card("c", 2),
+      session_card("a", 1)
+    ];
+
+    assert_eq!(locate_cards(&store, &cards), vec![Some(2), Some(4), Some(3), None, Some(2)]);
+  }
 }
@@ -1,106 +1,262 @@
-use std::{collections::HashSet, fmt::Display, path::PathBuf};
+use std::{error::Error, fmt::Display, io::{stdin, stdout, BufReader}};
+use clap::{Arg, ArgMatches};
 use colored::Colorize;
-use rustyline::{error::ReadlineError, Editor, KeyPress, Cmd};
-use rand::seq::SliceRandom;
-use crate::{card::Card, commands::RecallError, list::List};
+use serde_json::Value;
+use chrono::{DateTime, Utc};
+use rand::{rngs::StdRng, SeedableRng};
+use recall::{
+  clock::{Clock, FixedClock, SystemClock},
+  filter::Filter,
+  session::Order,
+  terminal::{ScriptedTerminal, Terminal, TtyTerminal}
+};
+use crate::commands::RecallError;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Format {
+  Text,
+  Json,
+  Tsv
+}
 
-pub fn print_strip<S: Display, T: Display>(symbol: S, text: T) {
-  println!("{} {}", symbol, text);
+impl Format {
+  pub fn arg<'a>() -> Arg<'a, 'static> {
+    Arg::with_name("format")
+    .long("format")
+    .help("Output format of informational commands")
+    .takes_value(true)
+    .possible_values(&["text", "json", "tsv"])
+    .default_value("text")
+    .global(true)
+  }
+
+  pub fn from_matches(matches: &ArgMatches) -> Format {
+    match matches.value_of("format") {
+      Some("json") => Format::Json,
+      Some("tsv") => Format::Tsv,
+      _ => Format::Text
+    }
+  }
 }
 
-pub fn print_header_strip<T: Display>(text: T) {
-  print_strip("#", text.to_string().bold().bright_white());
+pub fn deck_dir_arg<'a>() -> Arg<'a, 'static> {
+  Arg::with_name("deck-dir")
+  .long("deck-dir")
+  .value_name("dir")
+  .help("Directory of the lists, overriding the config")
+  .takes_value(true)
+  .global(true)
 }
 
-pub fn print_error_strip<T: Display>(text: T) {
-  print_strip("!".red().bold(), text);
+pub fn color_arg<'a>() -> Arg<'a, 'static> {
+  Arg::with_name("color")
+  .long("color")
+  .value_name("when")
+  .help("Whether to color the output, overriding the config")
+  .takes_value(true)
+  .possible_values(&["auto", "always", "never"])
+  .global(true)
 }
 
-pub fn print_help_strip<T: Display>(text: T) {
-  print_strip("?".blue().bold(), text);
+/// Hidden option to run commands as if it was the given time, e.g. to preview
+/// a review session. Sessions run this way are dry runs, see `is_dry_run`.
+pub fn now_arg<'a>() -> Arg<'a, 'static> {
+  Arg::with_name("now")
+  .long("now")
+  .value_name("rfc3339")
+  .takes_value(true)
+  .global(true)
+  .hidden(true)
 }
 
-pub fn print_bullet_list<D: Display, I: IntoIterator<Item = D>>(list: I) {
-  for item in list {
-    print_strip("*".bright_black(), item);
+pub fn clock_from_matches(matches: &ArgMatches) -> Result<Box<dyn Clock>, RecallError> {
+  match matches.value_of("now") {
+    Some(value) => {
+      let now = DateTime::parse_from_rfc3339(value)
+        .map_err(|_| RecallError::InvalidArgument {
+          name: "now",
+          value: value.to_string()
+        })?;
+
+      Ok(Box::new(FixedClock::new(now.with_timezone(&Utc))))
+    },
+    None => Ok(Box::new(SystemClock))
   }
 }
 
-pub fn prompt<T: Display>(text: T) -> Result<String, RecallError> {
-  prompt_with_prefix(text, "> ")
+/// Whether answers of a session must not be saved, because it runs at a time
+/// given with `--now` and would store due times relative to it.
+pub fn is_dry_run(matches: &ArgMatches) -> bool {
+  matches.is_present("now")
 }
 
-pub fn prompt_with_prefix<T: Display, P: AsRef<str>>(text: T, prefix: P) -> Result<String, RecallError> {
-  let text = text.to_string();
+/// Hidden option to seed the shuffling of cards, so sessions can be repeated.
+pub fn seed_arg<'a>() -> Arg<'a, 'static> {
+  Arg::with_name("seed")
+  .long("seed")
+  .takes_value(true)
+  .global(true)
+  .hidden(true)
+}
 
-  if text.len() > 0 {
-    println!("{}:", text.bright_white());
+pub fn rng_from_matches(matches: &ArgMatches) -> Result<StdRng, RecallError> {
+  match matches.value_of("seed") {
+    Some(value) => {
+      let seed = value.parse::<u64>()
+        .map_err(|_| RecallError::InvalidArgument {
+          name: "seed",
+          value: value.to_string()
+        })?;
+
+      Ok(StdRng::seed_from_u64(seed))
+    },
+    None => Ok(StdRng::from_entropy())
   }
+}
 
-  let mut editor = Editor::<()>::new();
+pub fn filter_arg<'a>() -> Arg<'a, 'static> {
+  Arg::with_name("filter")
+  .long("filter")
+  .value_name("filter")
+  .help("Only selects cards matching all filters")
+  .long_help(
+    "Only selects cards matching all filters: proficiency:<names>, \
+    level:<min>-<max>, tag:<tag>, front:<text>, critical or suspended"
+  )
+  .takes_value(true)
+  .multiple(true)
+  .number_of_values(1)
+}
 
-  editor.readline(prefix.as_ref())
-  .map(|x| Ok(x))
-  .unwrap_or_else(|err| match err {
-    ReadlineError::Eof => Ok(String::new()),
-    ReadlineError::Interrupted => std::process::exit(130),
-    _ => Err(RecallError::new("Unable to prompt user."))
+pub fn filters_from_matches(matches: &ArgMatches) -> Result<Vec<Filter>, RecallError> {
+  matches.values_of("filter").into_iter().flatten()
+  .map(|value| {
+    Filter::parse(value).map_err(|_| RecallError::InvalidArgument {
+      name: "filter",
+      value: value.to_string()
+    })
   })
+  .collect()
 }
 
-pub fn prompt_multiline<T: Display>(text: T) -> Result<String, RecallError> {
-  prompt_multiline_with_initial(text, ("", ""))
+pub fn order_arg<'a>() -> Arg<'a, 'static> {
+  Arg::with_name("order")
+  .long("order")
+  .help("Order of the due cards")
+  .long_help(
+    "Order of the due cards: random, overdue for the longest overdue first, \
+    level for the lowest level first, critical for critical cards first or \
+    relative for the longest overdue compared to their interval first"
+  )
+  .takes_value(true)
+  .possible_values(&["random", "overdue", "level", "critical", "relative"])
+  .default_value("random")
 }
 
-pub fn prompt_multiline_with_initial<T: Display>(text: T, initial: (&str, &str)) -> Result<String, RecallError> {
-  let text = text.to_string();
+pub fn order_from_matches(matches: &ArgMatches) -> Order {
+  matches.value_of("order")
+    .and_then(Order::from_name)
+    .unwrap_or(Order::Random)
+}
 
-  if text.len() > 0 {
-    print!("{}: ", text.bright_white());
+pub fn print_json(value: &Value) {
+  println!("{}", serde_json::to_string_pretty(value).unwrap());
+}
+
+/// Prints rows of tab-separated values. Tabs and newlines inside of values are
+/// escaped, so every row stays on one line.
+pub fn print_tsv<R, I>(rows: R)
+where
+  R: IntoIterator<Item = I>,
+  I: IntoIterator<Item = String>
+{
+  for row in rows {
+    let row = row.into_iter()
+      .map(|value| {
+        value
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+      })
+      .collect::<Vec<_>>();
+
+    println!("{}", row.join("\t"));
   }
+}
+
+pub fn print_strip<S: Display, T: Display>(symbol: S, text: T) {
+  println!("{} {}", symbol, text);
+}
+
+pub fn print_header_strip<T: Display>(text: T) {
+  print_strip("#", text.to_string().bold().bright_white());
+}
 
-  println!("{}", "(Press ^D to finish)".cyan());
+pub fn print_error_strip<T: Display>(text: T) {
+  print_strip("!".red().bold(), text);
+}
 
-  let mut editor = Editor::<()>::new();
+/// Prints a fatal error and all of its causes to stderr.
+pub fn eprint_error<E: Error>(err: &E) {
+  eprintln!("{} {}", "!".red().bold(), err);
 
-  editor.bind_sequence(KeyPress::Enter, Cmd::Insert(1, "\n".to_string()));
-  editor.bind_sequence(KeyPress::Ctrl('D'), Cmd::AcceptLine);
+  let mut source = err.source();
 
-  editor.readline_with_initial("> ", initial)
-  .map(|x| Ok(x))
-  .unwrap_or_else(|err| match err {
-    ReadlineError::Eof => Ok(String::new()),
-    ReadlineError::Interrupted => std::process::exit(130),
-    _ => Err(RecallError::new("Unable to prompt user."))
-  })
+  while let Some(cause) = source {
+    eprintln!("  {} {}", "Caused by:".bright_black(), cause);
+    source = cause.source();
+  }
 }
 
-pub fn prompt_multiple_choice<T: Display>(text: T, hotkeys: &[char]) -> Result<char, RecallError> {
-  let text = text.to_string();
+pub fn print_help_strip<T: Display>(text: T) {
+  print_strip("?".blue().bold(), text);
+}
 
-  if text.len() > 0 {
-    print!("{}: ", text.bright_white());
+pub fn print_bullet_list<D: Display, I: IntoIterator<Item = D>>(list: I) {
+  for item in list {
+    print_strip("*".bright_black(), item);
   }
+}
 
-  let mut editor = Editor::<()>::new();
+pub fn prompt<T: Display>(text: T) -> Result<String, RecallError> {
+  TtyTerminal.prompt(&text.to_string())
+}
 
-  loop {
-    let input = editor.readline("> ")
-      .map(|x| Ok(x))
-      .unwrap_or_else(|err| match err {
-        ReadlineError::Eof => Ok(String::new()),
-        ReadlineError::Interrupted => std::process::exit(130),
-        _ => Err(RecallError::new("Unable to prompt user."))
-      })?;
+/// Asks a yes or no question, defaulting to no.
+pub fn confirm<T: Display>(text: T) -> Result<bool, RecallError> {
+  let answer = prompt(format_args!("{} (y/n)", text))?;
 
-    let chars = input.chars().collect::<Vec<_>>();
+  Ok(["y", "yes"].contains(&answer.trim().to_lowercase().as_str()))
+}
 
-    if chars.len() == 1 && hotkeys.contains(&chars[0]) {
-      break Ok(chars[0].to_ascii_lowercase());
-    }
+pub fn yes_arg<'a>() -> Arg<'a, 'static> {
+  Arg::with_name("yes")
+  .short("y")
+  .long("yes")
+  .help("Skips the confirmation")
+}
+
+pub fn prompt_multiline<T: Display>(text: T) -> Result<String, RecallError> {
+  TtyTerminal.prompt_multiline(&text.to_string(), "")
+}
+
+/// The terminal of interactive sessions, which reads answers from stdin
+/// instead if `--batch` is given.
+pub fn terminal_from_matches(matches: &ArgMatches) -> Box<dyn Terminal> {
+  if matches.is_present("batch") {
+    Box::new(ScriptedTerminal::new(BufReader::new(stdin()), stdout()))
+  } else {
+    Box::new(TtyTerminal)
   }
 }
 
+pub fn batch_arg<'a>() -> Arg<'a, 'static> {
+  Arg::with_name("batch")
+  .long("batch")
+  .help("Reads answers from stdin, one line per prompt, instead of the terminal")
+}
+
 pub fn inline_code<T: Display>(code: T) -> impl Display {
   format!("`{}`", code).cyan()
 }
@@ -127,100 +283,26 @@ pub fn progress_bar(progress: f64, width: u32) -> impl Display {
   result
 }
 
-pub fn loop_cards(
-  mut cards: Vec<(PathBuf, Card)>
-) -> Result<Vec<(PathBuf, Card, bool)>, RecallError> {
-  let total_count = cards.len();
-  let mut result = Vec::new();
-  let mut shown_again = HashSet::new();
-  let mut first = true;
-  let mut rng = rand::thread_rng();
-
-  cards.shuffle(&mut rng);
-
-  while let Some((path, mut card)) = cards.pop() {
-    let list = List::new(&path);
-    if let None = list { continue; }
-
-    let list = list.unwrap();
-    let left_count = cards.len();
-    let proficiency = card.proficiency();
-
-    loop {
-      if first {
-        first = false;
-      } else {
-        println!();
-      }
-
-      println!(
-        "{} - {}",
-        format!(
-          "{}/{}",
-          total_count - left_count,
-          total_count
-        ).green(),
-        proficiency.colorize(proficiency.to_string())
-      );
-      println!("List: {}", list.name());
-      println!();
-      println!("  {}", card.front);
-      prompt_with_prefix("", "")?;
-      println!("{} {}", "Back:".bright_white(), card.back);
-      println!();
-      println!("{} {}", "Notes:".bright_white(), card.notes);
-      println!();
-
-      let choice = prompt_multiple_choice(
-        format_args!(
-          "show {}gain, {}dit, {}ext card - {}inish",
-          "a".bold().cyan().underline(),
-          "e".bold().cyan().underline(),
-          "n".bold().cyan().underline(),
-          "f".bold().cyan().underline()
-        ),
-        &['a', 'e', 'n', 'f']
-      )?;
-
-      match choice {
-        'a' => {
-          let key = (path.clone(), card.line_number);
-
-          cards.push((path, card));
-          cards.shuffle(&mut rng);
-
-          let len = cards.len();
-          if cards.get(len - 1).map(|(path, card)| (path, card.line_number)) == Some((&key.0, key.1)) {
-            cards.swap(len - 1, 0)
-          }
-
-          shown_again.insert(key);
-
-          break;
-        },
-        'n' => {
-          let line_number = card.line_number;
-
-          result.push((
-            path.clone(),
-            card,
-            !shown_again.contains(&(path, line_number))
-          ));
-
-          break;
-        },
-        'f' => break,
-        'e' => {
-          let back = prompt_multiline_with_initial("Back", (&card.back, ""))?;
-          let notes = prompt_multiline_with_initial("Notes", (&card.notes, ""))?;
-
-          card.back = back;
-          card.notes = notes;
-        },
-        _ => unreachable!()
-      }
-    }
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use clap::{App, SubCommand};
+
+  fn format_of(args: &[&str]) -> Format {
+    let matches = App::new("recall")
+      .arg(Format::arg())
+      .subcommand(SubCommand::with_name("info"))
+      .get_matches_from(args);
+
+    matches.subcommand_matches("info")
+      .map(Format::from_matches)
+      .unwrap_or_else(|| Format::from_matches(&matches))
   }
 
-  Ok(result)
+  #[test]
+  fn format_is_a_global_option() {
+    assert_eq!(format_of(&["recall", "info"]), Format::Text);
+    assert_eq!(format_of(&["recall", "--format", "json", "info"]), Format::Json);
+    assert_eq!(format_of(&["recall", "info", "--format", "tsv"]), Format::Tsv);
+  }
 }
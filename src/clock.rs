@@ -0,0 +1,65 @@
+use std::cell::Cell;
+use chrono::{DateTime, Duration, Utc};
+
+/// The source of the current time for scheduling, so that it can be fixed in
+/// tests and moved forward in simulations.
+pub trait Clock {
+  fn now(&self) -> DateTime<Utc>;
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+  fn now(&self) -> DateTime<Utc> {
+    Utc::now()
+  }
+}
+
+/// A clock that stands still until it's moved explicitly.
+#[derive(Debug, Clone)]
+pub struct FixedClock {
+  now: Cell<DateTime<Utc>>
+}
+
+impl FixedClock {
+  pub fn new(now: DateTime<Utc>) -> FixedClock {
+    FixedClock {
+      now: Cell::new(now)
+    }
+  }
+
+  pub fn set(&self, now: DateTime<Utc>) {
+    self.now.set(now);
+  }
+
+  pub fn advance(&self, duration: Duration) {
+    self.now.set(self.now.get() + duration);
+  }
+}
+
+impl Clock for FixedClock {
+  fn now(&self) -> DateTime<Utc> {
+    self.now.get()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::TimeZone;
+  use super::*;
+
+  #[test]
+  fn fixed_clock_moves_only_when_told() {
+    let start = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+    let clock = FixedClock::new(start);
+
+    assert_eq!(clock.now(), start);
+
+    clock.advance(Duration::hours(2));
+    assert_eq!(clock.now(), start + Duration::hours(2));
+
+    clock.set(start);
+    assert_eq!(clock.now(), start);
+  }
+}
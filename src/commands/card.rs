@@ -0,0 +1,197 @@
+use clap::{ArgMatches, App, Arg, SubCommand};
+use super::{RecallError, Result};
+use recall::{card::Card, store::DeckStore};
+use crate::{app, cli};
+
+pub fn subcommand<'a>() -> App<'a, 'static> {
+  let get_list_arg = || Arg::with_name("list").help("Name of the list").required(true);
+  let get_fronts_arg = || {
+    Arg::with_name("fronts")
+    .help("Front of the cards")
+    .multiple(true)
+    .required(true)
+  };
+
+  SubCommand::with_name("card")
+  .about("Manages individual cards")
+  .subcommand(
+    SubCommand::with_name("suspend")
+    .about("Takes cards out of rotation")
+    .arg(get_list_arg())
+    .arg(get_fronts_arg())
+  )
+  .subcommand(
+    SubCommand::with_name("unsuspend")
+    .about("Returns suspended or buried cards to rotation")
+    .arg(get_list_arg())
+    .arg(get_fronts_arg())
+  )
+  .subcommand(
+    SubCommand::with_name("move")
+    .about("Moves a card to another list, keeping its scheduling state")
+    .arg(get_list_arg())
+    .arg(
+      Arg::with_name("front")
+      .help("Front of the card")
+      .required(true)
+    )
+    .arg(
+      Arg::with_name("destination")
+      .help("Name of the list to move the card to")
+      .required(true)
+    )
+  )
+}
+
+pub fn dispatch(matches: &ArgMatches) -> Result {
+  match matches.subcommand() {
+    ("suspend", Some(matches)) => update(matches, "suspended", |card| { card.suspend(); }),
+    ("unsuspend", Some(matches)) => update(matches, "unsuspended", |card| { card.unsuspend(); }),
+    ("move", Some(matches)) => move_card(matches),
+    _ => {
+      println!("{}", matches.usage());
+      Ok(())
+    }
+  }
+}
+
+fn update<F: Fn(&mut Card)>(matches: &ArgMatches, action: &str, f: F) -> Result {
+  let name = matches.value_of("list").unwrap();
+  let fronts = matches.values_of("fronts").unwrap().collect::<Vec<_>>();
+
+  let store = app::store(&app::config(matches)?);
+
+  if !store.deck_exists(name) {
+    return Err(RecallError::ListNotFound(name.to_string()));
+  }
+
+  let mut cards = store.cards(name)?
+    .into_iter()
+    .filter(|card| fronts.contains(&card.front.as_str()))
+    .collect::<Vec<_>>();
+
+  println!();
+
+  for front in fronts.iter() {
+    if cards.iter().any(|card| &card.front == front) {
+      println!("Card '{}' {}.", front, action);
+    } else {
+      cli::print_error_strip(format_args!("Card '{}' does not exist.", front));
+    }
+  }
+
+  println!();
+
+  for card in cards.iter_mut() {
+    f(card);
+  }
+
+  store.update_cards(name, cards)?;
+
+  Ok(())
+}
+
+fn move_card(matches: &ArgMatches) -> Result {
+  let name = matches.value_of("list").unwrap();
+  let front = matches.value_of("front").unwrap();
+  let destination = matches.value_of("destination").unwrap();
+
+  let store = app::store(&app::config(matches)?);
+
+  for list in [name, destination].iter() {
+    if !store.deck_exists(list) {
+      return Err(RecallError::ListNotFound(list.to_string()));
+    }
+  }
+
+  if destination == name {
+    return Err(RecallError::InvalidArgument {
+      name: "destination",
+      value: destination.to_string()
+    });
+  }
+
+  let (mut moved, remaining): (Vec<_>, Vec<_>) = store.cards(name)?
+    .into_iter()
+    .partition(|card| card.front == front);
+
+  let mut card = match moved.pop() {
+    Some(card) if moved.is_empty() => card,
+    Some(_) => return Err(RecallError::other(format!(
+      "List '{}' has several cards '{}', see `{} doctor`.", name, front, app::name()
+    ))),
+    None => return Err(RecallError::CardNotFound {
+      list: name.to_string(),
+      front: front.to_string()
+    })
+  };
+
+  let mut cards = store.cards(destination)?;
+
+  if cards.iter().any(|x| x.front == front) {
+    return Err(RecallError::DuplicateCard {
+      list: destination.to_string(),
+      front: front.to_string()
+    });
+  }
+
+  card.line_number = None;
+  cards.push(card);
+
+  // The card is added before it is removed, so it cannot get lost.
+  store.save_cards(destination, cards)?;
+  store.save_cards(name, remaining)?;
+
+  println!();
+  println!("Card '{}' moved to list {}.", front, destination);
+  println!();
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use recall::store::DirStore;
+
+  fn run(dirname: &std::path::Path, args: &[&str]) -> Result {
+    let mut all_args = vec!["recall", "--deck-dir", dirname.to_str().unwrap(), "card"];
+    all_args.extend_from_slice(args);
+
+    let matches = App::new("recall")
+      .arg(cli::deck_dir_arg())
+      .subcommand(subcommand())
+      .get_matches_from(all_args);
+
+    dispatch(matches.subcommand_matches("card").unwrap())
+  }
+
+  #[test]
+  fn moves_card_with_its_state() {
+    let dirname = std::env::temp_dir().join(format!("recall-test-{}-move", std::process::id()));
+
+    std::fs::create_dir_all(&dirname).unwrap();
+
+    let store = DirStore::new(&dirname);
+    let mut card = Card::new("x".to_string(), String::new(), String::new());
+
+    card.level = 3;
+    store.create_deck("a").unwrap();
+    store.create_deck("b").unwrap();
+    store.save_cards("a", vec![card.clone(), Card::new("y".to_string(), String::new(), String::new())]).unwrap();
+    store.save_cards("b", vec![Card::new("y".to_string(), String::new(), String::new())]).unwrap();
+
+    let moved = run(&dirname, &["move", "a", "x", "b"]);
+    let duplicate = run(&dirname, &["move", "a", "y", "b"]);
+    let missing = run(&dirname, &["move", "a", "x", "b"]);
+    let source_cards = store.cards("a");
+    let destination_cards = store.cards("b");
+
+    std::fs::remove_dir_all(&dirname).ok();
+    assert!(moved.is_ok());
+    assert!(matches!(duplicate, Err(RecallError::DuplicateCard { .. })));
+    assert!(matches!(missing, Err(RecallError::CardNotFound { .. })));
+    assert_eq!(source_cards.unwrap().len(), 1);
+    assert_eq!(destination_cards.unwrap().iter().map(|card| (card.front.as_str(), card.level)).collect::<Vec<_>>(), vec![("y", 0), ("x", 3)]);
+  }
+}
@@ -0,0 +1,385 @@
+use clap::{ArgMatches, App, Arg, SubCommand};
+use colored::Colorize;
+use serde_json::{json, Value};
+use super::{RecallError, Result};
+use recall::{
+  card::Proficiency,
+  config::{find_key, format_value, global_path, local_path, Origin},
+  ladder::{format_interval, Ladder, THRESHOLD_NAMES},
+  store::DeckStore
+};
+use crate::{cli::{self, Format}, app};
+
+pub fn subcommand<'a>() -> App<'a, 'static> {
+  SubCommand::with_name("config")
+  .about("Shows and changes the settings")
+  .long_about(
+    "Shows the scheduler settings, after checking all config files. Without \
+    a list name, the global settings and all lists with settings of their own \
+    are shown. Settings are read from the global config, the local config in \
+    the deck directory, `RECALL_*` environment variables and options, in \
+    increasing order of precedence."
+  )
+  .arg(
+    Arg::with_name("name")
+    .help("Name of the list to show the settings of")
+  )
+  .subcommand(
+    SubCommand::with_name("get")
+    .about("Prints the effective value of a setting")
+    .arg(
+      Arg::with_name("key")
+      .help("Name of the setting, e.g. session.size")
+      .required(true)
+    )
+  )
+  .subcommand(
+    SubCommand::with_name("set")
+    .about("Changes a setting in the local or global config file")
+    .arg(
+      Arg::with_name("key")
+      .help("Name of the setting, e.g. session.size")
+      .required(true)
+    )
+    .arg(
+      Arg::with_name("value")
+      .help("New value, intervals are separated by commas")
+      .required(true)
+    )
+    .arg(
+      Arg::with_name("global")
+      .long("global")
+      .help("Changes the global config instead of the one in the deck directory")
+    )
+  )
+  .subcommand(
+    SubCommand::with_name("list")
+    .about("Shows all settings and where they come from")
+  )
+}
+
+fn toml_to_json(value: &toml::Value) -> Value {
+  match value {
+    toml::Value::String(x) => json!(x),
+    toml::Value::Integer(x) => json!(x),
+    toml::Value::Boolean(x) => json!(x),
+    toml::Value::Array(values) => Value::Array(values.iter().map(toml_to_json).collect()),
+    value => json!(value.to_string())
+  }
+}
+
+fn origin_to_str(origin: &Origin) -> &'static str {
+  match origin {
+    Origin::Default => "default",
+    Origin::Global(_) => "global",
+    Origin::Local(_) => "local",
+    Origin::Env(_) => "env",
+    Origin::Flag(_) => "flag"
+  }
+}
+
+fn check_key(key: &str) -> Result {
+  match find_key(key) {
+    Some(_) => Ok(()),
+    None => Err(RecallError::InvalidArgument {
+      name: "key",
+      value: key.to_string()
+    })
+  }
+}
+
+fn ladder_to_json(ladder: &Ladder) -> Value {
+  json!({
+    "intervals": ladder.intervals().iter()
+      .map(|&x| format_interval(x))
+      .collect::<Vec<_>>(),
+    "penalty": ladder.penalty(),
+    "learning_steps": ladder.learning_steps().iter()
+      .map(|&x| format_interval(x))
+      .collect::<Vec<_>>(),
+    "max_level": ladder.max_level(),
+    "proficiency": THRESHOLD_NAMES.iter()
+      .zip(ladder.thresholds().iter())
+      .map(|(name, threshold)| (name.to_string(), json!(threshold)))
+      .collect::<serde_json::Map<_, _>>()
+  })
+}
+
+fn print_ladder(ladder: &Ladder) {
+  let print_row = |text: String, value: String| {
+    println!("{:>19}:  {}", text, value);
+  };
+
+  let intervals = ladder.intervals().iter()
+    .map(|&x| format_interval(x))
+    .collect::<Vec<_>>();
+
+  print_row("Intervals".to_string(), intervals.join(", "));
+  print_row("Penalty".to_string(), ladder.penalty().to_string());
+  print_row("Max Level".to_string(), ladder.max_level().to_string());
+
+  let learning_steps = ladder.learning_steps().iter()
+    .map(|&x| format_interval(x))
+    .collect::<Vec<_>>();
+
+  print_row(
+    "Learning Steps".to_string(),
+    if learning_steps.is_empty() { "-".to_string() } else { learning_steps.join(", ") }
+  );
+
+  let proficiencies = [
+    Proficiency::Apprentice,
+    Proficiency::Guru,
+    Proficiency::Master,
+    Proficiency::Enlightened
+  ];
+  let mut first_level = 1;
+
+  for (proficiency, &threshold) in proficiencies.iter().zip(ladder.thresholds().iter()) {
+    let levels = match threshold - first_level {
+      x if x < 0 => "-".to_string(),
+      0 => threshold.to_string(),
+      _ => format!("{}-{}", first_level, threshold)
+    };
+
+    print_row(
+      proficiency.colorize(format!("{:>19}", proficiency.to_string())).to_string(),
+      levels
+    );
+    first_level = first_level.max(threshold + 1);
+  }
+
+  let burned = if first_level >= ladder.max_level() {
+    ladder.max_level().to_string()
+  } else {
+    format!("{}-{}", first_level, ladder.max_level())
+  };
+
+  print_row(
+    Proficiency::Burned.colorize(format!("{:>19}", Proficiency::Burned.to_string())).to_string(),
+    burned
+  );
+}
+
+pub fn dispatch(matches: &ArgMatches) -> Result {
+  match matches.subcommand() {
+    ("get", Some(matches)) => get(matches),
+    ("set", Some(matches)) => set(matches),
+    ("list", Some(matches)) => list(matches),
+    _ => show(matches)
+  }
+}
+
+fn get(matches: &ArgMatches) -> Result {
+  let format = Format::from_matches(matches);
+  let key = matches.value_of("key").unwrap();
+  let config = app::config(matches)?;
+
+  check_key(key)?;
+
+  let value = config.get(key);
+  let origin = config.origin(key);
+
+  match format {
+    Format::Json => {
+      cli::print_json(&json!({
+        "key": key,
+        "value": value.as_ref().map(toml_to_json),
+        "origin": origin_to_str(&origin),
+        "source": origin.to_string()
+      }));
+    },
+    Format::Tsv => {
+      cli::print_tsv(vec![vec![
+        key.to_string(),
+        value.as_ref().map(format_value).unwrap_or_default(),
+        origin_to_str(&origin).to_string()
+      ]]);
+    },
+    Format::Text => {
+      if let Some(value) = value {
+        println!("{}", format_value(&value));
+      }
+    }
+  }
+
+  Ok(())
+}
+
+fn set(matches: &ArgMatches) -> Result {
+  let key = matches.value_of("key").unwrap();
+  let value = matches.value_of("value").unwrap();
+  let config = app::config(matches)?;
+
+  check_key(key)?;
+
+  let origin = if matches.is_present("global") {
+    Origin::Global(
+      global_path()
+      .ok_or_else(|| RecallError::other("Cannot find the global config directory."))?
+    )
+  } else {
+    Origin::Local(local_path(config.deck_dir()))
+  };
+
+  config.set(origin.clone(), key, value)?;
+
+  println!();
+  println!("Set {} to '{}' in {}.", cli::inline_code(key), value, origin);
+
+  let effective = app::config(matches)?.origin(key);
+
+  if effective != origin {
+    println!();
+    cli::print_help_strip(format_args!("The setting is overridden by {}", effective));
+  }
+
+  println!();
+  Ok(())
+}
+
+fn list(matches: &ArgMatches) -> Result {
+  let format = Format::from_matches(matches);
+  let config = app::config(matches)?;
+  let rows = config.keys().into_iter()
+    .map(|key| {
+      let value = config.get(&key);
+      let origin = config.origin(&key);
+      (key, value, origin)
+    })
+    .collect::<Vec<_>>();
+
+  match format {
+    Format::Json => {
+      cli::print_json(&Value::Array(
+        rows.iter()
+        .map(|(key, value, origin)| json!({
+          "key": key,
+          "value": value.as_ref().map(toml_to_json),
+          "origin": origin_to_str(origin),
+          "source": origin.to_string()
+        }))
+        .collect()
+      ));
+    },
+    Format::Tsv => {
+      cli::print_tsv(
+        rows.iter()
+        .map(|(key, value, origin)| vec![
+          key.clone(),
+          value.as_ref().map(format_value).unwrap_or_default(),
+          origin_to_str(origin).to_string()
+        ])
+      );
+    },
+    Format::Text => {
+      let width = rows.iter().map(|(key, _, _)| key.len()).max().unwrap_or(0);
+
+      println!();
+      cli::print_header_strip("Settings");
+      println!();
+
+      for (key, value, origin) in rows.iter() {
+        let value = match value {
+          Some(value) => format_value(value),
+          None => "-".bright_black().to_string()
+        };
+
+        println!(
+          "{:<width$}  {}  {}",
+          key,
+          value,
+          format!("({})", origin).bright_black(),
+          width = width
+        );
+      }
+
+      println!();
+    }
+  }
+
+  Ok(())
+}
+
+fn show(matches: &ArgMatches) -> Result {
+  let format = Format::from_matches(matches);
+  let name = matches.value_of("name");
+  let config = app::config(matches)?;
+  let files = config.layers().iter()
+    .filter_map(|layer| match layer.origin() {
+      Origin::Global(path) | Origin::Local(path) => Some(path),
+      _ => None
+    })
+    .collect::<Vec<_>>();
+
+  if let Some(name) = name {
+    if !app::store(&config).deck_exists(name) {
+      return Err(RecallError::ListNotFound(name.to_string()));
+    }
+  }
+
+  let ladders = match name {
+    Some(name) => vec![(Some(name), config.ladder(name))],
+    None => {
+      Some((None, config.scheduler())).into_iter()
+      .chain(config.lists().map(|(name, ladder)| (Some(name), ladder)))
+      .collect()
+    }
+  };
+
+  match format {
+    Format::Json => {
+      cli::print_json(&json!({
+        "files": files.iter().map(|path| path.display().to_string()).collect::<Vec<_>>(),
+        "scheduler": ladder_to_json(config.scheduler()),
+        "lists": config.lists()
+          .filter(|(list, _)| name.map(|name| name == *list).unwrap_or(true))
+          .map(|(list, ladder)| (list.to_string(), ladder_to_json(ladder)))
+          .collect::<serde_json::Map<_, _>>()
+      }));
+
+      return Ok(());
+    },
+    Format::Tsv => {
+      cli::print_tsv(
+        ladders.iter()
+        .map(|(list, ladder)| vec![
+          list.unwrap_or("").to_string(),
+          ladder.to_string(),
+          ladder.penalty().to_string(),
+          ladder.max_level().to_string(),
+          ladder.thresholds().iter().map(|x| x.to_string()).collect::<Vec<_>>().join(",")
+        ])
+      );
+
+      return Ok(());
+    },
+    Format::Text => {}
+  }
+
+  println!();
+  cli::print_header_strip("Config");
+  println!();
+
+  if files.is_empty() {
+    println!("No config file, using defaults");
+  } else {
+    println!("Files:");
+    cli::print_bullet_list(files.iter().map(|path| path.display()));
+  }
+
+  for (list, ladder) in ladders {
+    println!();
+
+    match list {
+      Some(list) => println!("List '{}'", list),
+      None => println!("All lists")
+    }
+
+    println!();
+    print_ladder(ladder);
+  }
+
+  println!();
+  Ok(())
+}
@@ -0,0 +1,232 @@
+use std::collections::{BTreeMap, HashSet};
+use clap::{ArgMatches, App, Arg, SubCommand};
+use colored::Colorize;
+use serde_json::{json, Value};
+use super::{RecallError, Result};
+use recall::{
+  card::{get_cards, Card},
+  config::Config,
+  dedupe::{find_duplicates, MAX_DISTANCE},
+  store::DeckStore
+};
+use crate::{cli::{self, Format}, app};
+
+pub fn subcommand<'a>() -> App<'a, 'static> {
+  SubCommand::with_name("dedupe")
+  .about("Finds and merges duplicate cards across lists")
+  .long_about(
+    "Finds cards with the same front, fronts that only differ in case, \
+    whitespace or a few characters, and cards with the same back. For every \
+    pair, choose which card to keep. The kept card takes over the scheduling \
+    state of the card with more progress, the other one is removed."
+  )
+  .arg(
+    Arg::with_name("names")
+    .help("Name of the lists to check")
+    .multiple(true)
+  )
+  .arg(
+    Arg::with_name("max_distance")
+    .long("max-distance")
+    .help("Number of differing characters up to which fronts are similar, at most 2 or 0 to disable")
+    .takes_value(true)
+    .default_value("2")
+  )
+  .arg(
+    Arg::with_name("list")
+    .long("list")
+    .help("Only shows the duplicates, as do the json and tsv formats")
+  )
+  .arg(cli::batch_arg())
+}
+
+fn card_to_json(deck: &str, card: &Card) -> Value {
+  json!({
+    "list": deck,
+    "front": card.front,
+    "back": card.back,
+    "level": card.level,
+    "reviews": card.total_count
+  })
+}
+
+fn describe(config: &Config, deck: &str, card: &Card) -> String {
+  let proficiency = card.proficiency(config.ladder(deck));
+
+  format!(
+    "{}: {} {} {}  ({}, {} review(s))",
+    deck,
+    card.front,
+    "→".bright_black(),
+    card.back.replace('\n', " "),
+    proficiency.colorize(proficiency.to_string()),
+    card.total_count
+  )
+}
+
+pub fn dispatch(matches: &ArgMatches) -> Result {
+  let format = Format::from_matches(matches);
+  let config = app::config(matches)?;
+  let store = app::store(&config);
+  let mut term = cli::terminal_from_matches(matches);
+  let names = matches.values_of("names")
+    .map(|names| names.collect::<Vec<_>>());
+  let max_distance_arg = matches.value_of("max_distance").unwrap();
+  let max_distance = max_distance_arg.parse::<usize>().ok()
+    .filter(|&x| x <= MAX_DISTANCE)
+    .ok_or_else(|| RecallError::InvalidArgument {
+      name: "max-distance",
+      value: max_distance_arg.to_string()
+    })?;
+
+  if let Some(name) = names.as_ref().and_then(|names| names.iter().find(|name| !store.deck_exists(name))) {
+    return Err(RecallError::ListNotFound(name.to_string()));
+  }
+
+  let mut cards = get_cards(
+    &store,
+    store.decks()?
+    .into_iter()
+    .filter(|deck| {
+      names.as_ref()
+      .map(|names| names.contains(&deck.as_str()))
+      .unwrap_or(true)
+    })
+  );
+  let duplicates = find_duplicates(&cards, max_distance)?;
+
+  match format {
+    Format::Json => {
+      cli::print_json(&Value::Array(
+        duplicates.iter()
+        .map(|&(i, j, reason)| json!({
+          "reason": reason.to_string(),
+          "cards": [card_to_json(&cards[i].0, &cards[i].1), card_to_json(&cards[j].0, &cards[j].1)]
+        }))
+        .collect()
+      ));
+
+      return Ok(());
+    },
+    Format::Tsv => {
+      cli::print_tsv(
+        duplicates.iter()
+        .map(|&(i, j, reason)| vec![
+          reason.to_string(),
+          cards[i].0.clone(),
+          cards[i].1.front.clone(),
+          cards[j].0.clone(),
+          cards[j].1.front.clone()
+        ])
+      );
+
+      return Ok(());
+    },
+    Format::Text => {}
+  }
+
+  println!();
+  cli::print_header_strip("Duplicates");
+  println!();
+
+  if duplicates.is_empty() {
+    println!("No duplicates found.");
+    println!();
+
+    return Ok(());
+  }
+
+  if matches.is_present("list") {
+    for &(i, j, reason) in duplicates.iter() {
+      println!("{}", reason.to_string().bright_white());
+      cli::print_bullet_list(vec![
+        describe(&config, &cards[i].0, &cards[i].1),
+        describe(&config, &cards[j].0, &cards[j].1)
+      ]);
+      println!();
+    }
+
+    return Ok(());
+  }
+
+  let mut removed = HashSet::new();
+  let mut changed = HashSet::new();
+
+  for (n, &(i, j, reason)) in duplicates.iter().enumerate() {
+    if removed.contains(&i) || removed.contains(&j) {
+      continue;
+    }
+
+    term.println(&format!(
+      "{} - {}",
+      format!("{}/{}", n + 1, duplicates.len()).green(),
+      reason
+    ));
+    term.println("");
+    term.println(&format!("  1  {}", describe(&config, &cards[i].0, &cards[i].1)));
+    term.println(&format!("  2  {}", describe(&config, &cards[j].0, &cards[j].1)));
+    term.println("");
+
+    let choice = term.prompt_multiple_choice(
+      &format!(
+        "keep {}, keep {}, keep {}oth - {}inish",
+        "1".bold().cyan().underline(),
+        "2".bold().cyan().underline(),
+        "b".bold().cyan().underline(),
+        "f".bold().cyan().underline()
+      ),
+      &['1', '2', 'b', 'f']
+    )?;
+
+    term.println("");
+
+    let (kept, other) = match choice {
+      Some('1') => (i, j),
+      Some('2') => (j, i),
+      Some('b') => continue,
+      _ => break
+    };
+
+    if cards[other].1.has_more_progress(&cards[kept].1) {
+      let schedule = cards[other].1.clone();
+      cards[kept].1.copy_schedule(&schedule);
+    }
+
+    removed.insert(other);
+    changed.insert(kept);
+  }
+
+  // Apply all decisions per list, matching cards by line number.
+
+  let mut decks = BTreeMap::<&str, (Vec<usize>, Vec<&Card>)>::new();
+
+  for &index in removed.iter() {
+    let (deck, card) = &cards[index];
+    decks.entry(deck).or_default().0.extend(card.line_number);
+  }
+
+  for &index in changed.iter().filter(|index| !removed.contains(index)) {
+    let (deck, card) = &cards[index];
+    decks.entry(deck).or_default().1.push(card);
+  }
+
+  for (deck, (removed_lines, changed_cards)) in decks.iter() {
+    let deck_cards = store.cards(deck)?
+      .into_iter()
+      .filter(|card| !card.line_number.map(|x| removed_lines.contains(&x)).unwrap_or(false))
+      .map(|card| {
+        changed_cards.iter()
+        .find(|x| x.line_number.is_some() && x.line_number == card.line_number)
+        .map(|&x| x.clone())
+        .unwrap_or(card)
+      })
+      .collect();
+
+    store.save_cards(deck, deck_cards)?;
+  }
+
+  println!("Removed {} duplicate(s).", removed.len());
+  println!();
+
+  Ok(())
+}
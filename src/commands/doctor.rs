@@ -0,0 +1,114 @@
+use clap::{ArgMatches, App, Arg, SubCommand};
+use colored::Colorize;
+use serde_json::json;
+use super::{RecallError, Result};
+use recall::{doctor::diagnose, list::get_lists, store::DeckStore};
+use crate::{app, cli::{self, Format}};
+
+pub fn subcommand<'a>() -> App<'a, 'static> {
+  SubCommand::with_name("doctor")
+  .about("Reports malformed lines and impossible values in lists")
+  .arg(
+    Arg::with_name("names")
+    .help("Name of the lists to check")
+    .multiple(true)
+  )
+}
+
+pub fn dispatch(matches: &ArgMatches) -> Result {
+  let format = Format::from_matches(matches);
+  let config = app::config(matches)?;
+  let store = app::store(&config);
+  let names = matches.values_of("names")
+    .map(|names| names.collect::<Vec<_>>());
+  let invalid_name = names.as_ref()
+    .and_then(|names| names.iter().find(|name| !store.deck_exists(name)));
+
+  if let Some(name) = invalid_name {
+    return Err(RecallError::ListNotFound(name.to_string()));
+  }
+
+  let mut lists = get_lists(store.dirname())?
+    .filter(|list| {
+      names.as_ref()
+      .map(|names| names.contains(&list.name()))
+      .unwrap_or(true)
+    })
+    .collect::<Vec<_>>();
+
+  lists.sort_by(|x, y| x.name().cmp(y.name()));
+
+  let mut reports = Vec::new();
+
+  for list in lists {
+    let problems = diagnose(&list, config.ladder(list.name()))?;
+
+    if !problems.is_empty() {
+      reports.push((list, problems));
+    }
+  }
+
+  match format {
+    Format::Json => {
+      cli::print_json(&json!(
+        reports.iter()
+        .flat_map(|(list, problems)| {
+          problems.iter().map(move |problem| json!({
+            "list": list.name(),
+            "path": list.path(),
+            "line": problem.line_number,
+            "message": problem.message
+          }))
+        })
+        .collect::<Vec<_>>()
+      ));
+
+      return Ok(());
+    },
+    Format::Tsv => {
+      cli::print_tsv(
+        reports.iter()
+        .flat_map(|(list, problems)| {
+          problems.iter().map(move |problem| vec![
+            list.name().to_string(),
+            problem.line_number.to_string(),
+            problem.message.clone()
+          ])
+        })
+      );
+
+      return Ok(());
+    },
+    Format::Text => {}
+  }
+
+  println!();
+  cli::print_header_strip("Doctor");
+  println!();
+
+  if reports.is_empty() {
+    println!("No problems found.");
+    println!();
+    return Ok(());
+  }
+
+  for (list, problems) in reports.iter() {
+    println!("{}", list.name().bold());
+    cli::print_bullet_list(
+      problems.iter()
+      .map(|problem| format!("Line {}: {}", problem.line_number, problem.message))
+    );
+    println!();
+  }
+
+  println!(
+    "Found {} problem(s) in {} list(s).",
+    reports.iter().map(|(_, problems)| problems.len()).sum::<usize>(),
+    reports.len()
+  );
+  println!();
+  cli::print_help_strip("Malformed lines are kept as they are until you fix them.");
+  println!();
+
+  Ok(())
+}
@@ -1,14 +1,11 @@
 use std::time::Duration as StdDuration;
 use clap::{ArgMatches, SubCommand, App, Arg};
-use chrono::{Utc, Duration};
+use chrono::Duration;
 use humantime::format_duration;
+use serde_json::{json, Map};
 use super::{RecallError, Result};
-use crate::{
-  card::{get_cards, list_proficiencies},
-  list::{list_exists, get_lists},
-  cli,
-  app
-};
+use recall::{card::{get_cards, list_proficiencies}, store::DeckStore};
+use crate::{cli::{self, Format}, app};
 
 pub fn subcommand<'a>() -> App<'a, 'static> {
   SubCommand::with_name("info")
@@ -21,18 +18,23 @@ pub fn subcommand<'a>() -> App<'a, 'static> {
 }
 
 pub fn dispatch(matches: &ArgMatches) -> Result {
+  let format = Format::from_matches(matches);
+  let config = app::config(matches)?;
+  let store = app::store(&config);
+  let now = cli::clock_from_matches(matches)?.now();
   let names = matches.values_of("names")
     .map(|names| names.collect::<Vec<_>>());
   let names_args = names.as_ref()
     .map(|names| " ".to_string() + &names.join(" "))
-    .unwrap_or_else(|| String::new());
-  let has_invalid_names = names.as_ref()
-    .map(|names| names.iter().any(|name| !list_exists(".", name)))
-    .unwrap_or(false);
+    .unwrap_or_default();
+  let invalid_name = names.as_ref()
+    .and_then(|names| names.iter().find(|name| !store.deck_exists(name)));
+
+  if let Some(name) = invalid_name {
+    if format != Format::Text {
+      return Err(RecallError::ListNotFound(name.to_string()));
+    }
 
-  if has_invalid_names {
-    println!();
-    println!("List not found.");
     println!();
 
     cli::print_help_strip(
@@ -49,50 +51,33 @@ pub fn dispatch(matches: &ArgMatches) -> Result {
       )
     );
 
-    println!();
-    return Ok(());
+    return Err(RecallError::ListNotFound(name.to_string()));
   }
 
   let cards = get_cards(
-    get_lists(".")
-    .map_err(|_| RecallError::new("Cannot read lists."))?
-    .filter(|list| {
+    &store,
+    store.decks()?
+    .into_iter()
+    .filter(|deck| {
       names.as_ref()
-      .map(|names| names.contains(&list.name()))
+      .map(|names| names.contains(&deck.as_str()))
       .unwrap_or(true)
     })
   )
-  .map(|(_, card)| card)
+  .into_iter()
+  .map(|(deck, card)| (config.ladder(&deck), card))
   .collect::<Vec<_>>();
-
-  println!();
-  cli::print_header_strip("Info");
+  let (ladders, cards): (Vec<_>, Vec<_>) = cards.into_iter().unzip();
 
   let total_count = cards.len();
-
-  if total_count == 0 {
-    println!();
-    println!("No cards found.");
-    println!();
-
-    cli::print_help_strip(
-      format_args!(
-        "Execute {} to append new card to an existing list.",
-        cli::inline_code(format_args!("{} list append <name>", app::name()))
-      )
-    );
-
-    println!();
-    return Ok(());
-  }
-
   let critical_count = cards.iter()
-    .filter(|card| card.critical())
+    .zip(ladders.iter())
+    .filter(|(card, ladder)| card.critical(ladder))
     .count();
   let has_correctness_cards = cards.iter()
     .filter_map(|card| card.correctness())
     .collect::<Vec<_>>();
-  let average_correctness = if has_correctness_cards.len() == 0 {
+  let average_correctness = if has_correctness_cards.is_empty() {
     None
   } else {
     Some(has_correctness_cards.iter().sum::<f64>()
@@ -100,17 +85,22 @@ pub fn dispatch(matches: &ArgMatches) -> Result {
   };
 
   let due_time = cards.iter()
-    .filter_map(|card| card.due_time)
+    .filter(|card| !card.suspended)
+    .filter_map(|card| {
+      card.due_time
+      .map(|x| card.buried_until.map(|y| x.max(y)).unwrap_or(x))
+    })
     .min();
-  let due_count = cards.iter().filter(|card| card.is_due()).count();
+  let suspended_count = cards.iter().filter(|card| card.suspended).count();
+  let due_count = cards.iter().filter(|card| card.is_due_at(now)).count();
   let due_next_hour_count = cards.iter()
     .filter(|card| {
-      card.is_due_at(Utc::now() + Duration::hours(1))
+      card.is_due_at(now + Duration::hours(1))
     })
     .count();
   let due_tomorrow_count = cards.iter()
     .filter(|card| {
-      card.is_due_at(Utc::now() + Duration::days(1))
+      card.is_due_at(now + Duration::days(1))
     })
     .count();
 
@@ -118,13 +108,80 @@ pub fn dispatch(matches: &ArgMatches) -> Result {
     .map(|proficiency| (
       proficiency,
       cards.iter()
-        .filter(|card| &card.proficiency() == &proficiency)
+        .zip(ladders.iter())
+        .filter(|(card, ladder)| card.proficiency(ladder) == proficiency)
         .count()
     ))
     .collect::<Vec<_>>();
 
   let inactive_count = count_by_proficiencies[0].1;
 
+  match format {
+    Format::Json => {
+      cli::print_json(&json!({
+        "lists": names,
+        "total": total_count,
+        "due_now": due_count,
+        "due_next_hour": due_next_hour_count,
+        "due_tomorrow": due_tomorrow_count,
+        "next_review": due_time.map(|x| x.to_rfc3339()),
+        "criticals": critical_count,
+        "suspended": suspended_count,
+        "average_correctness": average_correctness,
+        "proficiencies": count_by_proficiencies.iter()
+          .map(|(proficiency, count)| (proficiency.to_string().to_lowercase(), json!(count)))
+          .collect::<Map<_, _>>()
+      }));
+
+      return Ok(());
+    },
+    Format::Tsv => {
+      cli::print_tsv(
+        vec![
+          ("total".to_string(), total_count.to_string()),
+          ("due_now".to_string(), due_count.to_string()),
+          ("due_next_hour".to_string(), due_next_hour_count.to_string()),
+          ("due_tomorrow".to_string(), due_tomorrow_count.to_string()),
+          ("next_review".to_string(), due_time.map(|x| x.to_rfc3339()).unwrap_or_default()),
+          ("criticals".to_string(), critical_count.to_string()),
+          ("suspended".to_string(), suspended_count.to_string()),
+          (
+            "average_correctness".to_string(),
+            average_correctness.map(|x| x.to_string()).unwrap_or_default()
+          )
+        ]
+        .into_iter()
+        .chain(
+          count_by_proficiencies.iter()
+          .map(|(proficiency, count)| (proficiency.to_string().to_lowercase(), count.to_string()))
+        )
+        .map(|(key, value)| vec![key, value])
+      );
+
+      return Ok(());
+    },
+    Format::Text => {}
+  }
+
+  println!();
+  cli::print_header_strip("Info");
+
+  if total_count == 0 {
+    println!();
+    println!("No cards found.");
+    println!();
+
+    cli::print_help_strip(
+      format_args!(
+        "Execute {} to append new card to an existing list.",
+        cli::inline_code(format_args!("{} list append <name>", app::name()))
+      )
+    );
+
+    println!();
+    return Ok(());
+  }
+
   let col1_width = 19;
   let col2_width = count_by_proficiencies.iter()
     .map(|(_, count)| count.to_string().len())
@@ -155,7 +212,7 @@ pub fn dispatch(matches: &ArgMatches) -> Result {
     print_row(
       "Next Review In".to_string(),
       due_time
-        .and_then(|x| Duration::to_std(&(x - Utc::now())).ok())
+        .and_then(|x| Duration::to_std(&(x - now)).ok())
         .map(|duration| StdDuration::new(duration.as_secs(), 0))
         .map(|duration| format_duration(duration).to_string())
         .unwrap_or("-".to_string())
@@ -171,6 +228,8 @@ pub fn dispatch(matches: &ArgMatches) -> Result {
   println!();
   print_row("Criticals".to_string(), critical_count.to_string());
   println!();
+  print_row("Suspended".to_string(), suspended_count.to_string());
+  println!();
   print_row(
     "Average Correctness".to_string(),
     average_correctness
@@ -1,7 +1,8 @@
 use clap::{ArgMatches, App, Arg, SubCommand};
 use rand::seq::SliceRandom;
 use super::{RecallError, Result};
-use crate::{cli, list::{get_lists, list_exists}, app, card::{Proficiency, get_cards, update_cards}};
+use recall::{session, store::DeckStore, card::{Outcome, get_cards}};
+use crate::{cli, app};
 
 pub fn subcommand<'a>() -> App<'a, 'static> {
   SubCommand::with_name("learn")
@@ -11,16 +12,23 @@ pub fn subcommand<'a>() -> App<'a, 'static> {
     .help("Name of the lists to learn")
     .multiple(true)
   )
+  .arg(cli::batch_arg())
   .arg(
     Arg::with_name("count")
     .short("c")
     .long("count")
-    .help("The maximum number of new cards you want to learn")
+    .help("The maximum number of new cards you want to learn, overriding session.size")
     .takes_value(true)
   )
 }
 
 pub fn dispatch(matches: &ArgMatches) -> Result {
+  let config = app::config(matches)?;
+  let store = app::store(&config);
+  let clock = cli::clock_from_matches(matches)?;
+  let dry_run = cli::is_dry_run(matches);
+  let mut rng = cli::rng_from_matches(matches)?;
+  let mut term = cli::terminal_from_matches(matches);
   let names = matches.values_of("names")
     .map(|names| names.collect::<Vec<_>>());
   let count = Ok(matches.value_of("count"))
@@ -28,19 +36,19 @@ pub fn dispatch(matches: &ArgMatches) -> Result {
       count
       .map(|x| {
         x.parse::<usize>()
-        .map(|x| Some(x))
-        .map_err(|_| RecallError::new("Could not parse `count` option."))
+        .map(Some)
+        .map_err(|_| RecallError::InvalidArgument {
+          name: "count",
+          value: x.to_string()
+        })
       })
       .unwrap_or(Ok(None))
     })?;
 
-  let has_invalid_names = names.as_ref()
-    .map(|names| names.iter().any(|name| !list_exists(".", name)))
-    .unwrap_or(false);
+  let invalid_name = names.as_ref()
+    .and_then(|names| names.iter().find(|name| !store.deck_exists(name)));
 
-  if has_invalid_names {
-    println!();
-    println!("List not found.");
+  if let Some(name) = invalid_name {
     println!();
 
     cli::print_help_strip(
@@ -57,49 +65,80 @@ pub fn dispatch(matches: &ArgMatches) -> Result {
       )
     );
 
-    println!();
-    return Ok(());
+    return Err(RecallError::ListNotFound(name.to_string()));
   }
 
-  let cards = {
-    let mut cards = get_cards(
-      get_lists(".")
-      .map_err(|_| RecallError::new("Cannot read lists."))?
-      .filter(|list| {
-        names.as_ref()
-        .map(|names| names.contains(&list.name()))
-        .unwrap_or(true)
-      })
-    )
-    .filter(|(_, card)| card.proficiency() == Proficiency::Inactive)
-    .collect::<Vec<_>>();
+  println!();
+  cli::print_header_strip("Learning");
 
-    let mut rng = rand::thread_rng();
-    cards.shuffle(&mut rng);
+  let mut limit_reached = false;
+  let checkpoint = if dry_run {
+    None
+  } else {
+    session::prompt_resume(term.as_mut(), &store, &config, store.dirname(), "learn", clock.now())?
+  };
+  let (cards, results) = match checkpoint {
+    Some(checkpoint) => (checkpoint.queue, checkpoint.results),
+    None => {
+      let mut cards = get_cards(
+        &store,
+        store.decks()?
+        .into_iter()
+        .filter(|deck| {
+          names.as_ref()
+          .map(|names| names.contains(&deck.as_str()))
+          .unwrap_or(true)
+        })
+      )
+      .into_iter()
+      .filter(|(deck, card)| card.is_new(config.ladder(deck)))
+      .filter(|(_, card)| card.is_available_at(clock.now()))
+      .collect::<Vec<_>>();
 
-    if let Some(count) = count {
-      cards.truncate(count);
-    }
+      cards.shuffle(&mut rng);
 
-    cards
-  };
+      let counts = session::load_daily_counts(store.dirname(), clock.now())?;
+      let remaining = session::remaining(config.new_per_day(), counts.learned);
 
-  println!();
-  cli::print_header_strip("Learning");
+      limit_reached = remaining == Some(0) && !cards.is_empty();
 
-  if cards.len() > 0 {
+      if let Some(limit) = remaining.into_iter().chain(count.or_else(|| config.session_size())).min() {
+        cards.truncate(limit);
+      }
+
+      (cards, Vec::new())
+    }
+  };
+
+  if !cards.is_empty() || !results.is_empty() {
+    let checkpoint_dir = if dry_run { None } else { Some((store.dirname(), "learn")) };
     println!();
-    let mut cards = cli::loop_cards(cards)?;
+    let cards = session::loop_cards(term.as_mut(), &store, &config, checkpoint_dir, cards, results, clock.as_ref(), &mut rng)?;
+    let learned_count = cards.iter()
+      .filter(|&&(_, _, outcome)| outcome == Outcome::Remembered || outcome == Outcome::Forgotten)
+      .filter(|(deck, card, _)| card.is_new(config.ladder(deck)))
+      .count();
     println!();
-    println!("Learned {} new card(s).", cards.len());
+    println!("Learned {} new card(s).", learned_count);
     println!();
 
-    for &mut (_, ref mut card, remembered) in cards.iter_mut() {
-      card.review(remembered);
+    if dry_run {
+      cli::print_help_strip("Dry run at the time given by --now, no answers were saved.");
+      println!();
+    } else {
+      session::commit(&store, &config, store.dirname(), "learn", cards, clock.now())?;
     }
-
-    update_cards(cards.into_iter().map(|(path, card, _)| (path, card)))
-    .map_err(|_| RecallError::new("Updating cards failed."))?;
+  } else if limit_reached {
+    println!();
+    println!("Daily limit of {} new card(s) reached.", config.new_per_day().unwrap_or(0));
+    println!();
+    cli::print_help_strip(
+      format_args!(
+        "Execute {} to change the limit",
+        cli::inline_code(format_args!("{} config set limits.new_per_day <count>", app::name()))
+      )
+    );
+    println!();
   } else {
     println!();
     println!("No new cards to learn.");
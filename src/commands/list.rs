@@ -1,12 +1,25 @@
 use clap::{ArgMatches, App, Arg, SubCommand};
 use colored::Colorize;
 use super::{RecallError, Result};
-use crate::{app, cli};
-use crate::list::{List, get_lists, list_exists};
-use crate::card::Card;
+use std::io::{stdin, Read};
+use serde_json::{json, Value};
+use crate::{app, cli::{self, Format}};
+use recall::list::{Backend, List};
+use recall::card::Card;
+use recall::config::Config;
+use recall::editor;
+use recall::trash;
+use recall::storage::Storage;
+use recall::store::{check_name, DeckStore, DirStore};
+use recall::jsonl::JsonlFile;
 
 pub fn subcommand<'a>() -> App<'a, 'static> {
   let get_name_arg = || Arg::with_name("name").help("Name of the list");
+  let get_backend_arg = |name| {
+    Arg::with_name(name)
+    .possible_values(&["jsonl", "sqlite"])
+    .takes_value(true)
+  };
 
   SubCommand::with_name("list")
   .about("Shows and manages lists")
@@ -17,16 +30,33 @@ pub fn subcommand<'a>() -> App<'a, 'static> {
       get_name_arg()
       .required(true)
     )
+    .arg(
+      get_backend_arg("backend")
+      .long("backend")
+      .default_value("jsonl")
+      .help("Storage format of the list")
+    )
   )
   .subcommand(
     SubCommand::with_name("remove")
-    .about("Removes existing lists")
+    .about("Moves lists to the trash")
     .arg(
       Arg::with_name("names")
       .help("Name of the lists to remove")
       .multiple(true)
       .required(true)
     )
+    .arg(cli::yes_arg())
+  )
+  .subcommand(
+    SubCommand::with_name("restore")
+    .about("Restores lists from the trash")
+    .arg(
+      Arg::with_name("names")
+      .help("Name of the lists to restore")
+      .multiple(true)
+      .required(true)
+    )
   )
   .subcommand(
     SubCommand::with_name("append")
@@ -35,30 +65,158 @@ pub fn subcommand<'a>() -> App<'a, 'static> {
       get_name_arg()
       .required(true)
     )
+    .arg(
+      Arg::with_name("editor")
+      .long("editor")
+      .help("Writes any number of cards in the editor instead of prompting for one")
+      .conflicts_with_all(&["front", "stdin"])
+    )
+    .arg(
+      Arg::with_name("front")
+      .long("front")
+      .help("Appends a card with this front instead of prompting for one")
+      .takes_value(true)
+      .conflicts_with("stdin")
+    )
+    .arg(
+      Arg::with_name("back")
+      .long("back")
+      .help("Back of the card given with --front")
+      .takes_value(true)
+      .requires("front")
+    )
+    .arg(
+      Arg::with_name("notes")
+      .long("notes")
+      .help("Notes of the card given with --front")
+      .takes_value(true)
+      .requires("front")
+    )
+    .arg(
+      Arg::with_name("stdin")
+      .long("stdin")
+      .value_name("format")
+      .help("Reads cards from stdin, as JSON objects or TSV rows of front, back and notes")
+      .takes_value(true)
+      .possible_values(&["json", "tsv"])
+    )
+  )
+  .subcommand(
+    SubCommand::with_name("rename")
+    .about("Renames a list")
+    .arg(
+      get_name_arg()
+      .required(true)
+    )
+    .arg(
+      Arg::with_name("new_name")
+      .help("New name of the list")
+      .required(true)
+    )
+  )
+  .subcommand(
+    SubCommand::with_name("merge")
+    .about("Moves all cards of some lists into another one")
+    .long_about(
+      "Moves all cards of the source lists into the destination list, which is \
+      created if it does not exist, and moves the source lists to the trash. \
+      Cards keep their scheduling state. Of cards with the same front, the one \
+      in the destination is kept by default."
+    )
+    .arg(
+      Arg::with_name("names")
+      .help("Name of the source lists followed by the destination list")
+      .multiple(true)
+      .min_values(2)
+      .required(true)
+    )
+    .arg(
+      Arg::with_name("duplicates")
+      .long("duplicates")
+      .help("Which card to keep of cards with the same front")
+      .long_help(
+        "Which card to keep of cards with the same front: the one already in \
+        the destination (keep), the one being moved (replace) or the one \
+        with more progress (best). A kept destination card takes over the \
+        progress of the moved one if it has more."
+      )
+      .takes_value(true)
+      .possible_values(&["keep", "replace", "best"])
+      .default_value("keep")
+    )
+  )
+  .subcommand(
+    SubCommand::with_name("split")
+    .about("Moves cards into new lists by tag or proficiency")
+    .long_about(
+      "Moves cards into new lists named <name>-<tag> or <name>-<proficiency>. \
+      Tags are #hashtags in the notes, cards are moved by their first tag and \
+      cards without tags stay. Slashes in tags become dashes in list names. \
+      The list is moved to the trash if no cards stay. Cards keep their \
+      scheduling state."
+    )
+    .arg(
+      get_name_arg()
+      .required(true)
+    )
+    .arg(
+      Arg::with_name("by")
+      .long("by")
+      .help("What to split the list by")
+      .takes_value(true)
+      .possible_values(&["tag", "proficiency"])
+      .required(true)
+    )
+  )
+  .subcommand(
+    SubCommand::with_name("convert")
+    .about("Converts a list to another storage format")
+    .arg(
+      get_name_arg()
+      .required(true)
+    )
+    .arg(
+      get_backend_arg("to")
+      .long("to")
+      .required(true)
+      .help("Storage format to convert to")
+    )
   )
 }
 
+fn parse_backend(value: &str) -> Backend {
+  Backend::from_extension(value).unwrap_or(Backend::Jsonl)
+}
+
+fn new_list(store: &DirStore, name: &str, backend: Backend) -> recall::error::Result<List> {
+  if !backend.is_supported() {
+    return Err(RecallError::other("SQLite support is not enabled."));
+  }
+
+  List::new(store.dirname().join(format!("{}.{}", name, backend.extension())))
+    .ok_or_else(|| RecallError::other("List initialization failed."))
+}
+
 pub fn dispatch(matches: &ArgMatches) -> Result {
   match matches.subcommand() {
     ("add", Some(matches)) => add(matches),
     ("remove", Some(matches)) => remove(matches),
+    ("restore", Some(matches)) => restore(matches),
     ("append", Some(matches)) => append(matches),
-    ("", _) => list(),
+    ("convert", Some(matches)) => convert(matches),
+    ("rename", Some(matches)) => rename(matches),
+    ("merge", Some(matches)) => merge(matches),
+    ("split", Some(matches)) => split(matches),
+    ("", _) => list(matches),
     _ => unimplemented!()
   }
 }
 
 fn add(matches: &ArgMatches) -> Result {
   let name = matches.value_of("name").unwrap();
+  let backend = parse_backend(matches.value_of("backend").unwrap());
 
-  if list_exists(".", name) {
-    return Err(RecallError::new("List already exists."));
-  }
-
-  let list = List::new(format!("./{}.jsonl", name))
-    .ok_or(RecallError::new("List initialization failed."))?;
-  list.save_cards(vec![])
-    .map_err(|_| RecallError::new("Add list failed."))?;
+  app::store(&app::config(matches)?).with_backend(backend).create_deck(name)?;
 
   println!();
   println!("List '{}' added.", name);
@@ -68,57 +226,138 @@ fn add(matches: &ArgMatches) -> Result {
 }
 
 fn remove(matches: &ArgMatches) -> Result {
+  let config = app::config(matches)?;
+  let store = app::store(&config);
+  let now = cli::clock_from_matches(matches)?.now();
+  let mut seen = std::collections::HashSet::new();
+  let names = matches.values_of("names").unwrap()
+    .filter(|name| seen.insert(*name))
+    .collect::<Vec<_>>();
+
+  if let Some(name) = names.iter().find(|name| !store.deck_exists(name)) {
+    return Err(RecallError::ListNotFound(name.to_string()));
+  }
+
+  if !matches.is_present("yes") {
+    println!();
+
+    for name in names.iter() {
+      cli::print_bullet_list(Some(format_args!("{} ({} cards)", name, store.cards(name)?.len())));
+    }
+
+    println!();
+
+    if !cli::confirm(format_args!("Move {} list(s) to the trash?", names.len()))? {
+      println!();
+      println!("No lists removed.");
+      println!();
+
+      return Ok(());
+    }
+  }
+
+  println!();
+
+  for name in names.iter() {
+    trash::move_to_trash(store.dirname(), &store.list(name)?, now)?;
+
+    println!("List '{}' moved to the trash.", name);
+  }
+
+  println!();
+  cli::print_help_strip(format_args!(
+    "Execute {} to restore a list",
+    cli::inline_code(format_args!("{} list restore <name>", app::name()))
+  ));
+  println!();
+
+  Ok(())
+}
+
+fn restore(matches: &ArgMatches) -> Result {
+  let store = app::store(&app::config(matches)?);
   let names = matches.values_of("names").unwrap();
 
   println!();
 
   for name in names {
-    if !list_exists(".", name) {
-      cli::print_error_strip(format_args!("List '{}' does not exist.", name));
-      continue;
-    }
+    trash::restore(store.dirname(), name)
+    .map_err(|err| match err {
+      RecallError::ListNotFound(_) => {
+        RecallError::other(format!("List '{}' is not in the trash.", name))
+      },
+      err => err
+    })?;
 
-    let list = List::new(format!("./{}.jsonl", name))
-      .ok_or(RecallError::new("List initialization failed"))?;
-    list.delete()
-      .map_err(|_| RecallError::new("Remove list failed."))?;
-
-    println!("List '{}' removed.", name);
+    println!("List '{}' restored.", name);
   }
 
   println!();
   Ok(())
 }
 
+fn check_front(cards: &[Card], name: &str, front: &str) -> Result {
+  if cards.iter().any(|card| card.front == front) {
+    return Err(RecallError::DuplicateCard {
+      list: name.to_string(),
+      front: front.to_string()
+    });
+  }
+
+  Ok(())
+}
+
 fn append(matches: &ArgMatches) -> Result {
+  let config = app::config(matches)?;
+  let store = app::store(&config);
+  let format = Format::from_matches(matches);
   let name = matches.value_of("name").unwrap();
 
-  if !list_exists(".", name) {
-    return Err(RecallError::new("List does not exist."));
+  if !store.deck_exists(name) {
+    return Err(RecallError::ListNotFound(name.to_string()));
   }
 
-  let list = List::new(format!("./{}.jsonl", name))
-    .ok_or(RecallError::new("List initialization failed."))?;
-  let mut cards = list.cards()
-    .map_err(|_| RecallError::new("Failed to read cards."))
-    .map(|cards| cards.collect::<Vec<_>>())?;
+  let mut cards = store.cards(name)?;
+
+  if matches.is_present("editor") {
+    return append_with_editor(&store, &config, name, cards, format);
+  }
+
+  if let Some(front) = matches.value_of("front") {
+    let new_card = Card::new(
+      front.to_string(),
+      matches.value_of("back").unwrap_or("").to_string(),
+      matches.value_of("notes").unwrap_or("").to_string()
+    );
+
+    return append_cards(&store, name, cards, vec![Ok(new_card)], format);
+  }
+
+  if let Some(input_format) = matches.value_of("stdin") {
+    let mut text = String::new();
+
+    stdin().read_to_string(&mut text).map_err(|err| RecallError::io("<stdin>", err))?;
+
+    let new_cards = match input_format {
+      "json" => parse_json_cards(&text),
+      _ => parse_tsv_cards(&text)
+    };
+
+    return append_cards(&store, name, cards, new_cards, format);
+  }
 
   println!();
 
   let front = cli::prompt("Front")?;
-  let duplicate = cards.iter().any(|card| card.front == front);
 
-  if duplicate {
-    return Err(RecallError::new("Duplicate entry detected."));
-  }
+  check_front(&cards, name, &front)?;
 
   let back = cli::prompt_multiline("Back")?;
   let notes = cli::prompt_multiline("Notes")?;
   let new_card = Card::new(front, back, notes);
 
   cards.push(new_card);
-  list.save_cards(cards)
-    .map_err(|_| RecallError::new("Failed to append card."))?;
+  store.save_cards(name, cards)?;
 
   println!();
   println!("Card appended to list {}.", name);
@@ -127,29 +366,546 @@ fn append(matches: &ArgMatches) -> Result {
   Ok(())
 }
 
-fn list() -> Result {
+fn invalid_line(line: usize, message: String) -> RecallError {
+  RecallError::InvalidTemplate { line, message }
+}
+
+/// Reads cards from JSON lines or a JSON array of objects with `front`, and
+/// optionally `back` and `notes`.
+fn parse_json_cards(text: &str) -> Vec<recall::error::Result<Card>> {
+  let values = if text.trim_start().starts_with('[') {
+    match serde_json::from_str::<Vec<Value>>(text) {
+      Ok(values) => values.into_iter().enumerate().map(|(i, value)| (i + 1, Ok(value))).collect(),
+      Err(err) => vec![(err.line(), Err(err.to_string()))]
+    }
+  } else {
+    text.lines()
+    .enumerate()
+    .filter(|(_, line)| !line.trim().is_empty())
+    .map(|(i, line)| (i + 1, serde_json::from_str::<Value>(line).map_err(|err| err.to_string())))
+    .collect::<Vec<_>>()
+  };
+
+  values.into_iter()
+  .map(|(line, value)| {
+    let value = value.map_err(|message| invalid_line(line, message))?;
+    let field = |name: &str| -> recall::error::Result<String> {
+      match value.get(name) {
+        None | Some(Value::Null) => Ok(String::new()),
+        Some(Value::String(x)) => Ok(x.clone()),
+        Some(_) => Err(invalid_line(line, format!("`{}` should be a string.", name)))
+      }
+    };
+
+    if !value.is_object() {
+      return Err(invalid_line(line, "Expected an object with `front`, `back` and `notes`.".to_string()));
+    }
+
+    Ok(Card::new(field("front")?, field("back")?, field("notes")?))
+  })
+  .collect()
+}
+
+/// Reads cards from rows of front, back and notes, escaped like the TSV
+/// output of other commands.
+fn parse_tsv_cards(text: &str) -> Vec<recall::error::Result<Card>> {
+  let unescape = |value: &str| {
+    let mut result = String::new();
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+      match c {
+        '\\' => match chars.next() {
+          Some('t') => result.push('\t'),
+          Some('n') => result.push('\n'),
+          Some('r') => result.push('\r'),
+          Some(c) => result.push(c),
+          None => result.push('\\')
+        },
+        c => result.push(c)
+      }
+    }
+
+    result
+  };
+
+  text.lines()
+  .enumerate()
+  .filter(|(_, line)| !line.trim().is_empty())
+  .map(|(i, line)| {
+    let columns = line.split('\t').map(unescape).collect::<Vec<_>>();
+
+    if columns.len() > 3 {
+      return Err(invalid_line(i + 1, format!("Expected at most 3 columns, found {}.", columns.len())));
+    }
+
+    let mut columns = columns.into_iter();
+
+    Ok(Card::new(
+      columns.next().unwrap_or_default(),
+      columns.next().unwrap_or_default(),
+      columns.next().unwrap_or_default()
+    ))
+  })
+  .collect()
+}
+
+/// Appends the new cards that have a front and are no duplicates, and reports
+/// the result of every card. Fails if any card was not appended.
+fn append_cards(
+  store: &DirStore,
+  name: &str,
+  mut cards: Vec<Card>,
+  new_cards: Vec<recall::error::Result<Card>>,
+  format: Format
+) -> Result {
+  let total_count = new_cards.len();
+  let mut results = Vec::new();
+
+  for (i, new_card) in new_cards.into_iter().enumerate() {
+    let front = new_card.as_ref().ok().map(|card| card.front.clone());
+    let result = new_card.and_then(|card| {
+      if card.front.trim().is_empty() {
+        return Err(RecallError::other("The front is empty."));
+      }
+
+      check_front(&cards, name, &card.front)?;
+      Ok(card)
+    });
+
+    match result {
+      Ok(card) => {
+        cards.push(card);
+        results.push((i + 1, front, None));
+      },
+      Err(err) => results.push((i + 1, front, Some(err)))
+    }
+  }
+
+  let appended_count = results.iter().filter(|(_, _, err)| err.is_none()).count();
+
+  if appended_count > 0 {
+    store.save_cards(name, cards)?;
+  }
+
+  match format {
+    Format::Json => {
+      cli::print_json(&Value::Array(
+        results.iter()
+        .map(|(index, front, err)| json!({
+          "index": index,
+          "front": front,
+          "appended": err.is_none(),
+          "error": err.as_ref().map(|err| err.to_string())
+        }))
+        .collect()
+      ));
+    },
+    Format::Tsv => {
+      cli::print_tsv(
+        results.iter()
+        .map(|(index, front, err)| vec![
+          index.to_string(),
+          if err.is_none() { "appended" } else { "failed" }.to_string(),
+          front.clone().unwrap_or_default(),
+          err.as_ref().map(|err| err.to_string()).unwrap_or_default()
+        ])
+      );
+    },
+    Format::Text if total_count == 1 => {},
+    Format::Text => {
+      println!();
+
+      for (_, front, err) in results.iter() {
+        match (front, err) {
+          (Some(front), None) => println!("Card '{}' appended.", front),
+          (_, Some(err)) => cli::print_error_strip(err),
+          (None, None) => unreachable!()
+        }
+      }
+    }
+  }
+
+  let failed_count = total_count - appended_count;
+
+  if total_count == 1 {
+    if let Some((_, _, Some(_))) = results.first() {
+      return Err(results.pop().unwrap().2.unwrap());
+    }
+  }
+
+  if format == Format::Text {
+    println!();
+
+    if total_count == 1 {
+      println!("Card appended to list {}.", name);
+    } else {
+      println!("Appended {} of {} card(s) to list {}.", appended_count, total_count, name);
+    }
+
+    println!();
+  }
+
+  if failed_count > 0 {
+    return Err(RecallError::other(format!("{} card(s) were not appended.", failed_count)));
+  }
+
+  Ok(())
+}
+
+fn append_with_editor(
+  store: &DirStore,
+  config: &Config,
+  name: &str,
+  cards: Vec<Card>,
+  format: Format
+) -> Result {
+  let command = config.editor()
+    .ok_or_else(|| RecallError::other("No editor set, see `editor` in the config or $EDITOR."))?;
+  let mut text = editor::to_template(&format!("New cards for list '{}'.", name), &[]);
+
+  let new_cards = loop {
+    text = editor::edit(&command, &text)?;
+
+    match editor::parse_template(&text) {
+      Ok(new_cards) => break new_cards,
+      Err(err) => {
+        println!();
+        cli::print_error_strip(&err);
+
+        if !cli::confirm("Edit again?")? {
+          println!();
+          println!("No cards appended.");
+          println!();
+
+          return Ok(());
+        }
+      }
+    }
+  };
+
+  append_cards(store, name, cards, new_cards.into_iter().map(Ok).collect(), format)
+}
+
+fn rename(matches: &ArgMatches) -> Result {
+  let config = app::config(matches)?;
+  let store = app::store(&config);
+  let name = matches.value_of("name").unwrap();
+  let new_name = matches.value_of("new_name").unwrap();
+
+  if !store.deck_exists(name) {
+    return Err(RecallError::ListNotFound(name.to_string()));
+  }
+
+  store.rename_deck(name, new_name)?;
+
+  println!();
+  println!("List {} renamed to {}.", name, new_name);
+
+  if config.lists().any(|(list, _)| list == name) {
+    println!();
+    cli::print_help_strip(format_args!(
+      "The scheduler settings of the list are still under {} in the config",
+      cli::inline_code(format_args!("lists.{}", name))
+    ));
+  }
+
+  println!();
+  Ok(())
+}
+
+/// Adds cards to the destination cards, which are matched by front. Cards
+/// with the front of a destination card are resolved by `duplicates`, a kept
+/// destination card takes over the progress of the dropped one if it has
+/// more. Returns the number of moved cards and of duplicates.
+fn move_cards(destination: &mut Vec<Card>, cards: Vec<Card>, duplicates: &str) -> (usize, usize) {
+  let mut moved_count = 0;
+  let mut duplicate_count = 0;
+
+  for mut card in cards {
+    card.line_number = None;
+
+    match destination.iter_mut().find(|x| x.front == card.front) {
+      Some(existing) => {
+        duplicate_count += 1;
+
+        let replace = match duplicates {
+          "replace" => true,
+          "best" => card.has_more_progress(existing),
+          _ => false
+        };
+
+        if replace {
+          card.line_number = existing.line_number;
+          *existing = card;
+          moved_count += 1;
+        } else if card.has_more_progress(existing) {
+          existing.copy_schedule(&card);
+        }
+      },
+      None => {
+        destination.push(card);
+        moved_count += 1;
+      }
+    }
+  }
+
+  (moved_count, duplicate_count)
+}
+
+fn merge(matches: &ArgMatches) -> Result {
+  let config = app::config(matches)?;
+  let store = app::store(&config);
+  let now = cli::clock_from_matches(matches)?.now();
+  let mut names = matches.values_of("names").unwrap().collect::<Vec<_>>();
+  let destination = names.pop().unwrap();
+  let duplicates = matches.value_of("duplicates").unwrap();
+
+  if let Some(name) = names.iter().find(|name| !store.deck_exists(name)) {
+    return Err(RecallError::ListNotFound(name.to_string()));
+  }
+
+  let mut seen = std::collections::HashSet::new();
+
+  if let Some(name) = names.iter().chain(Some(&destination)).find(|name| !seen.insert(**name)) {
+    return Err(RecallError::InvalidArgument {
+      name: "names",
+      value: name.to_string()
+    });
+  }
+
+  if !store.deck_exists(destination) {
+    let backend = store.list(names[0])?.backend();
+    app::store(&config).with_backend(backend).create_deck(destination)?;
+  }
+
+  let mut cards = store.cards(destination)?;
+  let mut moved_count = 0;
+  let mut duplicate_count = 0;
+
+  for name in names.iter() {
+    let (moved, duplicate) = move_cards(&mut cards, store.cards(name)?, duplicates);
+
+    moved_count += moved;
+    duplicate_count += duplicate;
+  }
+
+  store.save_cards(destination, cards)?;
+
+  // Sources go to the trash rather than being deleted, so malformed lines
+  // and dropped duplicates can still be recovered.
+  for name in names.iter() {
+    trash::move_to_trash(store.dirname(), &store.list(name)?, now)?;
+  }
+
+  println!();
+  println!("Merged {} card(s) of {} into {}.", moved_count, names.join(", "), destination);
+
+  if duplicate_count > 0 {
+    let kept = match duplicates {
+      "replace" => "the moved cards",
+      "best" => "the cards with more progress",
+      _ => "the cards of the destination"
+    };
+
+    println!("Kept {} of {} duplicate(s).", kept, duplicate_count);
+  }
+
+  println!("Moved {} to the trash.", names.join(", "));
+  println!();
+  cli::print_help_strip(format_args!(
+    "Execute {} to restore a list",
+    cli::inline_code(format_args!("{} list restore <name>", app::name()))
+  ));
+  println!();
+  Ok(())
+}
+
+fn split(matches: &ArgMatches) -> Result {
+  let config = app::config(matches)?;
+  let store = app::store(&config);
+  let now = cli::clock_from_matches(matches)?.now();
+  let name = matches.value_of("name").unwrap();
+  let by = matches.value_of("by").unwrap();
+
+  if !store.deck_exists(name) {
+    return Err(RecallError::ListNotFound(name.to_string()));
+  }
+
+  let ladder = config.ladder(name);
+  let mut groups = std::collections::BTreeMap::<String, Vec<Card>>::new();
+  let mut remaining = Vec::new();
+
+  for card in store.cards(name)? {
+    // Path separators in tags would make the list end up in another
+    // directory.
+    let key = match by {
+      "tag" => card.tags().first().map(|tag| tag.replace(&['/', '\\'][..], "-")),
+      _ => Some(card.proficiency(ladder).to_string().to_lowercase())
+    };
+
+    match key {
+      Some(key) => groups.entry(format!("{}-{}", name, key)).or_default().push(card),
+      None => remaining.push(card)
+    }
+  }
+
+  if groups.is_empty() {
+    println!();
+    println!("No cards to split off.");
+    println!();
+
+    return Ok(());
+  }
+
+  // Check all new lists before creating any, so that a failed split leaves
+  // everything as it was.
+
+  for list in groups.keys() {
+    check_name(list)?;
+  }
+
+  if let Some(existing) = groups.keys().find(|list| store.deck_exists(list)) {
+    return Err(RecallError::ListExists(existing.to_string()));
+  }
+
+  let new_store = app::store(&config).with_backend(store.list(name)?.backend());
+
+  println!();
+
+  for (list, mut cards) in groups {
+    let count = cards.len();
+
+    for card in cards.iter_mut() {
+      card.line_number = None;
+    }
+
+    new_store.create_deck(&list)?;
+    new_store.save_cards(&list, cards)?;
+
+    println!("Moved {} card(s) to {}.", count, list);
+  }
+
+  if remaining.is_empty() {
+    trash::move_to_trash(store.dirname(), &store.list(name)?, now)?;
+    println!("Moved the empty list {} to the trash.", name);
+  } else {
+    println!("Kept {} card(s) in {}.", remaining.len(), name);
+    store.save_cards(name, remaining)?;
+  }
+
+  println!();
+  Ok(())
+}
+
+fn convert(matches: &ArgMatches) -> Result {
+  let name = matches.value_of("name").unwrap();
+  let backend = parse_backend(matches.value_of("to").unwrap());
+  let store = app::store(&app::config(matches)?);
+  let list = store.list(name)?;
+
+  println!();
+
+  if list.backend() == backend {
+    println!("List '{}' is already stored as {}.", name, backend.extension());
+    println!();
+    return Ok(());
+  }
+
+  // Write the new file completely before removing the old one, so that a
+  // failed conversion leaves the list intact.
+
+  let cards = list.cards()?;
+  let count = cards.len();
+  let malformed_count = match list.backend() {
+    Backend::Jsonl => JsonlFile::new(list.path()).malformed_lines()?.len(),
+    Backend::Sqlite => 0
+  };
+  let converted = new_list(&store, name, backend)?;
+
+  if converted.path().exists() {
+    return Err(RecallError::ListExists(name.to_string()));
+  }
+
+  if let Err(err) = converted.save_cards(cards) {
+    converted.delete().ok();
+    return Err(err);
+  }
+
+  list.delete()?;
+
+  println!("Converted {} card(s) of list '{}' to {}.", count, name, backend.extension());
+
+  if malformed_count > 0 {
+    cli::print_error_strip(format_args!("Dropped {} malformed line(s).", malformed_count));
+  }
+
+  println!();
+
+  Ok(())
+}
+
+fn list(matches: &ArgMatches) -> Result {
+  let format = Format::from_matches(matches);
+
   // Get lists
 
-  let lists = get_lists(".")
-    .map_err(|_| RecallError::new("Unable to read from working directory"))?
-    .collect::<Vec<_>>();
+  let store = app::store(&app::config(matches)?);
+  let now = cli::clock_from_matches(matches)?.now();
+  let decks = store.decks()?;
+  let due_counts = store.due_counts(&decks, now);
+
+  if format != Format::Text {
+    let inventory = decks.iter()
+      .zip(due_counts)
+      .map(|(deck, due_count)| {
+        let count = store.cards(deck).map(|cards| cards.len()).unwrap_or(0);
+
+        (deck, count, due_count)
+      })
+      .collect::<Vec<_>>();
+
+    if format == Format::Json {
+      cli::print_json(&json!(
+        inventory.iter()
+        .map(|(deck, count, due_count)| json!({
+          "name": deck,
+          "path": store.location(deck),
+          "cards": count,
+          "due": due_count
+        }))
+        .collect::<Vec<_>>()
+      ));
+    } else {
+      cli::print_tsv(
+        inventory.iter()
+        .map(|(deck, count, due_count)| vec![
+          deck.to_string(),
+          count.to_string(),
+          due_count.to_string()
+        ])
+      );
+    }
+
+    return Ok(());
+  }
 
   println!();
   cli::print_header_strip("Lists");
   println!();
 
-  if lists.len() > 0 {
+  if !decks.is_empty() {
     cli::print_bullet_list(
-      lists.iter()
-      .map(|list| {
+      decks.iter()
+      .zip(due_counts)
+      .map(|(deck, due_count)| {
         format!(
           "{} {}",
-          list.name(),
-          list.cards().ok()
-            .map(|cards| cards.filter(|card| card.is_due()).count())
-            .and_then(|x| if x == 0 { None } else { Some(x) })
+          deck,
+          Some(due_count)
+            .filter(|&x| x > 0)
             .map(|x| format!("({})", x))
-            .unwrap_or_else(|| String::new())
+            .unwrap_or_default()
             .bright_red()
         )
       })
@@ -169,3 +925,145 @@ fn list() -> Result {
 
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn fronts(cards: &[recall::error::Result<Card>]) -> Vec<Option<&str>> {
+    cards.iter().map(|card| card.as_ref().ok().map(|card| card.front.as_str())).collect()
+  }
+
+  fn error_line(card: &recall::error::Result<Card>) -> Option<usize> {
+    match card {
+      Err(RecallError::InvalidTemplate { line, .. }) => Some(*line),
+      _ => None
+    }
+  }
+
+  #[test]
+  fn parses_json_lines_and_arrays() {
+    let lines = parse_json_cards("{\"front\":\"a\",\"back\":\"1\"}\n\n{\"front\":5}\n[1]\n{\"front\":\"b\",\"notes\":null}\n");
+    let array = parse_json_cards("[{\"front\":\"a\"},\n{\"front\":\"b\",\"back\":\"2\",\"notes\":\"x\"}]");
+
+    assert_eq!(fronts(&lines), vec![Some("a"), None, None, Some("b")]);
+    assert_eq!(error_line(&lines[1]), Some(3));
+    assert_eq!(error_line(&lines[2]), Some(4));
+    assert_eq!(fronts(&array), vec![Some("a"), Some("b")]);
+    assert_eq!(array[1].as_ref().map(|card| card.notes.as_str()).ok(), Some("x"));
+    assert_eq!(error_line(&parse_json_cards("[{\"front\":\"a\"},\n")[0]), Some(2));
+  }
+
+  #[test]
+  fn parses_tsv_rows() {
+    let cards = parse_tsv_cards("a\t1\ttab\\there\n\nb\\nc\n\t\t\nd\t1\t2\t3\n");
+
+    assert_eq!(fronts(&cards), vec![Some("a"), Some("b\nc"), None]);
+    assert_eq!(cards[0].as_ref().map(|card| card.notes.as_str()).ok(), Some("tab\there"));
+    assert_eq!(error_line(&cards[2]), Some(5));
+  }
+
+  fn card(front: &str, level: i8, notes: &str) -> Card {
+    let mut card = Card::new(front.to_string(), String::new(), notes.to_string());
+
+    card.level = level;
+    card
+  }
+
+  fn levels(cards: &[Card]) -> Vec<(&str, i8)> {
+    cards.iter().map(|card| (card.front.as_str(), card.level)).collect()
+  }
+
+  fn run(dirname: &std::path::Path, args: &[&str]) -> Result {
+    let mut all_args = vec!["recall", "--deck-dir", dirname.to_str().unwrap(), "list"];
+    all_args.extend_from_slice(args);
+
+    let matches = App::new("recall")
+      .arg(cli::deck_dir_arg())
+      .subcommand(subcommand())
+      .get_matches_from(all_args);
+
+    dispatch(matches.subcommand_matches("list").unwrap())
+  }
+
+  #[test]
+  fn moves_cards_by_front() {
+    let moved = || vec![card("a", 1, ""), card("b", 5, ""), card("c", 0, "")];
+    let mut kept = vec![card("a", 3, ""), card("b", 3, "")];
+    let mut replaced = kept.clone();
+    let mut best = kept.clone();
+
+    assert_eq!(move_cards(&mut kept, moved(), "keep"), (1, 2));
+    assert_eq!(move_cards(&mut replaced, moved(), "replace"), (3, 2));
+    assert_eq!(move_cards(&mut best, moved(), "best"), (2, 2));
+    assert_eq!(levels(&kept), vec![("a", 3), ("b", 5), ("c", 0)]);
+    assert_eq!(levels(&replaced), vec![("a", 1), ("b", 5), ("c", 0)]);
+    assert_eq!(levels(&best), vec![("a", 3), ("b", 5), ("c", 0)]);
+  }
+
+  #[test]
+  fn merges_and_splits_lists() {
+    let dirname = std::env::temp_dir().join(format!("recall-test-{}-merge", std::process::id()));
+
+    std::fs::create_dir_all(&dirname).unwrap();
+
+    let store = DirStore::new(&dirname);
+
+    store.create_deck("a").unwrap();
+    store.create_deck("b").unwrap();
+    store.save_cards("a", vec![card("x", 2, "#verb"), card("y", 0, "")]).unwrap();
+    store.save_cards("b", vec![card("x", 4, ""), card("z", 1, "#noun #verb")]).unwrap();
+
+    let merged = run(&dirname, &["merge", "a", "b", "c", "--duplicates", "best"]);
+    let merged_cards = store.cards("c");
+    let decks_after_merge = store.decks();
+    let trashed = recall::trash::trashed_lists(&dirname).map(|lists| lists.len());
+    let missing = run(&dirname, &["merge", "a", "c"]);
+    let repeated = run(&dirname, &["merge", "c", "c", "d"]);
+    let split = run(&dirname, &["split", "c", "--by", "tag"]);
+    let decks_after_split = store.decks();
+    let remaining_cards = store.cards("c");
+    let noun_cards = store.cards("c-noun");
+
+    std::fs::remove_dir_all(&dirname).ok();
+    assert!(merged.is_ok());
+    assert_eq!(levels(&merged_cards.unwrap()), vec![("x", 4), ("y", 0), ("z", 1)]);
+    assert_eq!(decks_after_merge.unwrap(), vec!["c"]);
+    assert_eq!(trashed.unwrap(), 2);
+    assert!(matches!(missing, Err(RecallError::ListNotFound(_))));
+    assert!(matches!(repeated, Err(RecallError::InvalidArgument { .. })));
+    assert!(split.is_ok());
+    assert_eq!(decks_after_split.unwrap(), vec!["c", "c-noun"]);
+    assert_eq!(levels(&remaining_cards.unwrap()), vec![("x", 4), ("y", 0)]);
+    assert_eq!(levels(&noun_cards.unwrap()), vec![("z", 1)]);
+  }
+
+  #[test]
+  fn split_checks_all_lists_before_creating_any() {
+    let dirname = std::env::temp_dir().join(format!("recall-test-{}-split", std::process::id()));
+
+    std::fs::create_dir_all(&dirname).unwrap();
+
+    let store = DirStore::new(&dirname);
+
+    store.create_deck("s").unwrap();
+    store.create_deck("s-noun").unwrap();
+    store.save_cards("s", vec![card("p", 1, "#verb/past"), card("q", 2, "#noun"), card("r", 0, "")]).unwrap();
+
+    let existing = run(&dirname, &["split", "s", "--by", "tag"]);
+    let decks_after_failure = store.decks();
+
+    store.delete_deck("s-noun").unwrap();
+
+    let split = run(&dirname, &["split", "s", "--by", "tag"]);
+    let decks_after_split = store.decks();
+    let past_cards = store.cards("s-verb-past");
+
+    std::fs::remove_dir_all(&dirname).ok();
+    assert!(matches!(existing, Err(RecallError::ListExists(_))));
+    assert_eq!(decks_after_failure.unwrap(), vec!["s", "s-noun"]);
+    assert!(split.is_ok());
+    assert_eq!(decks_after_split.unwrap(), vec!["s", "s-noun", "s-verb-past"]);
+    assert_eq!(levels(&past_cards.unwrap()), vec![("p", 1)]);
+  }
+}
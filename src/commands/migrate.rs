@@ -0,0 +1,56 @@
+use clap::{ArgMatches, App, Arg, SubCommand};
+use super::{RecallError, Result};
+use recall::list::get_lists;
+use recall::storage::Storage;
+use recall::store::DeckStore;
+use crate::app;
+
+pub fn subcommand<'a>() -> App<'a, 'static> {
+  SubCommand::with_name("migrate")
+  .about("Upgrades lists to the current storage format")
+  .arg(
+    Arg::with_name("names")
+    .help("Name of the lists to upgrade")
+    .multiple(true)
+  )
+}
+
+pub fn dispatch(matches: &ArgMatches) -> Result {
+  let store = app::store(&app::config(matches)?);
+  let names = matches.values_of("names")
+    .map(|names| names.collect::<Vec<_>>());
+  let invalid_name = names.as_ref()
+    .and_then(|names| names.iter().find(|name| !store.deck_exists(name)));
+
+  if let Some(name) = invalid_name {
+    return Err(RecallError::ListNotFound(name.to_string()));
+  }
+
+  let mut lists = get_lists(store.dirname())?
+    .filter(|list| {
+      names.as_ref()
+      .map(|names| names.contains(&list.name()))
+      .unwrap_or(true)
+    })
+    .collect::<Vec<_>>();
+
+  lists.sort_by(|x, y| x.name().cmp(y.name()));
+
+  println!();
+
+  let mut migrated_count = 0;
+
+  for list in lists.iter() {
+    if list.migrate()? {
+      println!("List '{}' migrated.", list.name());
+      migrated_count += 1;
+    }
+  }
+
+  if migrated_count == 0 {
+    println!("All lists are up to date.");
+  }
+
+  println!();
+  Ok(())
+}
@@ -1,30 +1,20 @@
-use std::fmt::Display;
-use std::error::Error;
-
+pub mod card;
+pub mod config;
+pub mod dedupe;
+pub mod doctor;
 pub mod info;
 pub mod learn;
 pub mod list;
+pub mod migrate;
+pub mod reschedule;
+pub mod reset;
 pub mod review;
+pub mod simulate;
+pub mod status;
+pub mod study;
+pub mod trash;
+pub mod undo;
 
-#[derive(Debug)]
-pub struct RecallError {
-  pub message: &'static str
-}
-
-impl Error for RecallError {}
-
-impl Display for RecallError {
-  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    write!(f, "{}", self.message)
-  }
-}
-
-impl RecallError {
-  pub fn new(message: &'static str) -> RecallError {
-    RecallError {
-      message
-    }
-  }
-}
+pub use recall::error::RecallError;
 
-pub type Result = std::result::Result<(), RecallError>;
+pub type Result<T = ()> = std::result::Result<T, RecallError>;
@@ -0,0 +1,122 @@
+use clap::{ArgMatches, App, Arg, ArgGroup, SubCommand};
+use chrono::Duration;
+use super::{RecallError, Result};
+use recall::{session, store::DeckStore, card::{get_cards, update_cards}, ladder::parse_interval};
+use crate::{cli, app};
+
+pub fn subcommand<'a>() -> App<'a, 'static> {
+  SubCommand::with_name("reschedule")
+  .about("Moves or spreads out the due times of overdue cards")
+  .long_about(
+    "Moves or spreads out the due times of overdue cards, e.g. to catch up \
+    after a break. With --shift, the due time of every overdue card is \
+    postponed by the given duration. With --spread, overdue cards become due \
+    evenly over the given duration, starting now with the most overdue ones."
+  )
+  .arg(
+    Arg::with_name("names")
+    .help("Name of the lists to reschedule")
+    .multiple(true)
+  )
+  .arg(
+    Arg::with_name("shift")
+    .long("shift")
+    .value_name("duration")
+    .help("Postpones overdue cards by the duration, e.g. 3d")
+    .takes_value(true)
+  )
+  .arg(
+    Arg::with_name("spread")
+    .long("spread")
+    .value_name("duration")
+    .help("Spreads overdue cards over the duration from now, e.g. 7d")
+    .takes_value(true)
+  )
+  .group(
+    ArgGroup::with_name("mode")
+    .args(&["shift", "spread"])
+    .required(true)
+  )
+  .arg(cli::filter_arg())
+}
+
+fn parse_duration(matches: &ArgMatches, name: &'static str) -> std::result::Result<Option<Duration>, RecallError> {
+  matches.value_of(name)
+  .map(|value| {
+    parse_interval(value).map_err(|_| RecallError::InvalidArgument {
+      name,
+      value: value.to_string()
+    })
+  })
+  .transpose()
+}
+
+pub fn dispatch(matches: &ArgMatches) -> Result {
+  let config = app::config(matches)?;
+  let store = app::store(&config);
+  let now = cli::clock_from_matches(matches)?.now();
+  let filters = cli::filters_from_matches(matches)?;
+  let shift = parse_duration(matches, "shift")?;
+  let spread = parse_duration(matches, "spread")?;
+  let names = matches.values_of("names")
+    .map(|names| names.collect::<Vec<_>>());
+
+  if let Some(name) = names.as_ref().and_then(|names| names.iter().find(|name| !store.deck_exists(name))) {
+    return Err(RecallError::ListNotFound(name.to_string()));
+  }
+
+  let mut cards = get_cards(
+    &store,
+    store.decks()?
+    .into_iter()
+    .filter(|deck| {
+      names.as_ref()
+      .map(|names| names.contains(&deck.as_str()))
+      .unwrap_or(true)
+    })
+  )
+  .into_iter()
+  .filter(|(_, card)| card.is_due_at(now))
+  .filter(|(deck, card)| filters.iter().all(|filter| filter.matches(card, config.ladder(deck))))
+  .collect::<Vec<_>>();
+
+  println!();
+
+  if cards.is_empty() {
+    println!("No overdue cards to reschedule.");
+    println!();
+
+    return Ok(());
+  }
+
+  if let Some(shift) = shift {
+    for (_, card) in cards.iter_mut() {
+      card.due_time = card.due_time.map(|x| x + shift);
+    }
+  }
+
+  if let Some(spread) = spread {
+    let count = cards.len() as i32;
+
+    cards.sort_by_key(|(_, card)| card.due_time);
+
+    for (i, (_, card)) in cards.iter_mut().enumerate() {
+      card.due_time = Some(now + spread * i as i32 / count);
+    }
+  }
+
+  let due_count = cards.iter().filter(|(_, card)| card.is_due_at(now)).count();
+  let previous = update_cards(&store, cards)?;
+
+  session::save_undo(store.dirname(), &previous)?;
+
+  println!("Rescheduled {} overdue card(s), {} of them are still due.", previous.len(), due_count);
+  println!();
+  cli::print_help_strip(format_args!(
+    "Execute {} to restore the previous due times",
+    cli::inline_code(format_args!("{} undo", app::name()))
+  ));
+  println!();
+
+  Ok(())
+}
@@ -0,0 +1,79 @@
+use clap::{ArgMatches, App, Arg, SubCommand};
+use super::{RecallError, Result};
+use recall::{session, store::DeckStore, card::{get_cards, update_cards}};
+use crate::{cli, app};
+
+pub fn subcommand<'a>() -> App<'a, 'static> {
+  SubCommand::with_name("reset")
+  .about("Puts cards back to Inactive, to learn them from scratch")
+  .arg(
+    Arg::with_name("names")
+    .help("Name of the lists to reset")
+    .multiple(true)
+    .required(true)
+  )
+  .arg(cli::filter_arg())
+  .arg(cli::yes_arg())
+}
+
+pub fn dispatch(matches: &ArgMatches) -> Result {
+  let config = app::config(matches)?;
+  let store = app::store(&config);
+  let filters = cli::filters_from_matches(matches)?;
+  let names = matches.values_of("names").unwrap().collect::<Vec<_>>();
+
+  if let Some(name) = names.iter().find(|name| !store.deck_exists(name)) {
+    return Err(RecallError::ListNotFound(name.to_string()));
+  }
+
+  let mut cards = get_cards(&store, names.iter().map(|name| name.to_string()))
+    .into_iter()
+    .filter(|(deck, card)| {
+      let ladder = config.ladder(deck);
+
+      !card.is_new(ladder)
+      && filters.iter().all(|filter| filter.matches(card, ladder))
+    })
+    .collect::<Vec<_>>();
+
+  println!();
+
+  if cards.is_empty() {
+    println!("No cards to reset.");
+    println!();
+
+    return Ok(());
+  }
+
+  if !matches.is_present("yes") {
+    let question = format!("Reset {} card(s) of {} to Inactive?", cards.len(), names.join(", "));
+
+    if !cli::confirm(question)? {
+      println!();
+      println!("No cards reset.");
+      println!();
+
+      return Ok(());
+    }
+
+    println!();
+  }
+
+  for (_, card) in cards.iter_mut() {
+    card.reset();
+  }
+
+  let previous = update_cards(&store, cards)?;
+
+  session::save_undo(store.dirname(), &previous)?;
+
+  println!("Reset {} card(s).", previous.len());
+  println!();
+  cli::print_help_strip(format_args!(
+    "Execute {} to restore them",
+    cli::inline_code(format_args!("{} undo", app::name()))
+  ));
+  println!();
+
+  Ok(())
+}
@@ -1,7 +1,7 @@
 use clap::{ArgMatches, App, Arg, SubCommand};
 use super::{RecallError, Result};
-use crate::{list::{get_lists, list_exists}, cli, card::{update_cards, get_cards}, app};
-use rand::prelude::SliceRandom;
+use recall::{session, store::DeckStore, card::{Outcome, get_cards}};
+use crate::{cli, app};
 
 pub fn subcommand<'a>() -> App<'a, 'static> {
   SubCommand::with_name("review")
@@ -11,19 +11,45 @@ pub fn subcommand<'a>() -> App<'a, 'static> {
     .help("Name of the lists to review")
     .multiple(true)
   )
+  .arg(cli::batch_arg())
+  .arg(cli::order_arg())
+  .arg(
+    Arg::with_name("limit")
+    .short("l")
+    .long("limit")
+    .help("The maximum number of cards you want to review, overriding session.size")
+    .takes_value(true)
+  )
 }
 
 pub fn dispatch(matches: &ArgMatches) -> Result {
+  let config = app::config(matches)?;
+  let store = app::store(&config);
+  let clock = cli::clock_from_matches(matches)?;
+  let dry_run = cli::is_dry_run(matches);
+  let mut rng = cli::rng_from_matches(matches)?;
+  let mut term = cli::terminal_from_matches(matches);
   let names = matches.values_of("names")
     .map(|names| names.collect::<Vec<_>>());
+  let order = cli::order_from_matches(matches);
+  let limit = Ok(matches.value_of("limit"))
+    .and_then(|limit| {
+      limit
+      .map(|x| {
+        x.parse::<usize>()
+        .map(Some)
+        .map_err(|_| RecallError::InvalidArgument {
+          name: "limit",
+          value: x.to_string()
+        })
+      })
+      .unwrap_or(Ok(None))
+    })?;
 
-  let has_invalid_names = names.as_ref()
-    .map(|names| names.iter().any(|name| !list_exists(".", name)))
-    .unwrap_or(false);
+  let invalid_name = names.as_ref()
+    .and_then(|names| names.iter().find(|name| !store.deck_exists(name)));
 
-  if has_invalid_names {
-    println!();
-    println!("List not found.");
+  if let Some(name) = invalid_name {
     println!();
 
     cli::print_help_strip(
@@ -40,47 +66,86 @@ pub fn dispatch(matches: &ArgMatches) -> Result {
       )
     );
 
-    println!();
-    return Ok(());
+    return Err(RecallError::ListNotFound(name.to_string()));
   }
 
-  let cards = {
-    let mut cards = get_cards(
-      get_lists(".")
-      .map_err(|_| RecallError::new("Cannot read lists."))?
-      .filter(|list| {
-        names.as_ref()
-        .map(|names| names.contains(&list.name()))
-        .unwrap_or(true)
-      })
-    )
-    .filter(|(_, card)| card.is_due())
-    .collect::<Vec<_>>();
+  println!();
+  cli::print_header_strip("Review");
 
-    let mut rng = rand::thread_rng();
-    cards.shuffle(&mut rng);
-    cards
+  let mut limit_reached = false;
+  let checkpoint = if dry_run {
+    None
+  } else {
+    session::prompt_resume(term.as_mut(), &store, &config, store.dirname(), "review", clock.now())?
   };
+  let (cards, results) = match checkpoint {
+    Some(checkpoint) => (checkpoint.queue, checkpoint.results),
+    None => {
+      let mut cards = get_cards(
+        &store,
+        store.decks()?
+        .into_iter()
+        .filter(|deck| {
+          names.as_ref()
+          .map(|names| names.contains(&deck.as_str()))
+          .unwrap_or(true)
+        })
+      )
+      .into_iter()
+      .filter(|(_, card)| card.is_due_at(clock.now()))
+      .collect::<Vec<_>>();
 
-  println!();
-  cli::print_header_strip("Review");
+      session::order_cards(&mut cards, order, &config, clock.now(), &mut rng);
 
-  if cards.len() > 0 {
-    println!();
-    let mut cards = cli::loop_cards(cards)?;
-    let remembered_count = cards.iter().filter(|&&(_, _, remembered)| remembered).count();
+      let counts = session::load_daily_counts(store.dirname(), clock.now())?;
+      let remaining = session::remaining(config.reviews_per_day(), counts.reviewed);
+
+      limit_reached = remaining == Some(0) && !cards.is_empty();
+
+      if let Some(limit) = remaining.into_iter().chain(limit.or_else(|| config.session_size())).min() {
+        cards.truncate(limit);
+      }
+
+      (cards, Vec::new())
+    }
+  };
+
+  if !cards.is_empty() || !results.is_empty() {
+    let checkpoint_dir = if dry_run { None } else { Some((store.dirname(), "review")) };
     println!();
-    println!("Reviewed {} card(s).", cards.len());
+    let cards = session::loop_cards(term.as_mut(), &store, &config, checkpoint_dir, cards, results, clock.as_ref(), &mut rng)?;
+    let remembered_count = cards.iter()
+      .filter(|&&(_, _, outcome)| outcome == Outcome::Remembered)
+      .count();
+    let reviewed_count = cards.iter()
+      .filter(|&&(_, _, outcome)| outcome == Outcome::Remembered || outcome == Outcome::Forgotten)
+      .count();
     println!();
-    println!("Remembered:  {}", cli::progress_bar(remembered_count as f64 / cards.len() as f64, 18));
+    println!("Reviewed {} card(s).", reviewed_count);
     println!();
 
-    for &mut (_, ref mut card, remembered) in cards.iter_mut() {
-      card.review(remembered);
+    if reviewed_count > 0 {
+      println!("Remembered:  {}", cli::progress_bar(remembered_count as f64 / reviewed_count as f64, 18));
+      println!();
     }
 
-    update_cards(cards.into_iter().map(|(path, card, _)| (path, card)))
-    .map_err(|_| RecallError::new("Updating cards failed."))?;
+    if dry_run {
+      cli::print_help_strip("Dry run at the time given by --now, no answers were saved.");
+      println!();
+    } else {
+      session::commit(&store, &config, store.dirname(), "review", cards, clock.now())?;
+    }
+  } else if limit_reached {
+    println!();
+    println!("Daily limit of {} review(s) reached.", config.reviews_per_day().unwrap_or(0));
+    println!();
+    cli::print_help_strip(
+      format_args!(
+        "Execute {} to change the limit",
+        cli::inline_code(format_args!("{} config set limits.reviews_per_day <count>", app::name()))
+      )
+    );
+    println!();
   } else {
     println!();
     println!("No cards to review right now.");
@@ -0,0 +1,256 @@
+use clap::{ArgMatches, App, Arg, SubCommand};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde_json::json;
+use super::{RecallError, Result};
+use recall::{
+  card::get_cards,
+  ladder::Ladder,
+  simulate::{simulate, Model, Report, Settings},
+  store::DeckStore
+};
+use crate::{cli::{self, Format}, app};
+
+pub fn subcommand<'a>() -> App<'a, 'static> {
+  SubCommand::with_name("simulate")
+  .about("Simulates reviews to compare interval ladders")
+  .long_about(
+    "Simulates daily reviews of lists and reports the expected workload, \
+    retention and time to Burned for the current ladder and each alternative \
+    given with --ladder."
+  )
+  .arg(
+    Arg::with_name("names")
+    .help("Name of the lists to simulate")
+    .multiple(true)
+  )
+  .arg(
+    Arg::with_name("days")
+    .long("days")
+    .help("Number of days to simulate")
+    .takes_value(true)
+    .default_value("90")
+  )
+  .arg(
+    Arg::with_name("new")
+    .long("new")
+    .help("Number of new cards learned per day")
+    .takes_value(true)
+    .default_value("10")
+  )
+  .arg(
+    Arg::with_name("sessions")
+    .long("sessions")
+    .help("Number of review sessions per day")
+    .takes_value(true)
+    .default_value("1")
+  )
+  .arg(
+    Arg::with_name("model")
+    .long("model")
+    .help("Recall probability, either constant:<probability> or decay:<half-life>:<growth>")
+    .takes_value(true)
+    .default_value("decay:2d:2.5")
+  )
+  .arg(
+    Arg::with_name("ladder")
+    .long("ladder")
+    .help("Alternative intervals to compare, e.g. 1d,3d,7d,30d")
+    .takes_value(true)
+    .multiple(true)
+    .number_of_values(1)
+  )
+}
+
+fn parse_arg<T: std::str::FromStr>(matches: &ArgMatches, name: &'static str) -> std::result::Result<T, RecallError> {
+  let value = matches.value_of(name).unwrap();
+
+  value.parse::<T>()
+  .map_err(|_| RecallError::InvalidArgument {
+    name,
+    value: value.to_string()
+  })
+}
+
+pub fn dispatch(matches: &ArgMatches) -> Result {
+  let format = Format::from_matches(matches);
+  let config = app::config(matches)?;
+  let store = app::store(&config);
+  let now = cli::clock_from_matches(matches)?.now();
+  let seed = cli::rng_from_matches(matches)?.gen::<u64>();
+  let names = matches.values_of("names")
+    .map(|names| names.collect::<Vec<_>>());
+  let invalid_name = names.as_ref()
+    .and_then(|names| names.iter().find(|name| !store.deck_exists(name)));
+
+  if let Some(name) = invalid_name {
+    return Err(RecallError::ListNotFound(name.to_string()));
+  }
+
+  let settings = Settings {
+    days: parse_arg(matches, "days")?,
+    new_per_day: parse_arg(matches, "new")?,
+    sessions_per_day: parse_arg(matches, "sessions")?
+  };
+
+  if settings.sessions_per_day == 0 {
+    return Err(RecallError::InvalidArgument {
+      name: "sessions",
+      value: "0".to_string()
+    });
+  }
+
+  let model_arg = matches.value_of("model").unwrap();
+  let model = Model::parse(model_arg)
+    .ok_or_else(|| RecallError::InvalidArgument {
+      name: "model",
+      value: model_arg.to_string()
+    })?;
+
+  // A single list is simulated with its own settings, several lists with the
+  // global ones.
+
+  let current = match names.as_deref() {
+    Some([name]) => config.ladder(name),
+    _ => config.scheduler()
+  };
+  let mut ladders = vec![("current".to_string(), current.clone())];
+
+  for value in matches.values_of("ladder").into_iter().flatten() {
+    let ladder = Ladder::parse(value, current)
+      .map_err(|_| RecallError::InvalidArgument {
+        name: "ladder",
+        value: value.to_string()
+      })?;
+
+    ladders.push((ladder.to_string(), ladder));
+  }
+
+  let cards = get_cards(
+    &store,
+    store.decks()?
+    .into_iter()
+    .filter(|deck| {
+      names.as_ref()
+      .map(|names| names.contains(&deck.as_str()))
+      .unwrap_or(true)
+    })
+  )
+  .into_iter()
+  .map(|(_, card)| card)
+  .collect::<Vec<_>>();
+
+  // Every ladder gets the same random numbers, so differences in the results
+  // come from the ladders only.
+
+  let reports = ladders.iter()
+    .map(|(_, ladder)| {
+      let mut rng = StdRng::seed_from_u64(seed);
+      simulate(&cards, ladder, &model, &settings, now, &mut rng)
+    })
+    .collect::<Vec<_>>();
+
+  let results = ladders.iter().zip(reports.iter()).collect::<Vec<_>>();
+
+  match format {
+    Format::Json => {
+      cli::print_json(&json!({
+        "lists": names,
+        "cards": cards.len(),
+        "days": settings.days,
+        "new_per_day": settings.new_per_day,
+        "sessions_per_day": settings.sessions_per_day,
+        "model": model.to_string(),
+        "ladders": results.iter()
+          .map(|((name, ladder), report)| json!({
+            "name": name,
+            "intervals": ladder.to_string(),
+            "reviews_per_day": report.average_reviews_per_day(),
+            "peak_reviews_per_day": report.peak_reviews_per_day(),
+            "retention": report.retention(),
+            "learned": report.learned,
+            "burned": report.burned,
+            "days_to_burned": report.average_days_to_burned(),
+            "daily_reviews": report.reviews_per_day
+          }))
+          .collect::<Vec<_>>()
+      }));
+
+      return Ok(());
+    },
+    Format::Tsv => {
+      cli::print_tsv(
+        results.iter()
+        .map(|((name, _), report)| vec![
+          name.clone(),
+          format!("{:.2}", report.average_reviews_per_day()),
+          report.peak_reviews_per_day().to_string(),
+          report.retention().map(|x| format!("{:.4}", x)).unwrap_or_default(),
+          report.burned.to_string(),
+          report.average_days_to_burned().map(|x| format!("{:.1}", x)).unwrap_or_default()
+        ])
+      );
+
+      return Ok(());
+    },
+    Format::Text => {}
+  }
+
+  println!();
+  cli::print_header_strip("Simulation");
+  println!();
+
+  if let Some(names) = names.as_ref() {
+    println!("Lists: {}", names.join(", "));
+  } else {
+    println!("All lists");
+  }
+
+  println!(
+    "{} card(s), {} day(s), {} new card(s) and {} session(s) per day, model {}",
+    cards.len(),
+    settings.days,
+    settings.new_per_day,
+    settings.sessions_per_day,
+    model
+  );
+
+  for ((name, ladder), report) in results.iter() {
+    println!();
+
+    if name == "current" {
+      println!("Current ladder: {}", ladder);
+    } else {
+      println!("Ladder: {}", ladder);
+    }
+
+    println!();
+    print_report(report);
+  }
+
+  println!();
+  Ok(())
+}
+
+fn print_report(report: &Report) {
+  let print_row = |text: &str, value: String| {
+    println!("{:>19}:  {}", text, value);
+  };
+
+  print_row("Reviews per Day", format!("{:.1}", report.average_reviews_per_day()));
+  print_row("Peak Reviews", report.peak_reviews_per_day().to_string());
+  print_row(
+    "Retention",
+    report.retention()
+      .map(|x| (x * 100.0).round() as u32)
+      .map(|x| x.to_string() + "%")
+      .unwrap_or("-".to_string())
+  );
+  print_row("Learned", report.learned.to_string());
+  print_row("Burned", report.burned.to_string());
+  print_row(
+    "Days to Burned",
+    report.average_days_to_burned()
+      .map(|x| format!("{:.1}", x))
+      .unwrap_or("-".to_string())
+  );
+}
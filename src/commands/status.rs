@@ -0,0 +1,94 @@
+use clap::{ArgMatches, App, Arg, SubCommand};
+use serde_json::json;
+use super::{RecallError, Result};
+use recall::store::DeckStore;
+use crate::{app, cli::{self, Format}};
+
+/// Exit code when no cards are due, distinct from the codes of errors.
+pub const NOTHING_DUE: i32 = 2;
+
+pub fn subcommand<'a>() -> App<'a, 'static> {
+  SubCommand::with_name("status")
+  .about("Prints a compact due count for shell prompts and status bars")
+  .long_about(
+    "Prints a compact due count for shell prompts and status bars. \
+    Exits with code 2 if no cards are due, so it can be told apart from \
+    errors, which exit with code 1 or a code from 64 to 78."
+  )
+  .arg(
+    Arg::with_name("names")
+    .help("Name of the lists to count")
+    .multiple(true)
+  )
+}
+
+/// Prints the due count and returns the exit code, `NOTHING_DUE` if no cards
+/// are due.
+pub fn dispatch(matches: &ArgMatches) -> Result<i32> {
+  let format = Format::from_matches(matches);
+  let store = app::store(&app::config(matches)?);
+  let names = matches.values_of("names")
+    .map(|names| names.collect::<Vec<_>>());
+
+  if let Some(name) = names.as_ref().and_then(|names| names.iter().find(|name| !store.deck_exists(name))) {
+    return Err(RecallError::ListNotFound(name.to_string()));
+  }
+
+  let decks = store.decks()?
+    .into_iter()
+    .filter(|deck| {
+      names.as_ref()
+      .map(|names| names.contains(&deck.as_str()))
+      .unwrap_or(true)
+    })
+    .collect::<Vec<_>>();
+
+  let now = cli::clock_from_matches(matches)?.now();
+  let due_count = store.due_counts(&decks, now).into_iter().sum::<usize>();
+
+  match format {
+    Format::Json => cli::print_json(&json!({"due": due_count})),
+    Format::Tsv => cli::print_tsv(vec![vec!["due".to_string(), due_count.to_string()]]),
+    Format::Text if due_count > 0 => println!("{} due", due_count),
+    Format::Text => {}
+  }
+
+  Ok(if due_count == 0 { NOTHING_DUE } else { 0 })
+}
+
+#[cfg(test)]
+mod tests {
+  use recall::store::DirStore;
+  use super::*;
+
+  fn run(dirname: &std::path::Path, args: &[&str]) -> Result<i32> {
+    let mut all_args = vec!["recall", "--deck-dir", dirname.to_str().unwrap(), "status"];
+    all_args.extend_from_slice(args);
+
+    let matches = App::new("recall")
+      .arg(cli::deck_dir_arg())
+      .subcommand(subcommand())
+      .get_matches_from(all_args);
+
+    dispatch(matches.subcommand_matches("status").unwrap())
+  }
+
+  #[test]
+  fn counts_due_cards_of_existing_lists() {
+    let dirname = std::env::temp_dir().join(format!("recall-test-{}-status", std::process::id()));
+
+    std::fs::create_dir_all(&dirname).unwrap();
+
+    let store = DirStore::new(&dirname);
+
+    store.create_deck("words").unwrap();
+    store.save_cards("words", vec![]).unwrap();
+
+    let empty = run(&dirname, &["words"]);
+    let missing = run(&dirname, &["words", "nosuch"]);
+
+    std::fs::remove_dir_all(&dirname).unwrap();
+    assert_eq!(empty.unwrap(), NOTHING_DUE);
+    assert!(matches!(missing, Err(RecallError::ListNotFound(name)) if name == "nosuch"));
+  }
+}
@@ -0,0 +1,192 @@
+use clap::{ArgMatches, App, Arg, SubCommand};
+use rand::seq::SliceRandom;
+use super::{RecallError, Result};
+use recall::{session, store::DeckStore, card::{Proficiency, Outcome, get_cards}};
+use crate::{cli, app};
+
+pub fn subcommand<'a>() -> App<'a, 'static> {
+  SubCommand::with_name("study")
+  .about("Starts a session of due reviews mixed with new cards")
+  .arg(
+    Arg::with_name("names")
+    .help("Name of the lists to study")
+    .multiple(true)
+  )
+  .arg(cli::batch_arg())
+  .arg(cli::order_arg())
+  .arg(
+    Arg::with_name("new")
+    .short("n")
+    .long("new")
+    .help("The maximum number of new cards you want to learn")
+    .takes_value(true)
+  )
+  .arg(
+    Arg::with_name("reviews_per_new")
+    .long("reviews-per-new")
+    .value_name("count")
+    .help("The number of reviews before every new card, overriding session.reviews_per_new")
+    .takes_value(true)
+  )
+  .arg(
+    Arg::with_name("limit")
+    .short("l")
+    .long("limit")
+    .help("The maximum number of cards you want to study, overriding session.size")
+    .takes_value(true)
+  )
+}
+
+fn parse_count(matches: &ArgMatches, name: &'static str) -> std::result::Result<Option<usize>, RecallError> {
+  matches.value_of(name)
+    .map(|x| {
+      x.parse::<usize>()
+      .map_err(|_| RecallError::InvalidArgument {
+        name,
+        value: x.to_string()
+      })
+    })
+    .transpose()
+}
+
+pub fn dispatch(matches: &ArgMatches) -> Result {
+  let config = app::config(matches)?;
+  let store = app::store(&config);
+  let clock = cli::clock_from_matches(matches)?;
+  let dry_run = cli::is_dry_run(matches);
+  let mut rng = cli::rng_from_matches(matches)?;
+  let mut term = cli::terminal_from_matches(matches);
+  let names = matches.values_of("names")
+    .map(|names| names.collect::<Vec<_>>());
+  let order = cli::order_from_matches(matches);
+  let new_count = parse_count(matches, "new")?;
+  let reviews_per_new = parse_count(matches, "reviews_per_new")?
+    .unwrap_or_else(|| config.reviews_per_new());
+  let limit = parse_count(matches, "limit")?;
+
+  if let Some(name) = names.as_ref().and_then(|names| names.iter().find(|name| !store.deck_exists(name))) {
+    return Err(RecallError::ListNotFound(name.to_string()));
+  }
+
+  println!();
+  cli::print_header_strip("Study");
+
+  let mut limits_reached = Vec::new();
+  let checkpoint = if dry_run {
+    None
+  } else {
+    session::prompt_resume(term.as_mut(), &store, &config, store.dirname(), "study", clock.now())?
+  };
+  let (cards, results) = match checkpoint {
+    Some(checkpoint) => (checkpoint.queue, checkpoint.results),
+    None => {
+      let (mut new, mut reviews): (Vec<_>, Vec<_>) = get_cards(
+        &store,
+        store.decks()?
+        .into_iter()
+        .filter(|deck| {
+          names.as_ref()
+          .map(|names| names.contains(&deck.as_str()))
+          .unwrap_or(true)
+        })
+      )
+      .into_iter()
+      .filter(|(_, card)| card.is_available_at(clock.now()))
+      .filter(|(deck, card)| card.is_new(config.ladder(deck)) || card.is_due_at(clock.now()))
+      .partition(|(deck, card)| card.is_new(config.ladder(deck)));
+
+      let counts = session::load_daily_counts(store.dirname(), clock.now())?;
+      let remaining_reviews = session::remaining(config.reviews_per_day(), counts.reviewed);
+      let remaining_new = session::remaining(config.new_per_day(), counts.learned);
+
+      if remaining_reviews == Some(0) && !reviews.is_empty() {
+        limits_reached.push(format!("Daily limit of {} review(s) reached.", config.reviews_per_day().unwrap_or(0)));
+      }
+
+      if remaining_new == Some(0) && !new.is_empty() && new_count != Some(0) {
+        limits_reached.push(format!("Daily limit of {} new card(s) reached.", config.new_per_day().unwrap_or(0)));
+      }
+
+      session::order_cards(&mut reviews, order, &config, clock.now(), &mut rng);
+      new.shuffle(&mut rng);
+
+      if let Some(limit) = remaining_reviews {
+        reviews.truncate(limit);
+      }
+
+      if let Some(limit) = remaining_new.into_iter().chain(new_count).min() {
+        new.truncate(limit);
+      }
+
+      let mut cards = session::mix_cards(reviews, new, reviews_per_new);
+
+      if let Some(limit) = limit.or_else(|| config.session_size()) {
+        cards.truncate(limit);
+      }
+
+      (cards, Vec::new())
+    }
+  };
+
+  if !limits_reached.is_empty() {
+    println!();
+
+    for message in limits_reached.iter() {
+      println!("{}", message);
+    }
+  }
+
+  if !cards.is_empty() || !results.is_empty() {
+    let checkpoint_dir = if dry_run { None } else { Some((store.dirname(), "study")) };
+    println!();
+    let cards = session::loop_cards(term.as_mut(), &store, &config, checkpoint_dir, cards, results, clock.as_ref(), &mut rng)?;
+    let answered = cards.iter()
+      .filter(|&&(_, _, outcome)| outcome == Outcome::Remembered || outcome == Outcome::Forgotten);
+    // Cards in learning are counted with the new ones, not as reviews.
+    let (learned, reviewed): (Vec<_>, Vec<_>) = answered
+      .partition(|(deck, card, _)| card.proficiency(config.ladder(deck)) == Proficiency::Inactive);
+    let learned_count = learned.len();
+    let (reviewed_count, remembered_count) = reviewed.into_iter()
+      .fold((0, 0), |(reviewed, remembered), &(_, _, outcome)| {
+        (reviewed + 1, remembered + (outcome == Outcome::Remembered) as usize)
+      });
+    println!();
+    println!("Learned {} card(s).", learned_count);
+    println!("Reviewed {} card(s).", reviewed_count);
+    println!();
+
+    if reviewed_count > 0 {
+      println!("Remembered:  {}", cli::progress_bar(remembered_count as f64 / reviewed_count as f64, 18));
+      println!();
+    }
+
+    if dry_run {
+      cli::print_help_strip("Dry run at the time given by --now, no answers were saved.");
+      println!();
+    } else {
+      session::commit(&store, &config, store.dirname(), "study", cards, clock.now())?;
+    }
+  } else if !limits_reached.is_empty() {
+    println!();
+    cli::print_help_strip(
+      format_args!(
+        "Execute {} to change the limits",
+        cli::inline_code(format_args!("{} config set limits.<name> <count>", app::name()))
+      )
+    );
+    println!();
+  } else {
+    println!();
+    println!("No cards to review or learn right now.");
+    println!();
+    cli::print_help_strip(
+      format_args!(
+        "Check {} to see when review is due.",
+        cli::inline_code(format_args!("{} info", app::name()))
+      )
+    );
+    println!();
+  }
+
+  Ok(())
+}
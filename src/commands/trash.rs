@@ -0,0 +1,120 @@
+use clap::{ArgMatches, App, SubCommand};
+use chrono::Local;
+use serde_json::{json, Value};
+use super::Result;
+use recall::{storage::Storage, trash};
+use crate::{app, cli::{self, Format}};
+
+pub fn subcommand<'a>() -> App<'a, 'static> {
+  SubCommand::with_name("trash")
+  .about("Shows the removed lists")
+  .subcommand(
+    SubCommand::with_name("empty")
+    .about("Deletes all removed lists for good")
+    .arg(cli::yes_arg())
+  )
+}
+
+pub fn dispatch(matches: &ArgMatches) -> Result {
+  match matches.subcommand() {
+    ("empty", Some(matches)) => empty(matches),
+    _ => show(matches)
+  }
+}
+
+fn show(matches: &ArgMatches) -> Result {
+  let format = Format::from_matches(matches);
+  let store = app::store(&app::config(matches)?);
+  let lists = trash::trashed_lists(store.dirname())?;
+  let rows = lists.iter()
+    .map(|trashed| {
+      let count = trashed.list.cards().map(|cards| cards.len()).unwrap_or(0);
+      (trashed.list.name(), trashed.removed_at, count)
+    })
+    .collect::<Vec<_>>();
+
+  match format {
+    Format::Json => {
+      cli::print_json(&Value::Array(
+        rows.iter()
+        .map(|(name, removed_at, count)| json!({
+          "name": name,
+          "removed_at": removed_at.to_rfc3339(),
+          "cards": count
+        }))
+        .collect()
+      ));
+    },
+    Format::Tsv => {
+      cli::print_tsv(
+        rows.iter()
+        .map(|(name, removed_at, count)| vec![
+          name.to_string(),
+          removed_at.to_rfc3339(),
+          count.to_string()
+        ])
+      );
+    },
+    Format::Text => {
+      println!();
+      cli::print_header_strip("Trash");
+      println!();
+
+      if rows.is_empty() {
+        println!("The trash is empty.");
+      } else {
+        cli::print_bullet_list(
+          rows.iter()
+          .map(|(name, removed_at, count)| format!(
+            "{} ({} cards), removed {}",
+            name,
+            count,
+            removed_at.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+          ))
+        );
+        println!();
+        cli::print_help_strip(format_args!(
+          "Execute {} to restore a list",
+          cli::inline_code(format_args!("{} list restore <name>", app::name()))
+        ));
+      }
+
+      println!();
+    }
+  }
+
+  Ok(())
+}
+
+fn empty(matches: &ArgMatches) -> Result {
+  let store = app::store(&app::config(matches)?);
+  let count = trash::trashed_lists(store.dirname())?.len();
+
+  println!();
+
+  if count == 0 {
+    println!("The trash is empty.");
+    println!();
+
+    return Ok(());
+  }
+
+  if !matches.is_present("yes") && !cli::confirm(format_args!("Delete {} list(s) for good?", count))? {
+    println!();
+    println!("The trash was not emptied.");
+    println!();
+
+    return Ok(());
+  }
+
+  let count = trash::empty(store.dirname())?;
+
+  if !matches.is_present("yes") {
+    println!();
+  }
+
+  println!("Deleted {} list(s).", count);
+  println!();
+
+  Ok(())
+}
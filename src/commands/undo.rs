@@ -0,0 +1,63 @@
+use clap::{ArgMatches, App, SubCommand};
+use super::{RecallError, Result};
+use recall::{session, card::{locate_cards, update_cards}};
+use crate::{cli, app};
+
+pub fn subcommand<'a>() -> App<'a, 'static> {
+  SubCommand::with_name("undo")
+  .about("Restores all cards touched by the last session")
+}
+
+pub fn dispatch(matches: &ArgMatches) -> Result {
+  let store = app::store(&app::config(matches)?);
+  let cards = session::load_undo(store.dirname())?;
+
+  println!();
+
+  let cards = match cards {
+    Some(cards) => cards,
+    None => {
+      println!("Nothing to undo.");
+      println!();
+      return Ok(());
+    }
+  };
+
+  // The lists may have changed since, so cards are found again by their front.
+
+  let lines = locate_cards(&store, &cards);
+  let missing_count = lines.iter().filter(|line| line.is_none()).count();
+
+  if missing_count > 0 && missing_count == cards.len() {
+    return Err(RecallError::other(format!(
+      "None of the {} card(s) of the last session were found, they may have been changed or removed since.",
+      missing_count
+    )));
+  }
+
+  let cards = cards.into_iter()
+    .zip(lines)
+    .filter_map(|((deck, mut card), line)| {
+      card.line_number = Some(line?);
+      Some((deck, card))
+    })
+    .collect::<Vec<_>>();
+
+  let restored = update_cards(&store, cards)?;
+
+  session::clear_undo(store.dirname())?;
+
+  println!("Restored {} card(s).", restored.len());
+
+  if missing_count > 0 {
+    println!();
+    cli::print_error_strip(format_args!(
+      "{} card(s) were not found and could not be restored.",
+      missing_count
+    ));
+  }
+
+  println!();
+
+  Ok(())
+}
@@ -0,0 +1,923 @@
+//! Settings read from TOML files, environment variables and command line
+//! options. Later sources override earlier ones:
+//!
+//! 1. `recall/config.toml` in `$XDG_CONFIG_DIRS` (`/etc/xdg`) and then in
+//!    `$XDG_CONFIG_HOME` (`~/.config`), the global config
+//! 2. `.recall/config.toml` in the deck directory, the local config
+//! 3. `RECALL_*` environment variables, e.g. `RECALL_SESSION_SIZE`
+//! 4. Command line options like `--deck-dir` and `--color`
+//!
+//! ```toml
+//! deck_dir = "~/decks"
+//! color = "auto"
+//! editor = "vim"
+//!
+//! [limits]
+//! new_per_day = 20
+//! reviews_per_day = 200
+//!
+//! [session]
+//! size = 50
+//! reviews_per_new = 4
+//!
+//! [scheduler]
+//! intervals = ["4h", "8h", "1d", "3d", "7d", "14d", "30d", "122d", "182d"]
+//! penalty = 2
+//! max_level = 9
+//! learning_steps = ["1m", "10m", "1h"]
+//!
+//! [scheduler.proficiency]
+//! apprentice = 4
+//! guru = 6
+//! master = 7
+//! enlightened = 8
+//!
+//! [lists.spanish.scheduler]
+//! intervals = ["1d", "3d", "7d", "30d"]
+//! ```
+//!
+//! Settings of a list override the global ones, missing settings fall back to
+//! them.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::env;
+use std::fmt::Display;
+use std::fs::{create_dir_all, read_to_string, write};
+use std::path::{Path, PathBuf};
+use chrono::Duration;
+use toml::{Table, Value};
+use toml_edit::DocumentMut;
+use crate::ladder::{format_interval, Ladder, THRESHOLD_NAMES, parse_interval};
+use crate::error::{RecallError, Result, WithPath};
+
+/// Where a setting comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+  Default,
+  Global(PathBuf),
+  Local(PathBuf),
+  Env(String),
+  Flag(String)
+}
+
+impl Display for Origin {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Origin::Default => write!(f, "default"),
+      Origin::Global(path) | Origin::Local(path) => write!(f, "'{}'", path.display()),
+      Origin::Env(name) => write!(f, "${}", name),
+      Origin::Flag(name) => write!(f, "--{}", name)
+    }
+  }
+}
+
+/// The settings of one source.
+#[derive(Debug, Clone)]
+pub struct Layer {
+  origin: Origin,
+  table: Table
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Color {
+  Auto,
+  Always,
+  Never
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Kind {
+  Text,
+  Integer,
+  Intervals
+}
+
+#[derive(Debug)]
+pub struct Key {
+  pub name: &'static str,
+  pub kind: Kind,
+  pub description: &'static str
+}
+
+/// All settings. Scheduler settings can also be given per list, e.g.
+/// `lists.spanish.scheduler.penalty`.
+pub const KEYS: &[Key] = &[
+  Key { name: "deck_dir", kind: Kind::Text, description: "Directory of the lists" },
+  Key { name: "color", kind: Kind::Text, description: "Colored output: auto, always or never" },
+  Key { name: "editor", kind: Kind::Text, description: "Command to edit cards with" },
+  Key { name: "limits.new_per_day", kind: Kind::Integer, description: "Maximum number of cards learned per day" },
+  Key { name: "limits.reviews_per_day", kind: Kind::Integer, description: "Maximum number of cards reviewed per day" },
+  Key { name: "session.size", kind: Kind::Integer, description: "Maximum number of cards per session" },
+  Key { name: "session.reviews_per_new", kind: Kind::Integer, description: "Reviews between two new cards in `recall study`" },
+  Key { name: "scheduler.intervals", kind: Kind::Intervals, description: "Intervals of the levels" },
+  Key { name: "scheduler.penalty", kind: Kind::Integer, description: "Levels lost when forgetting a card" },
+  Key { name: "scheduler.max_level", kind: Kind::Integer, description: "Number of levels, must match the intervals" },
+  Key { name: "scheduler.learning_steps", kind: Kind::Intervals, description: "Steps of new cards before level 1" },
+  Key { name: "scheduler.proficiency.apprentice", kind: Kind::Integer, description: "Highest level of apprentice cards" },
+  Key { name: "scheduler.proficiency.guru", kind: Kind::Integer, description: "Highest level of guru cards" },
+  Key { name: "scheduler.proficiency.master", kind: Kind::Integer, description: "Highest level of master cards" },
+  Key { name: "scheduler.proficiency.enlightened", kind: Kind::Integer, description: "Highest level of enlightened cards" }
+];
+
+/// Splits a key into the path of tables leading to it. List names may
+/// contain dots, so they are taken as a whole.
+fn key_path(name: &str) -> Option<Vec<String>> {
+  if let Some(rest) = name.strip_prefix("lists.") {
+    let index = rest.find(".scheduler.")?;
+    let (list, scheduler) = rest.split_at(index);
+
+    return Some(
+      vec!["lists".to_string(), list.to_string()].into_iter()
+      .chain(scheduler[1..].split('.').map(String::from))
+      .collect()
+    );
+  }
+
+  Some(name.split('.').map(String::from).collect())
+}
+
+/// Looks up a key, including per-list scheduler keys.
+pub fn find_key(name: &str) -> Option<&'static Key> {
+  let scheduler_key = name.strip_prefix("lists.")
+    .and_then(|rest| rest.find(".scheduler.").map(|index| &rest[index + 1..]));
+
+  KEYS.iter().find(|key| key.name == scheduler_key.unwrap_or(name))
+}
+
+/// The environment variable of a key, e.g. `RECALL_SESSION_SIZE`.
+pub fn env_var(key: &Key) -> String {
+  format!("RECALL_{}", key.name.to_uppercase().replace('.', "_"))
+}
+
+/// Parses a value given on the command line or in the environment.
+pub fn parse_value(kind: Kind, text: &str) -> std::result::Result<Value, String> {
+  match kind {
+    Kind::Text => Ok(Value::String(text.to_string())),
+    Kind::Integer => {
+      text.trim().parse::<i64>()
+      .map(Value::Integer)
+      .map_err(|_| format!("Expected an integer, found '{}'.", text))
+    },
+    Kind::Intervals => {
+      Ok(Value::Array(
+        text.split(',')
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .map(|x| Value::String(x.to_string()))
+        .collect()
+      ))
+    }
+  }
+}
+
+/// Formats a value the way [`parse_value`] reads it.
+pub fn format_value(value: &Value) -> String {
+  match value {
+    Value::String(x) => x.clone(),
+    Value::Array(values) => values.iter().map(format_value).collect::<Vec<_>>().join(","),
+    value => value.to_string()
+  }
+}
+
+fn lookup<'a>(table: &'a Table, path: &[String]) -> Option<&'a Value> {
+  let (last, tables) = path.split_last()?;
+
+  tables.iter()
+  .try_fold(table, |table, name| table.get(name).and_then(Value::as_table))
+  .and_then(|table| table.get(last))
+}
+
+fn insert(table: &mut Table, path: &[String], value: Value) {
+  let (last, tables) = path.split_last().expect("empty key");
+  let mut table = table;
+
+  for name in tables {
+    let entry = table.entry(name.clone()).or_insert_with(|| Value::Table(Table::new()));
+
+    if !entry.is_table() {
+      *entry = Value::Table(Table::new());
+    }
+
+    table = entry.as_table_mut().unwrap();
+  }
+
+  table.insert(last.clone(), value);
+}
+
+fn merge(base: &mut Table, other: &Table) {
+  for (name, value) in other.iter() {
+    match (base.get_mut(name), value) {
+      (Some(Value::Table(base)), Value::Table(other)) => merge(base, other),
+      _ => {
+        base.insert(name.clone(), value.clone());
+      }
+    }
+  }
+}
+
+/// Returns the path of the global config file, whether it exists or not.
+pub fn global_path() -> Option<PathBuf> {
+  env::var_os("XDG_CONFIG_HOME")
+  .filter(|x| !x.is_empty())
+  .map(PathBuf::from)
+  .or_else(|| env::var_os("HOME").map(|x| PathBuf::from(x).join(".config")))
+  .map(|x| x.join("recall").join("config.toml"))
+}
+
+/// Returns the paths of the system-wide config files, least important first.
+fn system_paths() -> Vec<PathBuf> {
+  let dirs = env::var("XDG_CONFIG_DIRS")
+    .ok()
+    .filter(|x| !x.is_empty())
+    .unwrap_or_else(|| "/etc/xdg".to_string());
+
+  dirs.split(':')
+  .rev()
+  .filter(|x| !x.is_empty())
+  .map(|x| Path::new(x).join("recall").join("config.toml"))
+  .collect()
+}
+
+/// Returns the path of the config file of a deck directory.
+pub fn local_path<P: AsRef<Path>>(deck_dir: P) -> PathBuf {
+  deck_dir.as_ref().join(".recall").join("config.toml")
+}
+
+fn expand_home(path: &str) -> PathBuf {
+  match (path.strip_prefix("~/"), env::var_os("HOME")) {
+    (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+    _ => PathBuf::from(path)
+  }
+}
+
+fn read_ladder(table: &Table, base: &Ladder) -> std::result::Result<Ladder, String> {
+  let read_int = |table: &Table, key: &str, default: i8| -> std::result::Result<i8, String> {
+    match table.get(key) {
+      None => Ok(default),
+      Some(Value::Integer(x)) if i8::try_from(*x).is_ok() => Ok(*x as i8),
+      Some(value) => Err(format!("`{}` should be a small integer, found `{}`.", key, value))
+    }
+  };
+
+  let read_intervals = |key: &str, default: &[Duration]| -> std::result::Result<Vec<Duration>, String> {
+    match table.get(key) {
+      None => Ok(default.to_vec()),
+      Some(Value::Array(values)) => {
+        values.iter()
+        .map(|value| match value {
+          Value::String(x) => parse_interval(x),
+          value => Err(format!("Intervals should be strings like \"1d\", found `{}`.", value))
+        })
+        .collect()
+      },
+      Some(value) => Err(format!("`{}` should be an array, found `{}`.", key, value))
+    }
+  };
+
+  let intervals = read_intervals("intervals", base.intervals())?;
+  let learning_steps = read_intervals("learning_steps", base.learning_steps())?;
+
+  // Settings inherited from `base` are capped to fit the intervals, like in
+  // `Ladder::parse`, so that overriding only the intervals works. Settings
+  // given here are checked as they are.
+
+  let max_level = intervals.len().min(i8::MAX as usize) as i8;
+  let cap = |x: i8| x.min(max_level - 1).max(0);
+  let penalty = read_int(table, "penalty", cap(base.penalty()))?;
+
+  if table.contains_key("max_level") {
+    let max_level = read_int(table, "max_level", 0)?;
+
+    if max_level < 0 || max_level as usize != intervals.len() {
+      return Err(format!(
+        "`max_level` is {}, but there are {} intervals.",
+        max_level,
+        intervals.len()
+      ));
+    }
+  }
+
+  let mut thresholds = base.thresholds().map(cap);
+
+  match table.get("proficiency") {
+    None => {},
+    Some(Value::Table(proficiency)) => {
+      for (threshold, name) in thresholds.iter_mut().zip(THRESHOLD_NAMES.iter()) {
+        *threshold = read_int(proficiency, name, *threshold)?;
+      }
+    },
+    Some(value) => return Err(format!("`proficiency` should be a table, found `{}`.", value))
+  }
+
+  Ladder::new(intervals, penalty, thresholds)?.with_learning_steps(learning_steps)
+}
+
+fn ladder_value(ladder: &Ladder, path: &[&str]) -> Option<Value> {
+  match path {
+    ["intervals"] => Some(Value::Array(
+      ladder.intervals().iter()
+      .map(|&x| Value::String(format_interval(x)))
+      .collect()
+    )),
+    ["penalty"] => Some(Value::Integer(ladder.penalty().into())),
+    ["learning_steps"] => Some(Value::Array(
+      ladder.learning_steps().iter()
+      .map(|&x| Value::String(format_interval(x)))
+      .collect()
+    )),
+    ["max_level"] => Some(Value::Integer(ladder.max_level().into())),
+    ["proficiency", name] => {
+      THRESHOLD_NAMES.iter()
+      .position(|x| x == name)
+      .map(|i| Value::Integer(ladder.thresholds()[i].into()))
+    },
+    _ => None
+  }
+}
+
+impl Layer {
+  /// Reads a config file. A missing file results in `None`.
+  pub fn read(origin: Origin) -> Result<Option<Layer>> {
+    let path = match &origin {
+      Origin::Global(path) | Origin::Local(path) => path.clone(),
+      _ => panic!("only files can be read")
+    };
+
+    if !path.exists() {
+      return Ok(None);
+    }
+
+    let text = read_to_string(&path).with_path(&path)?;
+    Layer::parse(&text, origin).map(Some)
+  }
+
+  pub fn parse(text: &str, origin: Origin) -> Result<Layer> {
+    let table = text.parse::<Table>()
+      .map_err(|err| invalid(&origin, err.message().to_string()))?;
+
+    Ok(Layer { origin, table })
+  }
+
+  /// A single setting given on the command line, e.g. `--deck-dir`.
+  pub fn flag(key: &str, flag: &str, value: &str) -> Result<Layer> {
+    Layer::single(Origin::Flag(flag.to_string()), key, value)
+  }
+
+  /// Settings given as `RECALL_*` environment variables.
+  pub fn env() -> Result<Vec<Layer>> {
+    KEYS.iter()
+    .filter_map(|key| env::var(env_var(key)).ok().map(|value| (key, value)))
+    .map(|(key, value)| Layer::single(Origin::Env(env_var(key)), key.name, &value))
+    .collect()
+  }
+
+  fn single(origin: Origin, name: &str, text: &str) -> Result<Layer> {
+    let key = find_key(name)
+      .ok_or_else(|| invalid(&origin, format!("Unknown setting `{}`.", name)))?;
+    let value = parse_value(key.kind, text)
+      .map_err(|message| invalid(&origin, message))?;
+    let mut table = Table::new();
+
+    insert(&mut table, &key_path(name).unwrap(), value);
+    Ok(Layer { origin, table })
+  }
+
+  pub fn origin(&self) -> &Origin {
+    &self.origin
+  }
+}
+
+fn invalid(origin: &Origin, message: String) -> RecallError {
+  RecallError::InvalidConfig {
+    origin: origin.to_string(),
+    message
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+  layers: Vec<Layer>,
+  table: Table,
+  deck_dir: PathBuf,
+  color: Color,
+  editor: Option<String>,
+  new_per_day: Option<usize>,
+  reviews_per_day: Option<usize>,
+  session_size: Option<usize>,
+  reviews_per_new: Option<usize>,
+  scheduler: Ladder,
+  lists: BTreeMap<String, Ladder>
+}
+
+impl Default for Config {
+  fn default() -> Config {
+    Config::from_layers(Vec::new()).unwrap()
+  }
+}
+
+impl Config {
+  /// Reads the global config, the environment, the given options and then the
+  /// local config of the resulting deck directory.
+  pub fn load(flags: Vec<Layer>) -> Result<Config> {
+    let mut layers = Vec::new();
+
+    for path in system_paths().into_iter().chain(global_path()) {
+      layers.extend(Layer::read(Origin::Global(path))?);
+    }
+
+    let mut overrides = Layer::env()?;
+    overrides.extend(flags);
+
+    let deck_dir = Config::from_layers(layers.iter().chain(overrides.iter()).cloned().collect())?
+      .deck_dir;
+
+    layers.extend(Layer::read(Origin::Local(local_path(deck_dir)))?);
+    layers.extend(overrides);
+    Config::from_layers(layers)
+  }
+
+  /// Merges the layers in order, checking the settings after every layer so
+  /// errors point to the source that introduced them.
+  pub fn from_layers(layers: Vec<Layer>) -> Result<Config> {
+    let mut config = Config::read(Table::new()).unwrap();
+
+    for layer in layers.iter() {
+      if let Origin::Local(_) = layer.origin {
+        if layer.table.contains_key("deck_dir") {
+          return Err(invalid(&layer.origin, "`deck_dir` can only be set globally.".to_string()));
+        }
+      }
+
+      let mut table = config.table;
+      merge(&mut table, &layer.table);
+      config = Config::read(table).map_err(|message| invalid(&layer.origin, message))?;
+    }
+
+    config.layers = layers;
+    Ok(config)
+  }
+
+  fn read(table: Table) -> std::result::Result<Config, String> {
+    let read_text = |key: &str| -> std::result::Result<Option<String>, String> {
+      match table.get(key) {
+        None => Ok(None),
+        Some(Value::String(x)) => Ok(Some(x.clone())),
+        Some(value) => Err(format!("`{}` should be a string, found `{}`.", key, value))
+      }
+    };
+
+    let read_count = |table: Option<&Value>, section: &str, key: &str| -> std::result::Result<Option<usize>, String> {
+      match table.and_then(|table| table.get(key)) {
+        None => Ok(None),
+        Some(Value::Integer(x)) if *x >= 0 => Ok(Some(*x as usize)),
+        Some(value) => {
+          Err(format!("`{}.{}` should be a positive integer, found `{}`.", section, key, value))
+        }
+      }
+    };
+
+    for section in ["limits", "session", "scheduler", "lists"].iter() {
+      match table.get(*section) {
+        None | Some(Value::Table(_)) => {},
+        Some(_) => return Err(format!("`{}` should be a table.", section))
+      }
+    }
+
+    let deck_dir = read_text("deck_dir")?
+      .map(|x| expand_home(&x))
+      .unwrap_or_else(|| PathBuf::from("."));
+
+    let color = match read_text("color")?.as_deref() {
+      None | Some("auto") => Color::Auto,
+      Some("always") => Color::Always,
+      Some("never") => Color::Never,
+      Some(value) => {
+        return Err(format!("`color` should be auto, always or never, found '{}'.", value));
+      }
+    };
+
+    let editor = read_text("editor")?;
+    let limits = table.get("limits");
+    let new_per_day = read_count(limits, "limits", "new_per_day")?;
+    let reviews_per_day = read_count(limits, "limits", "reviews_per_day")?;
+    let session_size = read_count(table.get("session"), "session", "size")?;
+    let reviews_per_new = read_count(table.get("session"), "session", "reviews_per_new")?;
+
+    let scheduler = match table.get("scheduler").and_then(Value::as_table) {
+      None => Ladder::default(),
+      Some(scheduler) => {
+        read_ladder(scheduler, &Ladder::default())
+        .map_err(|message| format!("[scheduler] {}", message))?
+      }
+    };
+
+    let mut lists = BTreeMap::new();
+
+    for (name, value) in table.get("lists").and_then(Value::as_table).into_iter().flatten() {
+      let ladder = match value.get("scheduler") {
+        None => continue,
+        Some(Value::Table(list_scheduler)) => {
+          read_ladder(list_scheduler, &scheduler)
+          .map_err(|message| format!("[lists.{}.scheduler] {}", name, message))?
+        },
+        Some(_) => return Err(format!("`lists.{}.scheduler` should be a table.", name))
+      };
+
+      lists.insert(name.clone(), ladder);
+    }
+
+    Ok(Config {
+      layers: Vec::new(),
+      table,
+      deck_dir,
+      color,
+      editor,
+      new_per_day,
+      reviews_per_day,
+      session_size,
+      reviews_per_new,
+      scheduler,
+      lists
+    })
+  }
+
+  /// The sources of the settings, least important first.
+  pub fn layers(&self) -> &[Layer] {
+    &self.layers
+  }
+
+  /// The effective value of a setting, or `None` if it is unset. Scheduler
+  /// settings are always set, possibly to their defaults.
+  pub fn get(&self, name: &str) -> Option<Value> {
+    let path = key_path(name)?;
+    find_key(name)?;
+
+    let scheduler = path.iter().position(|x| x == "scheduler");
+    let ladder = match (path.first().map(String::as_str), scheduler) {
+      (Some("scheduler"), _) => Some(&self.scheduler),
+      (Some("lists"), Some(_)) => Some(self.ladder(&path[1])),
+      _ => None
+    };
+
+    match (ladder, scheduler) {
+      (Some(ladder), Some(index)) => {
+        let rest = path[index + 1..].iter().map(String::as_str).collect::<Vec<_>>();
+        ladder_value(ladder, &rest)
+      },
+      _ => {
+        lookup(&self.table, &path).cloned().or_else(|| match name {
+          "deck_dir" => Some(Value::String(".".to_string())),
+          "color" => Some(Value::String("auto".to_string())),
+          _ => None
+        })
+      }
+    }
+  }
+
+  /// The most important source that sets the given setting.
+  pub fn origin(&self, name: &str) -> Origin {
+    key_path(name)
+    .and_then(|path| {
+      self.layers.iter().rev()
+      .find(|layer| lookup(&layer.table, &path).is_some())
+    })
+    .map(|layer| layer.origin.clone())
+    .unwrap_or(Origin::Default)
+  }
+
+  /// Names of all settings: the global ones and the scheduler settings of
+  /// every list that has some.
+  pub fn keys(&self) -> Vec<String> {
+    let mut keys = KEYS.iter().map(|key| key.name.to_string()).collect::<Vec<_>>();
+
+    for list in self.lists.keys() {
+      keys.extend(
+        KEYS.iter()
+        .filter_map(|key| key.name.strip_prefix("scheduler."))
+        .map(|name| format!("lists.{}.scheduler.{}", list, name))
+      );
+    }
+
+    keys
+  }
+
+  pub fn deck_dir(&self) -> &Path {
+    &self.deck_dir
+  }
+
+  pub fn color(&self) -> Color {
+    self.color
+  }
+
+  /// The editor command: `editor`, `$VISUAL` or `$EDITOR`.
+  pub fn editor(&self) -> Option<String> {
+    self.editor.clone()
+    .or_else(|| env::var("VISUAL").ok().filter(|x| !x.is_empty()))
+    .or_else(|| env::var("EDITOR").ok().filter(|x| !x.is_empty()))
+  }
+
+  pub fn new_per_day(&self) -> Option<usize> {
+    self.new_per_day
+  }
+
+  pub fn reviews_per_day(&self) -> Option<usize> {
+    self.reviews_per_day
+  }
+
+  pub fn session_size(&self) -> Option<usize> {
+    self.session_size
+  }
+
+  /// The number of reviews shown before every new card in a study session,
+  /// 4 by default.
+  pub fn reviews_per_new(&self) -> usize {
+    self.reviews_per_new.unwrap_or(4)
+  }
+
+  /// The global scheduler settings.
+  pub fn scheduler(&self) -> &Ladder {
+    &self.scheduler
+  }
+
+  /// The scheduler settings of the given list.
+  pub fn ladder(&self, list: &str) -> &Ladder {
+    self.lists.get(list).unwrap_or(&self.scheduler)
+  }
+
+  /// Lists with scheduler settings of their own.
+  pub fn lists(&self) -> impl Iterator<Item = (&str, &Ladder)> {
+    self.lists.iter().map(|(name, ladder)| (name.as_str(), ladder))
+  }
+
+  /// Changes a setting in the given config file, keeping its comments and
+  /// formatting. The file is only written if the resulting settings are valid.
+  pub fn set(&self, origin: Origin, name: &str, text: &str) -> Result<()> {
+    let path = match &origin {
+      Origin::Global(path) | Origin::Local(path) => path.clone(),
+      _ => panic!("only files can be changed")
+    };
+
+    let invalid_key = || RecallError::InvalidArgument {
+      name: "key",
+      value: name.to_string()
+    };
+
+    let key = find_key(name).ok_or_else(invalid_key)?;
+    let key_path = key_path(name).ok_or_else(invalid_key)?;
+    let value = parse_value(key.kind, text)
+      .map_err(|_| RecallError::InvalidArgument {
+        name: "value",
+        value: text.to_string()
+      })?;
+
+    let text = if path.exists() {
+      read_to_string(&path).with_path(&path)?
+    } else {
+      String::new()
+    };
+
+    let mut document = text.parse::<DocumentMut>()
+      .map_err(|err| invalid(&origin, err.message().to_string()))?;
+    let (last, tables) = key_path.split_last().unwrap();
+    let mut table = document.as_table_mut();
+
+    for name in tables {
+      table = table.entry(name)
+        .or_insert_with(|| {
+          let mut table = toml_edit::Table::new();
+          table.set_implicit(true);
+          toml_edit::Item::Table(table)
+        })
+        .as_table_mut()
+        .ok_or_else(|| invalid(&origin, format!("`{}` should be a table.", name)))?;
+    }
+
+    table.insert(last, toml_edit::value(match value {
+      Value::Integer(x) => toml_edit::Value::from(x),
+      Value::Array(values) => {
+        toml_edit::Value::Array(values.iter().map(format_value).collect())
+      },
+      value => toml_edit::Value::from(format_value(&value))
+    }));
+
+    let text = document.to_string();
+    let changed = Layer::parse(&text, origin.clone())?;
+    let mut layers = self.layers.iter()
+      .filter(|layer| layer.origin != origin)
+      .cloned()
+      .collect::<Vec<_>>();
+    let position = match origin {
+      Origin::Global(_) => layers.iter()
+        .position(|layer| !matches!(layer.origin, Origin::Global(_)))
+        .unwrap_or(layers.len()),
+      _ => layers.iter()
+        .position(|layer| matches!(layer.origin, Origin::Env(_) | Origin::Flag(_)))
+        .unwrap_or(layers.len())
+    };
+
+    layers.insert(position, changed);
+    Config::from_layers(layers)?;
+
+    if let Some(dir) = path.parent() {
+      create_dir_all(dir).with_path(dir)?;
+    }
+
+    write(&path, text).with_path(&path)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs::remove_dir_all;
+  use chrono::Duration;
+  use super::*;
+
+  fn temp_dir(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("recall-test-{}-{}", std::process::id(), name))
+  }
+
+  fn layer(text: &str, origin: Origin) -> Layer {
+    Layer::parse(text, origin).unwrap()
+  }
+
+  fn parse(text: &str) -> Result<Config> {
+    Config::from_layers(vec![Layer::parse(text, Origin::Default)?])
+  }
+
+  fn days(days: &[i64]) -> Vec<Duration> {
+    days.iter().map(|&x| Duration::days(x)).collect()
+  }
+
+  #[test]
+  fn later_layers_override_earlier_ones() {
+    let global = Origin::Global(PathBuf::from("global.toml"));
+    let local = Origin::Local(PathBuf::from("local.toml"));
+    let env = Origin::Env("RECALL_SESSION_SIZE".to_string());
+    let config = Config::from_layers(vec![
+      layer("color = \"never\"\n[session]\nsize = 10\n[limits]\nnew_per_day = 5\nreviews_per_day = 50", global.clone()),
+      layer("[session]\nsize = 20\n[limits]\nnew_per_day = 6", local.clone()),
+      layer("[session]\nsize = 30", env.clone()),
+      Layer::flag("color", "color", "always").unwrap()
+    ]).unwrap();
+
+    assert_eq!(config.color(), Color::Always);
+    assert_eq!(config.session_size(), Some(30));
+    assert_eq!(config.new_per_day(), Some(6));
+    assert_eq!(config.reviews_per_day(), Some(50));
+    assert_eq!(config.origin("color"), Origin::Flag("color".to_string()));
+    assert_eq!(config.origin("session.size"), env);
+    assert_eq!(config.origin("limits.new_per_day"), local);
+    assert_eq!(config.origin("limits.reviews_per_day"), global);
+    assert_eq!(config.origin("editor"), Origin::Default);
+    assert_eq!(config.get("deck_dir"), Some(Value::String(".".to_string())));
+    assert_eq!(config.get("editor"), None);
+  }
+
+  #[test]
+  fn load_reads_global_local_env_and_flags() {
+    let dir = temp_dir("config-load");
+    let deck_dir = dir.join("decks");
+    let global = dir.join("home").join("recall").join("config.toml");
+
+    create_dir_all(global.parent().unwrap()).unwrap();
+    create_dir_all(deck_dir.join(".recall")).unwrap();
+    write(&global, format!(
+      "deck_dir = {:?}\ncolor = \"never\"\n[session]\nsize = 10\n[limits]\nnew_per_day = 5\nreviews_per_day = 50",
+      deck_dir.display().to_string()
+    )).unwrap();
+    write(local_path(&deck_dir), "[session]\nsize = 20\n[limits]\nnew_per_day = 6").unwrap();
+
+    env::set_var("XDG_CONFIG_HOME", dir.join("home"));
+    env::set_var("XDG_CONFIG_DIRS", dir.join("none"));
+    env::set_var("RECALL_SESSION_SIZE", "30");
+
+    let config = Config::load(vec![Layer::flag("color", "color", "always").unwrap()]);
+    let flagged = Config::load(vec![Layer::flag("session.size", "session-size", "40").unwrap()]);
+
+    env::remove_var("RECALL_SESSION_SIZE");
+    remove_dir_all(&dir).ok();
+
+    let config = config.unwrap();
+
+    assert_eq!(config.deck_dir(), deck_dir.as_path());
+    assert_eq!(config.color(), Color::Always);
+    assert_eq!(config.session_size(), Some(30));
+    assert_eq!(config.new_per_day(), Some(6));
+    assert_eq!(config.reviews_per_day(), Some(50));
+    assert_eq!(config.origin("limits.new_per_day"), Origin::Local(local_path(&deck_dir)));
+    assert_eq!(flagged.unwrap().session_size(), Some(40));
+  }
+
+  #[test]
+  fn lists_override_global_scheduler() {
+    let config = Config::from_layers(vec![
+      layer("[scheduler]\npenalty = 1\n[scheduler.proficiency]\nenlightened = 7", Origin::Default),
+      layer(
+        "[lists.spanish.scheduler]\nintervals = [\"1d\", \"2d\", \"3d\", \"4d\", \"5d\", \"6d\", \"7d\", \"8d\", \"9d\", \"10d\"]",
+        Origin::Default
+      ),
+      Layer::flag("lists.french.scheduler.penalty", "penalty", "3").unwrap()
+    ]).unwrap();
+
+    let spanish = config.ladder("spanish");
+    let french = config.ladder("french");
+
+    assert_eq!(config.scheduler().thresholds(), [4, 6, 7, 7]);
+    assert_eq!(spanish.intervals(), days(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]).as_slice());
+    assert_eq!((spanish.penalty(), spanish.thresholds()), (1, [4, 6, 7, 7]));
+    assert_eq!((french.intervals(), french.penalty()), (Ladder::default().intervals(), 3));
+    assert_eq!(config.ladder("german"), config.scheduler());
+    assert_eq!(config.get("lists.spanish.scheduler.max_level"), Some(Value::Integer(10)));
+    assert_eq!(config.get("lists.german.scheduler.penalty"), Some(Value::Integer(1)));
+    assert!(config.keys().contains(&"lists.french.scheduler.penalty".to_string()));
+    assert_eq!(config.lists().map(|(name, _)| name).collect::<Vec<_>>(), vec!["french", "spanish"]);
+  }
+
+  #[test]
+  fn rejects_invalid_settings() {
+    let texts = [
+      "[scheduler",
+      "scheduler = 1",
+      "color = \"blue\"",
+      "[session]\nsize = -1",
+      "[scheduler]\nintervals = \"1d\"",
+      "[scheduler]\nintervals = [1]",
+      "[scheduler]\npenalty = 1000",
+      "[scheduler]\nmax_level = 3",
+      "[scheduler.proficiency]\nguru = 2",
+      "[lists.spanish]\nscheduler = 1"
+    ];
+
+    for text in texts.iter() {
+      assert!(matches!(parse(text), Err(RecallError::InvalidConfig { .. })), "{}", text);
+    }
+
+    let local = Layer::parse("deck_dir = \"/tmp\"", Origin::Local(PathBuf::from("local.toml"))).unwrap();
+
+    assert!(Config::from_layers(vec![local]).is_err());
+    assert!(Layer::flag("session.length", "length", "1").is_err());
+    assert!(Layer::flag("session.size", "session-size", "many").is_err());
+  }
+
+  #[test]
+  fn set_keeps_comments_and_rejects_invalid_settings() {
+    let dir = temp_dir("config-set");
+    let path = dir.join("config.toml");
+    let origin = Origin::Global(path.clone());
+
+    create_dir_all(&dir).unwrap();
+    write(&path, "# Settings\ncolor = \"never\"\n").unwrap();
+
+    let config = Config::from_layers(vec![Layer::read(origin.clone()).unwrap().unwrap()]).unwrap();
+    let result = config.set(origin.clone(), "session.size", "25");
+    let invalid = config.set(origin.clone(), "scheduler.penalty", "20");
+    let text = read_to_string(&path).unwrap();
+
+    remove_dir_all(&dir).ok();
+    assert!(result.is_ok());
+    assert!(matches!(invalid, Err(RecallError::InvalidConfig { .. })));
+    assert_eq!(text, "# Settings\ncolor = \"never\"\n\n[session]\nsize = 25\n");
+  }
+
+  #[test]
+  fn shorter_intervals_cap_inherited_settings() {
+    let config = parse("[lists.spanish.scheduler]\nintervals = [\"1d\", \"3d\", \"7d\", \"30d\"]").unwrap();
+    let spanish = config.ladder("spanish");
+
+    assert_eq!(spanish.intervals(), days(&[1, 3, 7, 30]).as_slice());
+    assert_eq!((spanish.penalty(), spanish.thresholds()), (2, [3, 3, 3, 3]));
+    assert!(parse("[scheduler]\nintervals = [\"1d\", \"2d\"]\npenalty = 2").is_err());
+    assert!(parse("[scheduler]\nintervals = [\"1d\", \"2d\"]\n[scheduler.proficiency]\nguru = 2").is_err());
+
+    let dir = temp_dir("config-set-intervals");
+    let path = dir.join("config.toml");
+    let origin = Origin::Global(path.clone());
+
+    create_dir_all(&dir).unwrap();
+    write(&path, "").unwrap();
+
+    let config = Config::from_layers(vec![Layer::read(origin.clone()).unwrap().unwrap()]).unwrap();
+    let result = config.set(origin, "scheduler.intervals", "1d,2d");
+    let text = read_to_string(&path).unwrap();
+
+    remove_dir_all(&dir).ok();
+    assert!(result.is_ok());
+    assert_eq!(text, "[scheduler]\nintervals = [\"1d\", \"2d\"]\n");
+  }
+
+  #[test]
+  fn lists_inherit_learning_steps() {
+    let config = parse(
+      "[scheduler]\nlearning_steps = [\"1m\", \"10m\"]\n[lists.spanish.scheduler]\npenalty = 1\n[lists.french.scheduler]\nlearning_steps = []"
+    ).unwrap();
+
+    assert_eq!(config.ladder("spanish").learning_steps(), &[Duration::minutes(1), Duration::minutes(10)]);
+    assert!(config.ladder("french").learning_steps().is_empty());
+    assert_eq!(
+      config.get("scheduler.learning_steps"),
+      Some(Value::Array(vec![Value::String("1m".to_string()), Value::String("10m".to_string())]))
+    );
+    assert!(parse("[scheduler]\nlearning_steps = [\"0m\"]").is_err());
+  }
+}
@@ -0,0 +1,289 @@
+//! Detection of duplicate cards, by front or by back.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use crate::card::Card;
+use crate::error::{RecallError, Result};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Reason {
+  /// The fronts are equal.
+  Exact,
+  /// The fronts are equal after normalizing case and whitespace.
+  Normalized,
+  /// The normalized fronts are within the given edit distance.
+  Similar(usize),
+  /// The backs are equal after normalizing, the fronts are not.
+  SameBack
+}
+
+impl std::fmt::Display for Reason {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Reason::Exact => write!(f, "same front"),
+      Reason::Normalized => write!(f, "same front except case or whitespace"),
+      Reason::Similar(distance) => write!(f, "fronts differ by {} character(s)", distance),
+      Reason::SameBack => write!(f, "same back")
+    }
+  }
+}
+
+/// The highest edit distance [`find_duplicates`] accepts. Every front is
+/// indexed by all texts left after deleting up to that many characters, which
+/// grows too fast for larger distances.
+pub const MAX_DISTANCE: usize = 2;
+
+/// Lower case text with single spaces between words.
+pub fn normalize(text: &str) -> String {
+  text.split_whitespace()
+  .map(|word| word.to_lowercase())
+  .collect::<Vec<_>>()
+  .join(" ")
+}
+
+/// The Levenshtein distance of the characters of both strings.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+  let b = b.chars().collect::<Vec<_>>();
+  let mut row = (0..=b.len()).collect::<Vec<_>>();
+
+  for (i, x) in a.chars().enumerate() {
+    let mut previous = row[0];
+    row[0] = i + 1;
+
+    for (j, &y) in b.iter().enumerate() {
+      let current = row[j + 1];
+
+      row[j + 1] = if x == y {
+        previous
+      } else {
+        1 + previous.min(row[j]).min(current)
+      };
+
+      previous = current;
+    }
+  }
+
+  row[b.len()]
+}
+
+/// Adds the hashes of all texts that result from deleting up to `count` of
+/// the remaining characters to `result`.
+fn deletion_hashes(chars: &[char], count: usize, kept: &mut Vec<char>, result: &mut Vec<u64>) {
+  match chars.split_first() {
+    Some((&first, rest)) => {
+      kept.push(first);
+      deletion_hashes(rest, count, kept, result);
+      kept.pop();
+
+      if count > 0 {
+        deletion_hashes(rest, count - 1, kept, result);
+      }
+    },
+    None => {
+      let mut hasher = DefaultHasher::new();
+      kept.hash(&mut hasher);
+      result.push(hasher.finish());
+    }
+  }
+}
+
+/// Pairs of fronts that may be within `max_distance` of each other. Such
+/// fronts can be made equal by deleting up to `max_distance` characters from
+/// each, so only fronts that share one of these shorter texts are paired.
+/// Texts are compared by hash, a collision only adds a pair to check.
+fn similar_candidates(fronts: &[String], lengths: &[usize], max_distance: usize) -> HashSet<(usize, usize)> {
+  let mut buckets = HashMap::<u64, Vec<usize>>::new();
+  let mut result = HashSet::new();
+
+  for (i, front) in fronts.iter().enumerate() {
+    if lengths[i] <= 2 * max_distance {
+      continue;
+    }
+
+    let chars = front.chars().collect::<Vec<_>>();
+    let mut hashes = Vec::new();
+
+    deletion_hashes(&chars, max_distance, &mut Vec::new(), &mut hashes);
+    hashes.sort_unstable();
+    hashes.dedup();
+
+    for hash in hashes {
+      buckets.entry(hash).or_default().push(i);
+    }
+  }
+
+  for bucket in buckets.values() {
+    for (k, &i) in bucket.iter().enumerate() {
+      for &j in bucket[k + 1..].iter() {
+        result.insert((i, j));
+      }
+    }
+  }
+
+  result
+}
+
+/// Finds pairs of duplicate cards and returns their indices with the
+/// strongest reason. Fronts only count as similar if they are longer than
+/// twice `max_distance`, so short words don't match each other.
+///
+/// Only cards with the same normalized front or back, or with fronts that
+/// may be similar, are compared, so large lists don't compare every pair.
+/// Fails if `max_distance` is above [`MAX_DISTANCE`].
+pub fn find_duplicates(cards: &[(String, Card)], max_distance: usize) -> Result<Vec<(usize, usize, Reason)>> {
+  if max_distance > MAX_DISTANCE {
+    return Err(RecallError::InvalidArgument {
+      name: "max-distance",
+      value: max_distance.to_string()
+    });
+  }
+
+  let fronts = cards.iter().map(|(_, card)| normalize(&card.front)).collect::<Vec<_>>();
+  let backs = cards.iter().map(|(_, card)| normalize(&card.back)).collect::<Vec<_>>();
+  let lengths = fronts.iter().map(|x| x.chars().count()).collect::<Vec<_>>();
+  let mut buckets = HashMap::<(bool, &str), Vec<usize>>::new();
+
+  for i in 0..cards.len() {
+    buckets.entry((true, &fronts[i])).or_default().push(i);
+
+    if !backs[i].is_empty() {
+      buckets.entry((false, &backs[i])).or_default().push(i);
+    }
+  }
+
+  let mut candidates = if max_distance > 0 {
+    similar_candidates(&fronts, &lengths, max_distance)
+  } else {
+    HashSet::new()
+  };
+
+  for bucket in buckets.values() {
+    for (k, &i) in bucket.iter().enumerate() {
+      for &j in bucket[k + 1..].iter() {
+        candidates.insert((i, j));
+      }
+    }
+  }
+
+  let mut result = Vec::new();
+
+  for (i, j) in candidates {
+    let reason = if cards[i].1.front == cards[j].1.front {
+      Some(Reason::Exact)
+    } else if fronts[i] == fronts[j] {
+      Some(Reason::Normalized)
+    } else if max_distance > 0
+      && lengths[i].min(lengths[j]) > 2 * max_distance
+      && lengths[i].max(lengths[j]) - lengths[i].min(lengths[j]) <= max_distance
+    {
+      Some(edit_distance(&fronts[i], &fronts[j]))
+      .filter(|&distance| distance <= max_distance)
+      .map(Reason::Similar)
+    } else {
+      None
+    };
+
+    let reason = reason.or_else(|| {
+      Some(Reason::SameBack).filter(|_| !backs[i].is_empty() && backs[i] == backs[j])
+    });
+
+    if let Some(reason) = reason {
+      result.push((i, j, reason));
+    }
+  }
+
+  result.sort_by_key(|&(i, j, reason)| (reason, i, j));
+  Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn cards(fields: &[(&str, &str)]) -> Vec<(String, Card)> {
+    fields.iter()
+    .map(|(front, back)| ("deck".to_string(), Card::new(front.to_string(), back.to_string(), String::new())))
+    .collect()
+  }
+
+  #[test]
+  fn normalizes_and_measures_text() {
+    assert_eq!(normalize("  Hola \t Mundo "), "hola mundo");
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+    assert_eq!(edit_distance("", "abc"), 3);
+    assert_eq!(edit_distance("añejo", "anejo"), 1);
+  }
+
+  #[test]
+  fn finds_duplicates_with_strongest_reason() {
+    let cards = cards(&[
+      ("hola", "hello"),
+      ("hola", "hi"),
+      ("Hola ", "hey"),
+      ("buenos días", "good morning"),
+      ("buenas días", "good day"),
+      ("adiós", "goodbye"),
+      ("chao", "Goodbye"),
+      ("gato", "cat"),
+      ("pato", "duck")
+    ]);
+
+    assert_eq!(find_duplicates(&cards, 2).unwrap(), vec![
+      (0, 1, Reason::Exact),
+      (0, 2, Reason::Normalized),
+      (1, 2, Reason::Normalized),
+      (3, 4, Reason::Similar(1)),
+      (5, 6, Reason::SameBack)
+    ]);
+  }
+
+  #[test]
+  fn short_fronts_are_not_similar() {
+    let cards = cards(&[("gato", "cat"), ("pato", "duck")]);
+
+    assert!(find_duplicates(&cards, 2).unwrap().is_empty());
+    assert_eq!(find_duplicates(&cards, 1).unwrap(), vec![(0, 1, Reason::Similar(1))]);
+    assert!(find_duplicates(&cards, 0).unwrap().is_empty());
+  }
+
+  #[test]
+  fn finds_similar_fronts_in_large_lists() {
+    let mut fields = (0..2000).map(|i| (format!("word number {}", i * 7), String::new())).collect::<Vec<_>>();
+
+    fields.push(("word numbr 70".to_string(), String::new()));
+
+    let fields = fields.iter().map(|(front, back)| (front.as_str(), back.as_str())).collect::<Vec<_>>();
+    let duplicates = find_duplicates(&cards(&fields), 1)
+      .unwrap()
+      .into_iter()
+      .filter(|&(_, j, _)| j == 2000)
+      .collect::<Vec<_>>();
+
+    assert_eq!(duplicates, vec![(10, 2000, Reason::Similar(1))]);
+  }
+
+  #[test]
+  fn finds_fronts_at_max_distance() {
+    let words = ["alpha", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel", "india", "juliett"];
+    let mut fields = (0..30)
+      .map(|i| (format!("{} {} jumps over the dog", words[i / 10], words[i % 10]), String::new()))
+      .collect::<Vec<_>>();
+
+    fields.push(("alpha hotl jumps ovr the dog".to_string(), String::new()));
+
+    let fields = fields.iter().map(|(front, back)| (front.as_str(), back.as_str())).collect::<Vec<_>>();
+    let cards = cards(&fields);
+    let duplicates = find_duplicates(&cards, MAX_DISTANCE)
+      .unwrap()
+      .into_iter()
+      .filter(|&(_, j, _)| j == 30)
+      .collect::<Vec<_>>();
+
+    assert_eq!(duplicates, vec![(7, 30, Reason::Similar(2))]);
+    assert!(matches!(
+      find_duplicates(&cards, MAX_DISTANCE + 1),
+      Err(RecallError::InvalidArgument { .. })
+    ));
+  }
+}
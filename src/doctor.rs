@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use serde_json::Value;
+use crate::jsonl::JsonlFile;
+use crate::ladder::Ladder;
+use crate::list::{Backend, List};
+use crate::record::{check_value, parse_line, Record, CURRENT_VERSION};
+use crate::error::Result;
+
+#[derive(Debug)]
+pub struct Problem {
+  pub line_number: usize,
+  pub message: String
+}
+
+/// Checks every line of the list for invalid JSON, wrong field types,
+/// impossible values and duplicate fronts. SQLite lists are checked by the
+/// database itself and have no problems to report.
+pub fn diagnose(list: &List, ladder: &Ladder) -> Result<Vec<Problem>> {
+  let mut problems = Vec::new();
+  let mut fronts = HashMap::new();
+
+  if list.backend() != Backend::Jsonl {
+    return Ok(problems);
+  }
+
+  for (i, line) in JsonlFile::new(list.path()).lines()? {
+    let mut push = |message: String| problems.push(Problem {
+      line_number: i,
+      message
+    });
+
+    let value = match serde_json::from_slice::<Value>(&line) {
+      Ok(value) => value,
+      Err(err) => {
+        push(format!("Invalid JSON: {}.", err));
+        continue;
+      }
+    };
+
+    if let Record::Header(version) = parse_line(&line) {
+      if version > CURRENT_VERSION {
+        push(format!("Unsupported format version {}.", version));
+      }
+
+      continue;
+    }
+
+    for message in check_value(&value, ladder.max_level()) {
+      push(message);
+    }
+
+    let front = match &value {
+      Value::Array(arr) => arr.first(),
+      Value::Object(object) => object.get("front"),
+      _ => None
+    };
+
+    if let Some(front) = front.and_then(|x| x.as_str()) {
+      if let Some(j) = fronts.get(front) {
+        push(format!("Duplicate front '{}', see line {}.", front, j));
+      } else {
+        fronts.insert(front.to_string(), i);
+      }
+    }
+  }
+
+  Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn finds_problems() {
+    let path = std::env::temp_dir().join(format!("recall-test-{}-doctor.jsonl", std::process::id()));
+    std::fs::write(&path, "[\"a\",\"b\"]\n\n{}\n[\"a\",\"c\"]\n[\"d\",\"e\",\"\",\"high\"]\n[\"f\"\n").unwrap();
+
+    let problems = diagnose(&List::new(&path).unwrap(), &Ladder::default()).unwrap()
+      .into_iter()
+      .map(|problem| problem.line_number)
+      .collect::<Vec<_>>();
+
+    assert_eq!(problems, vec![3, 4, 5, 6]);
+
+    std::fs::remove_file(&path).unwrap();
+  }
+}
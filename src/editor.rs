@@ -0,0 +1,266 @@
+//! Editing of cards in an external editor, through a temporary file in the
+//! template format:
+//!
+//! ```text
+//! # Lines starting with `#` are ignored unless they continue the back or
+//! # notes of a card.
+//! Front: hola
+//! Back: hello
+//! Notes: Back and notes can
+//! span several lines.
+//! ---
+//! Front: adiós
+//! Back: goodbye
+//! ```
+
+use std::env;
+use std::fs::{read_to_string, remove_file, OpenOptions};
+use std::io::{self, Write};
+use std::process::{self, Command};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::card::Card;
+use crate::error::{RecallError, Result, WithPath};
+
+const SEPARATOR: &str = "---";
+
+/// Opens `text` in the editor and returns the saved text. The editor command
+/// is run by the shell, so it can contain arguments like `code --wait`.
+pub fn edit(command: &str, text: &str) -> Result<String> {
+  let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.subsec_nanos()).unwrap_or(0);
+  let path = env::temp_dir().join(format!("recall-{}-{}.txt", process::id(), nanos));
+  let mut file = OpenOptions::new().write(true).create_new(true).open(&path).with_path(&path)?;
+
+  file.write_all(text.as_bytes()).with_path(&path)?;
+  drop(file);
+
+  let status = Command::new("sh")
+    .arg("-c")
+    .arg(format!("{} \"$@\"", command))
+    .arg(command)
+    .arg(&path)
+    .status();
+
+  let result = match status {
+    Ok(status) if status.success() => read_to_string(&path).with_path(&path),
+    Ok(status) => Err(RecallError::Editor {
+      command: command.to_string(),
+      source: io::Error::other(format!("The editor exited with {}.", status))
+    }),
+    Err(source) => Err(RecallError::Editor {
+      command: command.to_string(),
+      source
+    })
+  };
+
+  remove_file(&path).ok();
+  result
+}
+
+/// Writes cards in the template format, after a comment explaining it. Without
+/// cards, an empty card is written to fill in.
+pub fn to_template(comment: &str, cards: &[Card]) -> String {
+  let mut text = String::new();
+
+  for line in comment.lines() {
+    if line.is_empty() {
+      text += "#\n";
+    } else {
+      text += &format!("# {}\n", line);
+    }
+  }
+
+  text += "#\n";
+  text += "# Lines starting with `#` are ignored unless they continue the back or\n";
+  text += "# notes. Back and notes can span several lines, cards are separated by a\n";
+  text += "# line with `---`.\n\n";
+
+  let empty = [Card::new(String::new(), String::new(), String::new())];
+  let cards = if cards.is_empty() { &empty[..] } else { cards };
+
+  for (i, card) in cards.iter().enumerate() {
+    if i > 0 {
+      text += SEPARATOR;
+      text += "\n";
+    }
+
+    text += &format!("Front: {}\nBack: {}\nNotes: {}\n", card.front, card.back, card.notes);
+  }
+
+  text
+}
+
+/// Reads cards in the template format. Blocks without any text are skipped.
+pub fn parse_template(text: &str) -> Result<Vec<Card>> {
+  let mut cards = Vec::new();
+  let mut fields: [Option<String>; 3] = [None, None, None];
+  let mut current = None;
+  let mut block_start = 1;
+
+  let mut finish = |fields: &mut [Option<String>; 3], line: usize| -> Result<()> {
+    let [front, back, notes] = std::mem::take(fields);
+    let [front, back, notes] = [front, back, notes].map(|x| x.unwrap_or_default().trim().to_string());
+
+    if front.is_empty() && back.is_empty() && notes.is_empty() {
+      return Ok(());
+    }
+
+    let invalid = |message: &str| RecallError::InvalidTemplate {
+      line,
+      message: message.to_string()
+    };
+
+    if front.is_empty() {
+      return Err(invalid("The front is missing."));
+    }
+
+    if front.contains('\n') {
+      return Err(invalid("The front must be a single line."));
+    }
+
+    cards.push(Card::new(front, back, notes));
+    Ok(())
+  };
+
+  for (i, line) in text.lines().enumerate() {
+    // Only lines outside of fields are comments, so notes can have lines
+    // starting with `#`, like tags.
+    if current.is_none() && line.starts_with('#') {
+      continue;
+    }
+
+    if line.trim_end() == SEPARATOR {
+      finish(&mut fields, block_start)?;
+      current = None;
+      block_start = i + 2;
+      continue;
+    }
+
+    let field = ["front:", "back:", "notes:"].iter()
+      .position(|name| line.get(..name.len()).map(|x| x.eq_ignore_ascii_case(name)).unwrap_or(false));
+
+    match (field, current) {
+      (Some(field), _) => {
+        if fields[field].is_some() {
+          return Err(RecallError::InvalidTemplate {
+            line: i + 1,
+            message: "The field is given twice, separate cards with `---`.".to_string()
+          });
+        }
+
+        let name_len = ["front:", "back:", "notes:"][field].len();
+        fields[field] = Some(line[name_len..].trim_start().to_string());
+        current = Some(field);
+      },
+      (None, Some(field)) => {
+        let value = fields[field].get_or_insert_with(String::new);
+        value.push('\n');
+        value.push_str(line);
+      },
+      (None, None) if line.trim().is_empty() => {},
+      (None, None) => {
+        return Err(RecallError::InvalidTemplate {
+          line: i + 1,
+          message: "Expected `Front:`, `Back:` or `Notes:`.".to_string()
+        });
+      }
+    }
+  }
+
+  finish(&mut fields, block_start)?;
+  Ok(cards)
+}
+
+/// Opens the front, back and notes of a card in the editor and changes them
+/// to the saved text. Callers check that the front is still unique.
+pub fn edit_card(command: &str, card: &mut Card) -> Result<()> {
+  let text = edit(command, &to_template("Edit the card and save the file.", std::slice::from_ref(card)))?;
+  let mut cards = parse_template(&text)?;
+
+  if cards.len() != 1 {
+    return Err(RecallError::InvalidTemplate {
+      line: 1,
+      message: format!("Expected exactly one card, found {}.", cards.len())
+    });
+  }
+
+  let edited = cards.remove(0);
+
+  card.front = edited.front;
+  card.back = edited.back;
+  card.notes = edited.notes;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn fields(cards: &[Card]) -> Vec<(&str, &str, &str)> {
+    cards.iter().map(|card| (card.front.as_str(), card.back.as_str(), card.notes.as_str())).collect()
+  }
+
+  #[test]
+  fn parses_cards() {
+    let text = "# New cards.\n#\nFront: hola\nBack: hello\nNotes: Back and notes can\nspan several lines.\n---\n\
+      # Another card.\nfront:   adiós\nBACK: goodbye\n";
+    let cards = parse_template(text).unwrap();
+
+    assert_eq!(fields(&cards), vec![
+      ("hola", "hello", "Back and notes can\nspan several lines."),
+      ("adiós", "goodbye", "")
+    ]);
+  }
+
+  #[test]
+  fn keeps_hash_lines_inside_fields() {
+    let cards = parse_template("Front: hola\nBack: hello\nNotes: A greeting.\n#informal #spoken\n").unwrap();
+
+    assert_eq!(cards[0].notes, "A greeting.\n#informal #spoken");
+  }
+
+  #[test]
+  fn skips_empty_cards() {
+    let text = to_template("Add cards.", &[]);
+
+    assert!(parse_template(&text).unwrap().is_empty());
+    assert_eq!(parse_template("Front:\n---\n\n---\nFront: a\n").unwrap().len(), 1);
+  }
+
+  #[test]
+  fn reads_written_cards() {
+    let cards = vec![
+      Card::new("hola".to_string(), "hello".to_string(), "#greeting".to_string()),
+      Card::new("adiós".to_string(), "good\nbye".to_string(), String::new())
+    ];
+    let parsed = parse_template(&to_template("Edit the cards.\n\nSave to finish.", &cards)).unwrap();
+
+    assert_eq!(fields(&parsed), fields(&cards));
+  }
+
+  #[test]
+  fn rejects_invalid_cards() {
+    let line = |text: &str| match parse_template(text) {
+      Err(RecallError::InvalidTemplate { line, .. }) => Some(line),
+      _ => None
+    };
+
+    assert_eq!(line("Back: hello\n"), Some(1));
+    assert_eq!(line("Front: a\nBack: b\nFront: c\n"), Some(3));
+    assert_eq!(line("Front: a\n---\nFront: b\nhola\n"), Some(3));
+    assert_eq!(line("hola\nFront: a\n"), Some(1));
+  }
+
+  #[test]
+  fn edits_card_with_command() {
+    let mut card = Card::new("hola".to_string(), "hello".to_string(), String::new());
+    let two_cards = "sed -i -e 's/^Front: hi$/Front: a\\n---\\nFront: b/'";
+
+    card.level = 3;
+    edit_card("sed -i -e 's/^Front: hola$/Front: hi/' -e 's/^Notes:.*$/Notes: greeting/'", &mut card).unwrap();
+
+    assert_eq!(fields(&[card.clone()]), vec![("hi", "hello", "greeting")]);
+    assert_eq!(card.level, 3);
+    assert!(matches!(edit_card("false", &mut card), Err(RecallError::Editor { .. })));
+    assert!(matches!(edit_card(two_cards, &mut card), Err(RecallError::InvalidTemplate { .. })));
+  }
+}
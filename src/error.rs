@@ -0,0 +1,169 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum RecallError {
+  Io {
+    path: PathBuf,
+    source: io::Error
+  },
+  Parse {
+    path: PathBuf,
+    line: usize,
+    source: serde_json::Error
+  },
+  Database {
+    path: PathBuf,
+    source: Box<dyn Error + Send + Sync>
+  },
+  InvalidConfig {
+    origin: String,
+    message: String
+  },
+  InvalidArgument {
+    name: &'static str,
+    value: String
+  },
+  ListNotFound(String),
+  ListExists(String),
+  CardNotFound {
+    list: String,
+    front: String
+  },
+  DuplicateCard {
+    list: String,
+    front: String
+  },
+  InvalidTemplate {
+    line: usize,
+    message: String
+  },
+  Editor {
+    command: String,
+    source: io::Error
+  },
+  Prompt(Box<dyn Error + Send + Sync>),
+  Interrupted,
+  Other(String)
+}
+
+impl RecallError {
+  pub fn io<P: AsRef<Path>>(path: P, source: io::Error) -> RecallError {
+    RecallError::Io {
+      path: path.as_ref().to_path_buf(),
+      source
+    }
+  }
+
+  pub fn parse<P: AsRef<Path>>(path: P, line: usize, source: serde_json::Error) -> RecallError {
+    RecallError::Parse {
+      path: path.as_ref().to_path_buf(),
+      line,
+      source
+    }
+  }
+
+  pub fn other<S: Into<String>>(message: S) -> RecallError {
+    RecallError::Other(message.into())
+  }
+
+  /// Exit code of the process when failing with this error, loosely following
+  /// the conventions of `sysexits.h`.
+  pub fn exit_code(&self) -> i32 {
+    match self {
+      RecallError::InvalidArgument { .. } => 64,
+      RecallError::Parse { .. } | RecallError::InvalidTemplate { .. } => 65,
+      RecallError::InvalidConfig { .. } => 78,
+      RecallError::ListNotFound(_) | RecallError::CardNotFound { .. } => 66,
+      RecallError::ListExists(_) | RecallError::DuplicateCard { .. } => 73,
+      RecallError::Io { .. } | RecallError::Database { .. } => 74,
+      RecallError::Editor { .. } | RecallError::Prompt(_) => 74,
+      RecallError::Interrupted => 130,
+      RecallError::Other(_) => 1
+    }
+  }
+}
+
+impl Error for RecallError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      RecallError::Io { source, .. } => Some(source),
+      RecallError::Parse { source, .. } => Some(source),
+      RecallError::Database { source, .. } => Some(source.as_ref()),
+      RecallError::Editor { source, .. } => Some(source),
+      RecallError::Prompt(source) => Some(source.as_ref()),
+      _ => None
+    }
+  }
+}
+
+impl Display for RecallError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      RecallError::Io { path, .. } => write!(f, "Cannot access '{}'.", path.display()),
+      RecallError::Parse { path, line, .. } => {
+        write!(f, "Cannot parse line {} of '{}'.", line, path.display())
+      },
+      RecallError::Database { path, .. } => write!(f, "Cannot query database '{}'.", path.display()),
+      RecallError::InvalidConfig { origin, message } => {
+        write!(f, "Invalid config in {}: {}", origin, message)
+      },
+      RecallError::InvalidArgument { name, value } => {
+        write!(f, "Invalid value '{}' for `{}`.", value, name)
+      },
+      RecallError::ListNotFound(name) => write!(f, "List '{}' does not exist.", name),
+      RecallError::ListExists(name) => write!(f, "List '{}' already exists.", name),
+      RecallError::CardNotFound { list, front } => {
+        write!(f, "Card '{}' does not exist in list '{}'.", front, list)
+      },
+      RecallError::DuplicateCard { list, front } => {
+        write!(f, "Card '{}' already exists in list '{}'.", front, list)
+      },
+      RecallError::InvalidTemplate { line, message } => {
+        write!(f, "Invalid card on line {}: {}", line, message)
+      },
+      RecallError::Editor { command, .. } => write!(f, "Cannot run editor '{}'.", command),
+      RecallError::Prompt(_) => write!(f, "Unable to prompt user."),
+      RecallError::Interrupted => write!(f, "Interrupted."),
+      RecallError::Other(message) => write!(f, "{}", message)
+    }
+  }
+}
+
+pub type Result<T> = std::result::Result<T, RecallError>;
+
+pub(crate) trait WithPath<T> {
+  fn with_path<P: AsRef<Path>>(self, path: P) -> Result<T>;
+}
+
+impl<T> WithPath<T> for io::Result<T> {
+  fn with_path<P: AsRef<Path>>(self, path: P) -> Result<T> {
+    self.map_err(|err| RecallError::io(path, err))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn errors_have_exit_codes() {
+    let err = Err::<(), _>(io::Error::new(io::ErrorKind::NotFound, "missing"))
+      .with_path("words.jsonl")
+      .unwrap_err();
+
+    assert_eq!(err.to_string(), "Cannot access 'words.jsonl'.");
+    assert!(err.source().is_some());
+    assert_eq!(err.exit_code(), 74);
+
+    let invalid = RecallError::InvalidArgument { name: "level", value: "x".to_string() };
+
+    assert_eq!(invalid.exit_code(), 64);
+    assert_eq!(RecallError::ListNotFound("words".to_string()).exit_code(), 66);
+    assert_eq!(RecallError::ListExists("words".to_string()).exit_code(), 73);
+    assert_eq!(RecallError::Interrupted.exit_code(), 130);
+    assert_eq!(RecallError::other("failed").exit_code(), 1);
+  }
+}
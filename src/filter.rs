@@ -0,0 +1,118 @@
+//! Conditions that select cards, written like `proficiency:guru` or
+//! `tag:verb`.
+
+use crate::card::{list_proficiencies, Card, Proficiency};
+use crate::ladder::Ladder;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+  /// `proficiency:apprentice,guru`
+  Proficiency(Vec<Proficiency>),
+  /// `level:3` or `level:1-4`
+  Level(i8, i8),
+  /// `tag:verb`, a `#hashtag` in the notes
+  Tag(String),
+  /// `front:text`, a part of the front, ignoring case
+  Front(String),
+  /// `critical`
+  Critical,
+  /// `suspended`
+  Suspended
+}
+
+impl Filter {
+  pub fn parse(text: &str) -> Result<Filter, String> {
+    let (name, value) = match text.find(':') {
+      Some(index) => (&text[..index], Some(&text[index + 1..])),
+      None => (text, None)
+    };
+
+    let invalid = || format!("Invalid filter '{}'.", text);
+
+    match (name, value) {
+      ("proficiency", Some(value)) => {
+        value.split(',')
+        .map(|name| {
+          list_proficiencies().into_iter()
+          .find(|x| x.to_string().eq_ignore_ascii_case(name.trim()))
+          .ok_or_else(|| format!("Unknown proficiency '{}'.", name))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Filter::Proficiency)
+      },
+      ("level", Some(value)) => {
+        let (min, max) = match value.find('-') {
+          Some(index) => (&value[..index], &value[index + 1..]),
+          None => (value, value)
+        };
+
+        match (min.parse::<i8>(), max.parse::<i8>()) {
+          (Ok(min), Ok(max)) if min <= max => Ok(Filter::Level(min, max)),
+          _ => Err(invalid())
+        }
+      },
+      ("tag", Some(value)) if !value.is_empty() => {
+        Ok(Filter::Tag(value.trim_start_matches('#').to_string()))
+      },
+      ("front", Some(value)) => Ok(Filter::Front(value.to_lowercase())),
+      ("critical", None) => Ok(Filter::Critical),
+      ("suspended", None) => Ok(Filter::Suspended),
+      _ => Err(invalid())
+    }
+  }
+
+  pub fn matches(&self, card: &Card, ladder: &Ladder) -> bool {
+    match self {
+      Filter::Proficiency(proficiencies) => proficiencies.contains(&card.proficiency(ladder)),
+      Filter::Level(min, max) => (*min..=*max).contains(&card.level),
+      Filter::Tag(tag) => card.tags().iter().any(|x| x.eq_ignore_ascii_case(tag)),
+      Filter::Front(text) => card.front.to_lowercase().contains(text),
+      Filter::Critical => card.critical(ladder),
+      Filter::Suspended => card.suspended
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_filters() {
+    assert_eq!(
+      Filter::parse("proficiency:apprentice, Guru"),
+      Ok(Filter::Proficiency(vec![Proficiency::Apprentice, Proficiency::Guru]))
+    );
+    assert_eq!(Filter::parse("level:3"), Ok(Filter::Level(3, 3)));
+    assert_eq!(Filter::parse("level:1-4"), Ok(Filter::Level(1, 4)));
+    assert_eq!(Filter::parse("tag:#verb"), Ok(Filter::Tag("verb".to_string())));
+    assert_eq!(Filter::parse("front:Hola"), Ok(Filter::Front("hola".to_string())));
+    assert_eq!(Filter::parse("critical"), Ok(Filter::Critical));
+    assert_eq!(Filter::parse("suspended"), Ok(Filter::Suspended));
+  }
+
+  #[test]
+  fn rejects_invalid_filters() {
+    assert!(Filter::parse("proficiency:wizard").is_err());
+    assert!(Filter::parse("level:4-1").is_err());
+    assert!(Filter::parse("level:x").is_err());
+    assert!(Filter::parse("tag:").is_err());
+    assert!(Filter::parse("critical:yes").is_err());
+    assert!(Filter::parse("color:red").is_err());
+  }
+
+  #[test]
+  fn matches_cards() {
+    let ladder = Ladder::default();
+    let mut card = Card::new("Hola".to_string(), "hello".to_string(), "#greeting, informal".to_string());
+
+    card.level = 5;
+
+    assert!(Filter::parse("proficiency:guru").unwrap().matches(&card, &ladder));
+    assert!(Filter::parse("level:4-6").unwrap().matches(&card, &ladder));
+    assert!(Filter::parse("tag:Greeting").unwrap().matches(&card, &ladder));
+    assert!(Filter::parse("front:OL").unwrap().matches(&card, &ladder));
+    assert!(!Filter::parse("tag:informal").unwrap().matches(&card, &ladder));
+    assert!(!Filter::parse("suspended").unwrap().matches(&card, &ladder));
+  }
+}
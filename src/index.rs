@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::fs::{create_dir_all, metadata, File};
+use std::path::{Path, PathBuf};
+use std::io::{BufReader, BufWriter, Write};
+use std::time::UNIX_EPOCH;
+use chrono::{DateTime, TimeZone, Utc};
+use crate::list::{Backend, List};
+use crate::storage::Storage;
+use crate::error::{Result, WithPath};
+
+/// Modification time in seconds and nanoseconds, file size and sorted due
+/// timestamps of a list.
+type Entry = (u64, u32, u64, Vec<i64>);
+
+/// A cache of the due times of all cards per list, so that due counts can be
+/// determined without parsing lists that haven't changed.
+pub struct DueIndex {
+  entries: HashMap<String, Entry>,
+  changed: bool
+}
+
+fn index_path<P: AsRef<Path>>(dirname: P) -> PathBuf {
+  dirname.as_ref().join(".recall").join("index.json")
+}
+
+impl DueIndex {
+  pub fn load<P: AsRef<Path>>(dirname: P) -> DueIndex {
+    let entries = File::open(index_path(dirname)).ok()
+      .and_then(|file| serde_json::from_reader(BufReader::new(file)).ok())
+      .unwrap_or_default();
+
+    DueIndex {
+      entries,
+      changed: false
+    }
+  }
+
+  /// Writes the index if it has changed, forgetting lists that don't exist
+  /// anymore.
+  pub fn save<P: AsRef<Path>>(&mut self, dirname: P) -> Result<()> {
+    let len = self.entries.len();
+    self.entries.retain(|key, _| Path::new(key).exists());
+
+    if !self.changed && self.entries.len() == len {
+      return Ok(());
+    }
+
+    let path = index_path(&dirname);
+
+    create_dir_all(dirname.as_ref().join(".recall")).with_path(&dirname)?;
+
+    let file = File::create(&path).with_path(&path)?;
+    let mut buf_writer = BufWriter::new(file);
+
+    serde_json::to_writer(&mut buf_writer, &self.entries)
+    .map_err(|err| err.into())
+    .with_path(&path)?;
+    buf_writer.flush().with_path(&path)
+  }
+
+  /// Returns the sorted due timestamps of all available cards in the list,
+  /// reading the list only if it has changed since it was last indexed.
+  pub fn due_times(&mut self, list: &List) -> Result<&[i64]> {
+    let key = list.path().to_string_lossy().to_string();
+    let metadata = metadata(list.path()).with_path(list.path())?;
+    let modified = metadata.modified().with_path(list.path())?
+      .duration_since(UNIX_EPOCH)
+      .unwrap_or_default();
+    let (secs, nanos, len) = (modified.as_secs(), modified.subsec_nanos(), metadata.len());
+
+    let up_to_date = self.entries.get(&key)
+      .map(|&(x, y, z, _)| (x, y, z) == (secs, nanos, len))
+      .unwrap_or(false);
+
+    if !up_to_date {
+      let mut due_times = list.cards()?
+        .into_iter()
+        .filter(|card| !card.suspended)
+        .filter_map(|card| {
+          card.due_time
+          .map(|x| card.buried_until.map(|y| x.max(y)).unwrap_or(x))
+        })
+        .map(|x| x.timestamp())
+        .collect::<Vec<_>>();
+
+      due_times.sort_unstable();
+
+      self.entries.insert(key.clone(), (secs, nanos, len, due_times));
+      self.changed = true;
+    }
+
+    Ok(&self.entries[&key].3)
+  }
+
+  /// Counts the cards of the list that are due at the given time. SQLite
+  /// lists have an index of their own and are queried directly.
+  pub fn count_due_at<T: TimeZone>(&mut self, list: &List, date_time: DateTime<T>) -> Result<usize> {
+    if list.backend() == Backend::Sqlite {
+      return list.count_due_at(date_time.with_timezone(&Utc));
+    }
+
+    let timestamp = date_time.timestamp();
+
+    self.due_times(list)
+    .map(|due_times| due_times.partition_point(|&x| x <= timestamp))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::{Duration, Utc};
+  use crate::card::Card;
+
+  fn card(due_time: Option<DateTime<chrono::Utc>>) -> Card {
+    let mut card = Card::new("a".to_string(), "b".to_string(), String::new());
+
+    card.level = 1;
+    card.due_time = due_time;
+    card
+  }
+
+  #[test]
+  fn due_counts_follow_list_changes() {
+    let dirname = std::env::temp_dir().join(format!("recall-test-{}-index", std::process::id()));
+    std::fs::create_dir_all(&dirname).unwrap();
+
+    let list = List::new(dirname.join("words.jsonl")).unwrap();
+    let now = Utc::now();
+
+    list.save_cards(vec![card(Some(now - Duration::hours(1))), card(Some(now + Duration::hours(1)))]).unwrap();
+
+    let mut index = DueIndex::load(&dirname);
+    assert_eq!(index.count_due_at(&list, now).unwrap(), 1);
+    assert_eq!(index.count_due_at(&list, now + Duration::hours(2)).unwrap(), 2);
+    index.save(&dirname).unwrap();
+
+    list.save_cards(vec![card(Some(now - Duration::hours(1))), card(Some(now)), card(None)]).unwrap();
+
+    let mut index = DueIndex::load(&dirname);
+    assert_eq!(index.count_due_at(&list, now).unwrap(), 2);
+
+    index.save(&dirname).unwrap();
+    std::fs::remove_file(list.path()).unwrap();
+    DueIndex::load(&dirname).save(&dirname).unwrap();
+    assert!(DueIndex::load(&dirname).entries.is_empty());
+
+    std::fs::remove_dir_all(&dirname).unwrap();
+  }
+}
@@ -0,0 +1,299 @@
+use std::fs::{remove_file, File};
+use std::path::{Path, PathBuf};
+use std::io::{BufReader, BufRead, BufWriter, Write};
+use crate::card::Card;
+use crate::record::{
+  Record, LEGACY_VERSION, CURRENT_VERSION,
+  card_to_legacy_value, card_to_value, header, parse_line
+};
+use crate::storage::Storage;
+use crate::error::{Result, WithPath};
+
+fn is_blank(line: &[u8]) -> bool {
+  line.iter().all(|x| x.is_ascii_whitespace())
+}
+
+/// A list stored as a JSONL file with one card per line.
+#[derive(Debug)]
+pub struct JsonlFile {
+  path: PathBuf
+}
+
+impl JsonlFile {
+  pub fn new<P: AsRef<Path>>(path: P) -> JsonlFile {
+    JsonlFile {
+      path: path.as_ref().to_path_buf()
+    }
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  /// Reads all lines of the list as raw bytes, together with their line
+  /// numbers.
+  fn all_lines(&self) -> Result<Vec<(usize, Vec<u8>)>> {
+    let file = File::open(self.path()).with_path(self.path())?;
+    let buf_reader = BufReader::new(file);
+    let mut result = Vec::new();
+
+    for (i, line) in buf_reader.split(b'\n').enumerate() {
+      result.push((i + 1, line.with_path(self.path())?));
+    }
+
+    Ok(result)
+  }
+
+  /// Reads all non-blank lines of the list as raw bytes, together with their
+  /// line numbers.
+  pub fn lines(&self) -> Result<Vec<(usize, Vec<u8>)>> {
+    Ok(
+      self.all_lines()?
+      .into_iter()
+      .filter(|(_, line)| !is_blank(line))
+      .collect()
+    )
+  }
+
+  /// Returns the format version of the list, which is given by its header line
+  /// or is the legacy version if there is none.
+  pub fn version(&self) -> Result<u32> {
+    let version = self.lines()?
+      .into_iter()
+      .find_map(|(_, line)| match parse_line(&line) {
+        Record::Header(version) => Some(version),
+        _ => None
+      })
+      .unwrap_or(LEGACY_VERSION);
+
+    Ok(version)
+  }
+
+  /// Returns all lines that cannot be read as a card, so they can be kept
+  /// when the list is saved.
+  pub fn malformed_lines(&self) -> Result<Vec<(usize, Vec<u8>)>> {
+    Ok(
+      self.lines()?
+      .into_iter()
+      .filter(|(_, line)| matches!(parse_line(line), Record::Malformed))
+      .collect()
+    )
+  }
+
+  /// Returns all blank and malformed lines, which are written back as they
+  /// are when the list is saved, so the line numbers of the cards between
+  /// them don't change.
+  fn passthrough_lines(&self) -> Result<Vec<(usize, Vec<u8>)>> {
+    Ok(
+      self.all_lines()?
+      .into_iter()
+      .filter(|(_, line)| is_blank(line) || matches!(parse_line(line), Record::Malformed))
+      .collect()
+    )
+  }
+
+  /// Overwrites the list with the given cards in the given format version.
+  /// Blank and malformed lines of the existing file are kept in place
+  /// relative to the line numbers of the cards.
+  pub fn save_cards_with_version<I: IntoIterator<Item = Card>>(&self, cards: I, version: u32) -> Result<()> {
+    let malformed_lines = if self.path().exists() {
+      self.passthrough_lines()?
+    } else {
+      vec![]
+    };
+
+    let write = || -> std::io::Result<()> {
+      let file = File::create(self.path())?;
+      let mut buf_writer = BufWriter::new(file);
+      let mut malformed_lines = malformed_lines.into_iter().peekable();
+
+      if version > LEGACY_VERSION {
+        writeln!(buf_writer, "{}", header(version))?;
+      }
+
+      for card in cards {
+        while let Some((_, line)) = malformed_lines.next_if(|&(i, _)| {
+          card.line_number.map(|x| i < x).unwrap_or(false)
+        }) {
+          buf_writer.write_all(&line)?;
+          writeln!(buf_writer)?;
+        }
+
+        let value = if version > LEGACY_VERSION {
+          card_to_value(&card)
+        } else {
+          card_to_legacy_value(&card)
+        };
+
+        writeln!(buf_writer, "{}", value)?;
+      }
+
+      for (_, line) in malformed_lines {
+        buf_writer.write_all(&line)?;
+        writeln!(buf_writer)?;
+      }
+
+      buf_writer.flush()
+    };
+
+    write().with_path(self.path())
+  }
+}
+
+impl Storage for JsonlFile {
+  fn cards(&self) -> Result<Vec<Card>> {
+    Ok(
+      self.lines()?
+      .into_iter()
+      .filter_map(|(i, line)| match parse_line(&line) {
+        Record::Card(mut card) => {
+          card.line_number = Some(i);
+          Some(*card)
+        },
+        _ => None
+      })
+      .collect()
+    )
+  }
+
+  /// Overwrites the list with the given cards in the format version of the
+  /// existing file, or the current version for new lists. Legacy lists are
+  /// upgraded once a card is in learning, as they cannot store its step.
+  fn save_cards(&self, cards: Vec<Card>) -> Result<()> {
+    let version = if self.path().exists() {
+      self.version()?
+    } else {
+      CURRENT_VERSION
+    };
+
+    let version = if version == LEGACY_VERSION && cards.iter().any(|card| card.is_learning()) {
+      CURRENT_VERSION
+    } else {
+      version
+    };
+
+    self.save_cards_with_version(cards, version)
+  }
+
+  fn migrate(&self) -> Result<bool> {
+    if self.version()? >= CURRENT_VERSION {
+      return Ok(false);
+    }
+
+    let cards = self.cards()?;
+
+    self.save_cards_with_version(cards, CURRENT_VERSION)?;
+    Ok(true)
+  }
+
+  fn delete(&self) -> Result<()> {
+    remove_file(self.path()).with_path(self.path())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs::{read_to_string, write};
+  use super::*;
+
+  fn temp_file(name: &str, content: &str) -> JsonlFile {
+    let path = std::env::temp_dir().join(format!("recall-test-{}-{}.jsonl", std::process::id(), name));
+
+    write(&path, content).unwrap();
+    JsonlFile::new(path)
+  }
+
+  #[test]
+  fn malformed_lines_are_kept() {
+    let file = temp_file("malformed", "[\"a\",\"b\"]\nnot json\n[\"c\",\"d\"]\n42\n");
+    let mut cards = file.cards().unwrap();
+
+    assert_eq!(cards.len(), 2);
+    assert_eq!(cards[1].line_number, Some(3));
+
+    cards[1].level = 1;
+    file.save_cards(cards).unwrap();
+
+    let saved = read_to_string(file.path()).unwrap();
+    let lines = saved.lines().collect::<Vec<_>>();
+
+    file.delete().unwrap();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[1], "not json");
+    assert!(lines[2].starts_with("[\"c\",\"d\",\"\",1,"));
+    assert_eq!(lines[3], "42");
+  }
+
+  #[test]
+  fn migrate_writes_header_and_objects() {
+    let file = temp_file("migrate", "[\"a\",\"b\",\"\",0,null,0,0,false,null]\nnot json\n");
+
+    assert_eq!(file.version().unwrap(), LEGACY_VERSION);
+    assert!(file.migrate().unwrap());
+    assert!(!file.migrate().unwrap());
+
+    let version = file.version().unwrap();
+    let saved = read_to_string(file.path()).unwrap();
+
+    file.delete().unwrap();
+    assert_eq!(version, CURRENT_VERSION);
+    assert_eq!(
+      saved,
+      "{\"format\":\"recall\",\"version\":2}\n\
+      {\"front\":\"a\",\"back\":\"b\",\"notes\":\"\",\"level\":0}\n\
+      not json\n"
+    );
+  }
+
+  #[test]
+  fn blank_and_malformed_lines_are_kept() {
+    let content = "{\"format\":\"recall\",\"version\":2}\n\
+      {\"front\":\"a\",\"back\":\"1\",\"notes\":\"\",\"level\":0}\n\
+      \n\
+      broken\n\
+      {\"front\":\"b\",\"back\":\"2\",\"notes\":\"\",\"level\":0}\n";
+    let file = temp_file("passthrough", content);
+    let cards = file.cards().unwrap();
+
+    file.save_cards(cards).unwrap();
+
+    let saved = read_to_string(file.path()).unwrap();
+    let line_numbers = file.cards().unwrap().iter().map(|card| card.line_number).collect::<Vec<_>>();
+
+    file.delete().unwrap();
+    assert_eq!(saved, content);
+    assert_eq!(line_numbers, vec![Some(2), Some(5)]);
+  }
+
+  #[test]
+  fn legacy_list_keeps_learning_step() {
+    let file = temp_file("legacy-step", "[\"a\",\"1\",\"\",0,null,0,0,false,null]\n");
+    let mut cards = file.cards().unwrap();
+
+    cards[0].learning_step = Some(1);
+    file.save_cards(cards).unwrap();
+
+    let cards = file.cards().unwrap();
+    let version = file.version().unwrap();
+
+    file.delete().unwrap();
+    assert_eq!(version, CURRENT_VERSION);
+    assert_eq!(cards[0].learning_step, Some(1));
+    assert_eq!(cards[0].front, "a");
+  }
+
+  #[test]
+  fn legacy_list_stays_legacy_without_learning_cards() {
+    let file = temp_file("legacy", "[\"a\",\"1\",\"\",2,null,1,1,false,null]\n");
+    let cards = file.cards().unwrap();
+
+    file.save_cards(cards).unwrap();
+
+    let version = file.version().unwrap();
+    let cards = file.cards().unwrap();
+
+    file.delete().unwrap();
+    assert_eq!(version, LEGACY_VERSION);
+    assert_eq!((cards[0].level, cards[0].correct_count, cards[0].total_count), (2, 1, 1));
+  }
+}
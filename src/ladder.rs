@@ -0,0 +1,257 @@
+use std::fmt::Display;
+use chrono::Duration;
+use crate::card::Proficiency;
+
+/// The scheduler settings: the review intervals of the levels a card moves
+/// through, starting with level 1, how many levels a forgotten card drops and
+/// the highest level of each proficiency. Cards above the Enlightened
+/// threshold or at the maximum level are Burned. New cards go through the
+/// learning steps, if any, before reaching level 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ladder {
+  intervals: Vec<Duration>,
+  penalty: i8,
+  thresholds: [i8; 4],
+  learning_steps: Vec<Duration>
+}
+
+pub const THRESHOLD_NAMES: [&str; 4] = ["apprentice", "guru", "master", "enlightened"];
+
+impl Default for Ladder {
+  fn default() -> Ladder {
+    Ladder {
+      intervals: vec![
+        Duration::hours(4),
+        Duration::hours(8),
+        Duration::days(1),
+        Duration::days(3),
+        Duration::days(7),
+        Duration::days(14),
+        Duration::days(30),
+        Duration::days(122),
+        Duration::days(182)
+      ],
+      penalty: 2,
+      thresholds: [4, 6, 7, 8],
+      learning_steps: Vec::new()
+    }
+  }
+}
+
+impl Ladder {
+  /// Checks the settings, returning a description of the first problem.
+  /// `thresholds` are the highest levels of Apprentice, Guru, Master and
+  /// Enlightened cards.
+  pub fn new(intervals: Vec<Duration>, penalty: i8, thresholds: [i8; 4]) -> Result<Ladder, String> {
+    if intervals.is_empty() {
+      return Err("The ladder needs at least one interval.".to_string());
+    }
+
+    if intervals.len() > i8::MAX as usize {
+      return Err(format!("The ladder has more than {} intervals.", i8::MAX));
+    }
+
+    if intervals.iter().any(|x| *x <= Duration::zero()) {
+      return Err("Intervals must be positive.".to_string());
+    }
+
+    let max_level = intervals.len() as i8;
+
+    if penalty < 0 || penalty > max_level - 1 {
+      return Err(format!("Penalty {} must be between 0 and {}.", penalty, max_level - 1));
+    }
+
+    let mut previous = 0;
+
+    for (name, &threshold) in THRESHOLD_NAMES.iter().zip(thresholds.iter()) {
+      if threshold < previous || threshold >= max_level {
+        return Err(format!(
+          "Threshold of {} must be between {} and {}, found {}.",
+          name,
+          previous,
+          max_level - 1,
+          threshold
+        ));
+      }
+
+      previous = threshold;
+    }
+
+    Ok(Ladder {
+      intervals,
+      penalty,
+      thresholds,
+      learning_steps: Vec::new()
+    })
+  }
+
+  /// Sets the intervals of the learning steps, which may be empty.
+  pub fn with_learning_steps(mut self, learning_steps: Vec<Duration>) -> Result<Ladder, String> {
+    if learning_steps.iter().any(|x| *x <= Duration::zero()) {
+      return Err("Learning steps must be positive.".to_string());
+    }
+
+    self.learning_steps = learning_steps;
+    Ok(self)
+  }
+
+  /// Parses comma-separated intervals like `4h,8h,1d,3d`, taking the other
+  /// settings from `base`. Thresholds are capped to fit the new intervals.
+  pub fn parse(text: &str, base: &Ladder) -> Result<Ladder, String> {
+    let intervals = text.split(',')
+      .map(|x| parse_interval(x.trim()))
+      .collect::<Result<Vec<_>, _>>()?;
+    let max_level = intervals.len() as i8;
+    let mut thresholds = base.thresholds;
+
+    for threshold in thresholds.iter_mut() {
+      *threshold = (*threshold).min(max_level - 1).max(0);
+    }
+
+    Ladder::new(intervals, base.penalty.min(max_level - 1).max(0), thresholds)?
+    .with_learning_steps(base.learning_steps.clone())
+  }
+
+  pub fn intervals(&self) -> &[Duration] {
+    &self.intervals
+  }
+
+  pub fn penalty(&self) -> i8 {
+    self.penalty
+  }
+
+  pub fn thresholds(&self) -> [i8; 4] {
+    self.thresholds
+  }
+
+  pub fn learning_steps(&self) -> &[Duration] {
+    &self.learning_steps
+  }
+
+  pub fn max_level(&self) -> i8 {
+    self.intervals.len() as i8
+  }
+
+  /// The interval until the next review of a card at the given level.
+  pub fn interval(&self, level: i8) -> Duration {
+    let i = level.clamp(1, self.max_level()) - 1;
+    self.intervals[i as usize]
+  }
+
+  pub fn proficiency(&self, level: i8) -> Proficiency {
+    let [apprentice, guru, master, enlightened] = self.thresholds;
+
+    match level {
+      x if x <= 0 => Proficiency::Inactive,
+      x if x >= self.max_level() => Proficiency::Burned,
+      x if x <= apprentice => Proficiency::Apprentice,
+      x if x <= guru => Proficiency::Guru,
+      x if x <= master => Proficiency::Master,
+      x if x <= enlightened => Proficiency::Enlightened,
+      _ => Proficiency::Burned
+    }
+  }
+}
+
+pub fn parse_interval(text: &str) -> Result<Duration, String> {
+  humantime::parse_duration(text).ok()
+  .and_then(|x| Duration::from_std(x).ok())
+  .ok_or_else(|| format!("Invalid interval '{}'.", text))
+}
+
+pub fn format_interval(duration: Duration) -> String {
+  let seconds = duration.num_seconds();
+
+  match seconds {
+    x if x != 0 && x % 86400 == 0 => format!("{}d", x / 86400),
+    x if x != 0 && x % 3600 == 0 => format!("{}h", x / 3600),
+    x if x != 0 && x % 60 == 0 => format!("{}m", x / 60),
+    x => format!("{}s", x)
+  }
+}
+
+impl Display for Ladder {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let intervals = self.intervals.iter()
+      .map(|&x| format_interval(x))
+      .collect::<Vec<_>>();
+
+    write!(f, "{}", intervals.join(","))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn checks_settings() {
+    let intervals = || vec![Duration::hours(1), Duration::days(1), Duration::days(7)];
+
+    assert!(Ladder::new(intervals(), 1, [1, 1, 2, 2]).is_ok());
+    assert!(Ladder::new(Vec::new(), 0, [0, 0, 0, 0]).is_err());
+    assert!(Ladder::new(vec![Duration::zero()], 0, [0, 0, 0, 0]).is_err());
+    assert!(Ladder::new(intervals(), 3, [1, 1, 2, 2]).is_err());
+    assert!(Ladder::new(intervals(), 1, [2, 1, 2, 2]).is_err());
+    assert!(Ladder::new(intervals(), 1, [1, 1, 2, 3]).is_err());
+  }
+
+  #[test]
+  fn proficiency_of_levels() {
+    let ladder = Ladder::default();
+    let proficiencies = (0..=10).map(|level| ladder.proficiency(level)).collect::<Vec<_>>();
+
+    assert_eq!(proficiencies, vec![
+      Proficiency::Inactive,
+      Proficiency::Apprentice,
+      Proficiency::Apprentice,
+      Proficiency::Apprentice,
+      Proficiency::Apprentice,
+      Proficiency::Guru,
+      Proficiency::Guru,
+      Proficiency::Master,
+      Proficiency::Enlightened,
+      Proficiency::Burned,
+      Proficiency::Burned
+    ]);
+  }
+
+  #[test]
+  fn intervals_are_clamped_to_levels() {
+    let ladder = Ladder::default();
+
+    assert_eq!(ladder.interval(0), Duration::hours(4));
+    assert_eq!(ladder.interval(3), Duration::days(1));
+    assert_eq!(ladder.interval(20), Duration::days(182));
+  }
+
+  #[test]
+  fn parse_caps_settings_to_intervals() {
+    let ladder = Ladder::parse("1h, 1d", &Ladder::default()).unwrap();
+
+    assert_eq!(ladder.intervals(), &[Duration::hours(1), Duration::days(1)]);
+    assert_eq!(ladder.penalty(), 1);
+    assert_eq!(ladder.thresholds(), [1, 1, 1, 1]);
+    assert_eq!(ladder.to_string(), "1h,1d");
+    assert!(Ladder::parse("1h,soon", &Ladder::default()).is_err());
+  }
+
+  #[test]
+  fn formats_intervals() {
+    assert_eq!(format_interval(Duration::days(2)), "2d");
+    assert_eq!(format_interval(Duration::hours(30)), "30h");
+    assert_eq!(format_interval(Duration::minutes(90)), "90m");
+    assert_eq!(format_interval(Duration::seconds(61)), "61s");
+    assert_eq!(parse_interval("1h 30m"), Ok(Duration::minutes(90)));
+  }
+
+  #[test]
+  fn learning_steps_must_be_positive() {
+    let steps = vec![Duration::minutes(1), Duration::minutes(10)];
+    let ladder = Ladder::default().with_learning_steps(steps.clone()).unwrap();
+
+    assert_eq!(ladder.learning_steps(), steps.as_slice());
+    assert_eq!(Ladder::parse("1h,1d", &ladder).unwrap().learning_steps(), steps.as_slice());
+    assert!(Ladder::default().with_learning_steps(vec![Duration::zero()]).is_err());
+  }
+}
@@ -0,0 +1,40 @@
+//! Scheduling and storage of spaced repetition cards, as used by the `recall`
+//! command line app.
+//!
+//! Cards are stored in lists, which are JSONL files with one card per line,
+//! see [`record`] for the format, or SQLite databases if the `sqlite` feature
+//! is enabled. Both implement [`Storage`].
+//!
+//! Commands access lists as decks of a [`DeckStore`], such as a directory of
+//! lists ([`store::DirStore`]) or [`memory::MemoryStore`]. Use [`get_cards`] to
+//! read the cards of several decks and [`update_cards`] to write changed cards
+//! back.
+
+pub mod card;
+pub mod clock;
+pub mod config;
+pub mod dedupe;
+pub mod doctor;
+pub mod editor;
+pub mod filter;
+pub mod error;
+pub mod index;
+pub mod jsonl;
+pub mod ladder;
+pub mod list;
+pub mod memory;
+pub mod record;
+pub mod session;
+pub mod simulate;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod storage;
+pub mod store;
+pub mod terminal;
+pub mod trash;
+
+pub use card::{Card, Outcome, Proficiency, get_cards, update_cards};
+pub use error::RecallError;
+pub use list::List;
+pub use storage::Storage;
+pub use store::{DeckStore, DirStore};
@@ -1,17 +1,51 @@
-use std::fs::{read_dir, remove_file, File};
+use std::fs::read_dir;
 use std::path::{Path, PathBuf};
-use std::io::{BufReader, BufRead, BufWriter, Write, Result};
-use chrono::{Utc, DateTime};
-use serde_json::{json, Value};
+use chrono::{DateTime, Utc};
 use crate::card::Card;
+use crate::jsonl::JsonlFile;
+use crate::storage::Storage;
+use crate::error::{Result, WithPath};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Backend {
+  Jsonl,
+  Sqlite
+}
+
+impl Backend {
+  pub fn extension(&self) -> &'static str {
+    match self {
+      Backend::Jsonl => "jsonl",
+      Backend::Sqlite => "sqlite"
+    }
+  }
+
+  pub fn from_extension(extension: &str) -> Option<Backend> {
+    match extension {
+      "jsonl" => Some(Backend::Jsonl),
+      "sqlite" => Some(Backend::Sqlite),
+      _ => None
+    }
+  }
+
+  pub fn is_supported(&self) -> bool {
+    match self {
+      Backend::Jsonl => true,
+      Backend::Sqlite => cfg!(feature = "sqlite")
+    }
+  }
+}
 
 #[derive(Debug)]
 pub struct List {
   path: PathBuf,
-  name: String
+  name: String,
+  backend: Backend
 }
 
 impl List {
+  /// Creates a list for the given path. The backend is determined by the file
+  /// extension and defaults to JSONL.
   pub fn new<P: AsRef<Path>>(path: P) -> Option<List> {
     let path = path.as_ref();
     if path.is_dir() {
@@ -20,12 +54,17 @@ impl List {
 
     let full_path = path.to_str();
     let file_stem = path.file_stem().and_then(|x| x.to_str());
+    let backend = path.extension()
+      .and_then(|x| x.to_str())
+      .and_then(Backend::from_extension)
+      .unwrap_or(Backend::Jsonl);
 
     match (full_path, file_stem) {
       (Some(full_path), Some(file_stem)) => Some(
         List {
           path: Path::new(full_path).to_path_buf(),
-          name: file_stem.to_string()
+          name: file_stem.to_string(),
+          backend
         }
       ),
       _ => None
@@ -40,101 +79,70 @@ impl List {
     &self.name
   }
 
-  pub fn cards(&self) -> Result<impl Iterator<Item = Card>> {
-    let file = File::open(self.path())?;
-    let buf_reader = BufReader::new(file);
-
-    Ok(
-      buf_reader.lines()
-      .enumerate()
-      .filter_map(|(i, line)| line.ok().map(|line| (i + 1, line)))
-      .filter_map(|(i, line)| {
-        serde_json::from_str::<Vec<Value>>(&line).ok()
-        .map(|line| (i, line))
-      })
-      .map(|(i, arr)| {
-        let mut iter = arr.into_iter();
-
-        let front = iter.next()
-          .and_then(|x| serde_json::from_value::<String>(x).ok())
-          .unwrap_or_else(|| String::new());
-        let back = iter.next()
-          .and_then(|x| serde_json::from_value::<String>(x).ok())
-          .unwrap_or_else(|| String::new());
-        let notes = iter.next()
-          .and_then(|x| serde_json::from_value::<String>(x).ok())
-          .unwrap_or_else(|| String::new());
-        let level = iter.next()
-          .and_then(|x| serde_json::from_value::<i8>(x).ok())
-          .unwrap_or(0);
-        let due_time = iter.next()
-          .and_then(|x| serde_json::from_value::<String>(x).ok())
-          .and_then(|x| DateTime::parse_from_rfc3339(&x).ok())
-          .map(|date_time| date_time.with_timezone(&Utc));
-        let correct_count = iter.next()
-          .and_then(|x| serde_json::from_value::<u32>(x).ok())
-          .unwrap_or(0);
-        let total_count = iter.next()
-          .and_then(|x| serde_json::from_value::<u32>(x).ok())
-          .unwrap_or(0);
-
-        let mut card = Card::new(front, back, notes);
-
-        card.line_number = Some(i);
-        card.level = level;
-        card.due_time = due_time;
-        card.correct_count = correct_count;
-        card.total_count = total_count;
-
-        card
-      })
-    )
+  pub fn backend(&self) -> Backend {
+    self.backend
   }
 
-  pub fn save_cards<I: IntoIterator<Item = Card>>(&self, cards: I) -> Result<()> {
-    let file = File::create(self.path())?;
-    let mut buf_writer = BufWriter::new(file);
-
-    let lines = cards.into_iter()
-      .map(|card| {
-        serde_json::to_string(&json!([
-          card.front,
-          card.back,
-          card.notes,
-          card.level,
-          card.due_time.map(|x| x.to_rfc3339()),
-          card.correct_count,
-          card.total_count
-        ])).unwrap()
-      });
-
-    for line in lines {
-      writeln!(buf_writer, "{}", line)?;
+  fn storage(&self) -> Result<Box<dyn Storage>> {
+    match self.backend {
+      Backend::Jsonl => Ok(Box::new(JsonlFile::new(self.path()))),
+      #[cfg(feature = "sqlite")]
+      Backend::Sqlite => Ok(Box::new(crate::sqlite::SqliteFile::new(self.path()))),
+      #[cfg(not(feature = "sqlite"))]
+      Backend::Sqlite => Err(crate::error::RecallError::other("SQLite support is not enabled."))
     }
+  }
+}
+
+impl Storage for List {
+  fn cards(&self) -> Result<Vec<Card>> {
+    self.storage()?.cards()
+  }
 
-    buf_writer.flush()?;
-    Ok(())
+  fn save_cards(&self, cards: Vec<Card>) -> Result<()> {
+    self.storage()?.save_cards(cards)
   }
 
-  pub fn delete(&self) -> Result<()> {
-    remove_file(self.path())?;
+  fn update_cards(&self, cards: Vec<Card>) -> Result<Vec<Card>> {
+    self.storage()?.update_cards(cards)
+  }
 
-    Ok(())
+  fn count_due_at(&self, date_time: DateTime<Utc>) -> Result<usize> {
+    self.storage()?.count_due_at(date_time)
+  }
+
+  fn migrate(&self) -> Result<bool> {
+    self.storage()?.migrate()
+  }
+
+  fn delete(&self) -> Result<()> {
+    self.storage()?.delete()
   }
 }
 
 pub fn get_lists<P: AsRef<Path>>(dirname: P) -> Result<impl Iterator<Item = List>> {
+  let dirname = dirname.as_ref();
+
   Ok(
-    read_dir(dirname)?
+    read_dir(dirname).with_path(dirname)?
     .filter_map(|entry| entry.ok())
     .map(|entry| entry.path())
-    .filter(|path| path.extension().and_then(|x| x.to_str()) == Some("jsonl"))
+    .filter(|path| {
+      path.extension()
+      .and_then(|x| x.to_str())
+      .and_then(Backend::from_extension)
+      .map(|backend| backend.is_supported())
+      .unwrap_or(false)
+    })
     .filter_map(|path| List::new(&path))
   )
 }
 
-pub fn list_exists<P: AsRef<Path>>(dirname: P, name: &str) -> bool {
+pub fn find_list<P: AsRef<Path>>(dirname: P, name: &str) -> Option<List> {
   get_lists(dirname).ok()
-  .map(|lists| lists.into_iter().any(|list| list.name() == name))
-  .unwrap_or(false)
+  .and_then(|mut lists| lists.find(|list| list.name() == name))
+}
+
+pub fn list_exists<P: AsRef<Path>>(dirname: P, name: &str) -> bool {
+  find_list(dirname, name).is_some()
 }
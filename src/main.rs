@@ -1,9 +1,8 @@
 mod app;
 mod cli;
 mod commands;
-mod list;
-mod card;
 
+use std::io::{stdout, Write};
 use commands::RecallError;
 use clap::App;
 
@@ -12,27 +11,67 @@ fn main() {
     .author(app::author())
     .version(app::version())
     .about(app::description())
+    .arg(cli::Format::arg())
+    .arg(cli::deck_dir_arg())
+    .arg(cli::color_arg())
+    .arg(cli::now_arg())
+    .arg(cli::seed_arg())
     .subcommand(commands::list::subcommand())
+    .subcommand(commands::card::subcommand())
+    .subcommand(commands::config::subcommand())
     .subcommand(commands::info::subcommand())
+    .subcommand(commands::status::subcommand())
+    .subcommand(commands::doctor::subcommand())
+    .subcommand(commands::dedupe::subcommand())
+    .subcommand(commands::migrate::subcommand())
     .subcommand(commands::review::subcommand())
-    .subcommand(commands::learn::subcommand());
+    .subcommand(commands::reset::subcommand())
+    .subcommand(commands::reschedule::subcommand())
+    .subcommand(commands::learn::subcommand())
+    .subcommand(commands::study::subcommand())
+    .subcommand(commands::simulate::subcommand())
+    .subcommand(commands::trash::subcommand())
+    .subcommand(commands::undo::subcommand());
 
   let matches = app.clone().get_matches();
 
+  if let (_, Some(matches)) = matches.subcommand() {
+    if let Ok(config) = app::config(matches) {
+      app::apply_color(&config);
+    }
+  }
+
+  let mut exit_code = 0;
   let result = match matches.subcommand() {
+    ("card", Some(matches)) => commands::card::dispatch(matches),
+    ("config", Some(matches)) => commands::config::dispatch(matches),
+    ("dedupe", Some(matches)) => commands::dedupe::dispatch(matches),
+    ("doctor", Some(matches)) => commands::doctor::dispatch(matches),
     ("info", Some(matches)) => commands::info::dispatch(matches),
     ("learn", Some(matches)) => commands::learn::dispatch(matches),
     ("list", Some(matches)) => commands::list::dispatch(matches),
+    ("migrate", Some(matches)) => commands::migrate::dispatch(matches),
+    ("reschedule", Some(matches)) => commands::reschedule::dispatch(matches),
+    ("reset", Some(matches)) => commands::reset::dispatch(matches),
     ("review", Some(matches)) => commands::review::dispatch(matches),
-    _ => app.print_help().map_err(|_| RecallError::new("Printing help failed")),
+    ("simulate", Some(matches)) => commands::simulate::dispatch(matches),
+    ("status", Some(matches)) => commands::status::dispatch(matches).map(|code| exit_code = code),
+    ("study", Some(matches)) => commands::study::dispatch(matches),
+    ("trash", Some(matches)) => commands::trash::dispatch(matches),
+    ("undo", Some(matches)) => commands::undo::dispatch(matches),
+    _ => app.print_help().map_err(|err| RecallError::other(err.to_string())),
   };
 
-  match result {
-    Err(err) => {
-      println!();
-      cli::print_error_strip(err);
-      println!();
-    },
-    _ => {}
-  };
+  if let Err(err) = result {
+    eprintln!();
+    cli::eprint_error(&err);
+    eprintln!();
+
+    std::process::exit(err.exit_code());
+  }
+
+  if exit_code != 0 {
+    stdout().flush().ok();
+    std::process::exit(exit_code);
+  }
 }
@@ -0,0 +1,78 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use crate::card::Card;
+use crate::store::DeckStore;
+use crate::error::{RecallError, Result};
+
+/// Decks that only live in memory, e.g. for tests and simulations.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+  decks: RefCell<BTreeMap<String, Vec<Card>>>
+}
+
+impl MemoryStore {
+  pub fn new() -> MemoryStore {
+    MemoryStore::default()
+  }
+}
+
+impl DeckStore for MemoryStore {
+  fn decks(&self) -> Result<Vec<String>> {
+    Ok(self.decks.borrow().keys().cloned().collect())
+  }
+
+  fn cards(&self, deck: &str) -> Result<Vec<Card>> {
+    self.decks.borrow().get(deck)
+    .cloned()
+    .ok_or_else(|| RecallError::ListNotFound(deck.to_string()))
+  }
+
+  /// Overwrites the deck, numbering the cards by their position.
+  fn save_cards(&self, deck: &str, mut cards: Vec<Card>) -> Result<()> {
+    let mut decks = self.decks.borrow_mut();
+    let entry = decks.get_mut(deck)
+      .ok_or_else(|| RecallError::ListNotFound(deck.to_string()))?;
+
+    for (i, card) in cards.iter_mut().enumerate() {
+      card.line_number = Some(i + 1);
+    }
+
+    *entry = cards;
+    Ok(())
+  }
+
+  fn update_cards(&self, deck: &str, cards: Vec<Card>) -> Result<Vec<Card>> {
+    let mut decks = self.decks.borrow_mut();
+    let stored = decks.get_mut(deck)
+      .ok_or_else(|| RecallError::ListNotFound(deck.to_string()))?;
+    let mut previous = Vec::new();
+
+    for card in cards {
+      let entry = stored.iter_mut()
+        .find(|x| x.line_number.is_some() && x.line_number == card.line_number);
+
+      if let Some(entry) = entry {
+        previous.push(std::mem::replace(entry, card));
+      }
+    }
+
+    Ok(previous)
+  }
+
+  fn create_deck(&self, deck: &str) -> Result<()> {
+    let mut decks = self.decks.borrow_mut();
+
+    if decks.contains_key(deck) {
+      return Err(RecallError::ListExists(deck.to_string()));
+    }
+
+    decks.insert(deck.to_string(), Vec::new());
+    Ok(())
+  }
+
+  fn delete_deck(&self, deck: &str) -> Result<()> {
+    self.decks.borrow_mut().remove(deck)
+    .map(|_| ())
+    .ok_or_else(|| RecallError::ListNotFound(deck.to_string()))
+  }
+}
@@ -0,0 +1,310 @@
+//! Serialization of cards as lines of a list.
+//!
+//! Lists of format version 1 store every card as a positional array
+//! `[front, back, notes, level, due, correct, total, suspended, buried_until]`.
+//! Version 2 starts with a header line `{"format": "recall", "version": 2}`
+//! and stores every card as an object with named fields. Fields unknown to
+//! this version of recall are kept as they are. Only version 2 has a field for
+//! the learning step, so lists with cards in learning are stored in it.
+
+use std::convert::TryFrom;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Map, Value};
+use crate::card::Card;
+
+pub const LEGACY_VERSION: u32 = 1;
+pub const CURRENT_VERSION: u32 = 2;
+
+const FIELDS: [&str; 9] = [
+  "front", "back", "notes", "level", "due",
+  "correct", "total", "suspended", "buried_until"
+];
+
+#[derive(Debug)]
+pub enum Record {
+  Header(u32),
+  Card(Box<Card>),
+  Malformed
+}
+
+pub fn header(version: u32) -> Value {
+  json!({"format": "recall", "version": version})
+}
+
+pub fn parse_line(line: &[u8]) -> Record {
+  match serde_json::from_slice::<Value>(line) {
+    Ok(Value::Object(object)) if object.get("format") == Some(&json!("recall")) => {
+      object.get("version")
+      .and_then(|x| x.as_u64())
+      .map(|x| Record::Header(x as u32))
+      .unwrap_or(Record::Malformed)
+    },
+    Ok(value) => card_from_value(value)
+      .map(|card| Record::Card(Box::new(card)))
+      .unwrap_or(Record::Malformed),
+    Err(_) => Record::Malformed
+  }
+}
+
+/// Maps the positional fields of a legacy array to their names.
+pub fn legacy_to_object(arr: Vec<Value>) -> Map<String, Value> {
+  FIELDS.iter()
+  .map(|name| name.to_string())
+  .zip(arr)
+  .collect()
+}
+
+/// Reads a card from an object or a legacy array. Missing and null fields get
+/// default values, fields of the wrong type make the card malformed.
+pub fn card_from_value(value: Value) -> Option<Card> {
+  let mut object = match value {
+    Value::Array(arr) => legacy_to_object(arr),
+    Value::Object(object) => object,
+    _ => return None
+  };
+
+  let string = |x: &Value| x.as_str().map(|x| x.to_string());
+  let date_time = |x: &Value| {
+    x.as_str()
+    .and_then(|x| DateTime::parse_from_rfc3339(x).ok())
+    .map(|date_time| date_time.with_timezone(&Utc))
+  };
+  let count = |x: &Value| x.as_u64().and_then(|x| u32::try_from(x).ok());
+
+  let front = take(&mut object, "front", string)?.unwrap_or_default();
+  let back = take(&mut object, "back", string)?.unwrap_or_default();
+  let notes = take(&mut object, "notes", string)?.unwrap_or_default();
+  let mut card = Card::new(front, back, notes);
+
+  card.level = take(&mut object, "level", |x| x.as_i64().and_then(|x| i8::try_from(x).ok()))?.unwrap_or(0);
+  card.due_time = take(&mut object, "due", date_time)?;
+  card.correct_count = take(&mut object, "correct", count)?.unwrap_or(0);
+  card.total_count = take(&mut object, "total", count)?.unwrap_or(0);
+  card.suspended = take(&mut object, "suspended", Value::as_bool)?.unwrap_or(false);
+  card.buried_until = take(&mut object, "buried_until", date_time)?;
+  card.learning_step = take(&mut object, "step", |x| x.as_u64().map(|x| x as usize))?;
+  card.extra = object;
+
+  Some(card)
+}
+
+/// Removes a field from an object and converts it. Returns `None` if it has
+/// the wrong type and `Some(None)` if it is missing or null.
+fn take<T, F>(object: &mut Map<String, Value>, name: &str, convert: F) -> Option<Option<T>>
+where F: Fn(&Value) -> Option<T> {
+  match object.remove(name) {
+    None | Some(Value::Null) => Some(None),
+    Some(value) => convert(&value).map(Some)
+  }
+}
+
+/// Serializes a card as an object, leaving out fields with default values.
+pub fn card_to_value(card: &Card) -> Value {
+  let mut object = Map::new();
+
+  object.insert("front".to_string(), json!(card.front));
+  object.insert("back".to_string(), json!(card.back));
+  object.insert("notes".to_string(), json!(card.notes));
+  object.insert("level".to_string(), json!(card.level));
+
+  if let Some(due_time) = card.due_time {
+    object.insert("due".to_string(), json!(due_time.to_rfc3339()));
+  }
+
+  if card.total_count > 0 {
+    object.insert("correct".to_string(), json!(card.correct_count));
+    object.insert("total".to_string(), json!(card.total_count));
+  }
+
+  if card.suspended {
+    object.insert("suspended".to_string(), json!(true));
+  }
+
+  if let Some(buried_until) = card.buried_until {
+    object.insert("buried_until".to_string(), json!(buried_until.to_rfc3339()));
+  }
+
+  if let Some(step) = card.learning_step {
+    object.insert("step".to_string(), json!(step));
+  }
+
+  for (key, value) in card.extra.iter() {
+    object.entry(key.clone()).or_insert_with(|| value.clone());
+  }
+
+  Value::Object(object)
+}
+
+/// Serializes a card as a legacy array. Unknown fields and the learning step
+/// are lost.
+pub fn card_to_legacy_value(card: &Card) -> Value {
+  json!([
+    card.front,
+    card.back,
+    card.notes,
+    card.level,
+    card.due_time.map(|x| x.to_rfc3339()),
+    card.correct_count,
+    card.total_count,
+    card.suspended,
+    card.buried_until.map(|x| x.to_rfc3339())
+  ])
+}
+
+/// Checks the fields of a stored card for wrong types and impossible values,
+/// returning a description of each problem found.
+pub fn check_value(value: &Value, max_level: i8) -> Vec<String> {
+  let mut problems = Vec::new();
+
+  let object = match value {
+    Value::Array(arr) => {
+      if arr.len() > FIELDS.len() {
+        problems.push(format!("Expected at most {} fields, found {}.", FIELDS.len(), arr.len()));
+      }
+
+      legacy_to_object(arr.clone())
+    },
+    Value::Object(object) => object.clone(),
+    _ => {
+      problems.push("Expected an object or an array of card fields.".to_string());
+      return problems;
+    }
+  };
+
+  for (name, value) in object.iter() {
+    let expected = match name.as_str() {
+      "front" | "back" | "notes" => if value.is_string() { None } else { Some("a string") },
+      "level" => if value.is_i64() { None } else { Some("an integer") },
+      "due" | "buried_until" => match value {
+        Value::Null => None,
+        Value::String(x) if DateTime::parse_from_rfc3339(x).is_ok() => None,
+        _ => Some("null or an RFC 3339 date")
+      },
+      "correct" | "total" | "step" => if value.is_u64() { None } else { Some("a non-negative integer") },
+      "suspended" => if value.is_boolean() { None } else { Some("a boolean") },
+      _ => None
+    };
+
+    if let Some(expected) = expected {
+      problems.push(format!("Field `{}` should be {}, found `{}`.", name, expected, value));
+    }
+  }
+
+  let front = object.get("front").and_then(|x| x.as_str());
+  let level = object.get("level").and_then(|x| x.as_i64());
+  let due_time = object.get("due").filter(|x| !x.is_null());
+  let correct_count = object.get("correct").and_then(|x| x.as_u64());
+  let total_count = object.get("total").and_then(|x| x.as_u64());
+
+  if front.map(|x| x.trim().is_empty()).unwrap_or(true) {
+    problems.push("Card has no front.".to_string());
+  }
+
+  match level {
+    Some(x) if x < 0 => problems.push(format!("Level {} is negative.", x)),
+    Some(x) if x > max_level as i64 => {
+      problems.push(format!("Level {} exceeds the maximum level {}.", x, max_level))
+    },
+    Some(x) if x > 0 && due_time.is_none() => {
+      problems.push("Card has been learned, but has no due time.".to_string())
+    },
+    _ => {}
+  }
+
+  if let (Some(correct_count), Some(total_count)) = (correct_count, total_count) {
+    if correct_count > total_count {
+      problems.push(format!(
+        "Correct count {} exceeds total count {}.",
+        correct_count,
+        total_count
+      ));
+    }
+  }
+
+  problems
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::TimeZone;
+  use super::*;
+
+  fn learned_card() -> Card {
+    let mut card = Card::new("hola".to_string(), "hello".to_string(), "#greeting".to_string());
+
+    card.level = 3;
+    card.due_time = Some(Utc.with_ymd_and_hms(2024, 1, 2, 12, 0, 0).unwrap());
+    card.correct_count = 4;
+    card.total_count = 5;
+    card.suspended = true;
+    card.buried_until = Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap());
+    card
+  }
+
+  #[test]
+  fn objects_round_trip() {
+    let mut card = learned_card();
+
+    card.extra.insert("source".to_string(), json!("book"));
+
+    let parsed = card_from_value(card_to_value(&card)).unwrap();
+
+    assert_eq!(parsed.extra, card.extra);
+    assert_eq!(card_to_value(&parsed), card_to_value(&card));
+  }
+
+  #[test]
+  fn learning_steps_round_trip() {
+    let mut card = Card::new("a".to_string(), "b".to_string(), String::new());
+
+    card.learning_step = Some(1);
+
+    let value = card_to_value(&card);
+
+    assert_eq!(value["step"], json!(1));
+    assert_eq!(card_from_value(value).unwrap().learning_step, Some(1));
+    assert!(!check_value(&json!({"front": "a", "step": -1}), 9).is_empty());
+  }
+
+  #[test]
+  fn legacy_arrays_round_trip() {
+    let card = learned_card();
+    let value = card_to_legacy_value(&card);
+    let parsed = card_from_value(value.clone()).unwrap();
+
+    assert_eq!(value.as_array().map(|x| x.len()), Some(FIELDS.len()));
+    assert_eq!(card_to_value(&parsed), card_to_value(&card));
+    assert!(check_value(&value, 9).is_empty());
+  }
+
+  #[test]
+  fn new_cards_leave_out_default_fields() {
+    let card = Card::new("a".to_string(), "b".to_string(), String::new());
+
+    assert_eq!(card_to_value(&card), json!({"front": "a", "back": "b", "notes": "", "level": 0}));
+  }
+
+  #[test]
+  fn parses_lines() {
+    assert!(matches!(parse_line(br#"{"format":"recall","version":2}"#), Record::Header(2)));
+    assert!(matches!(parse_line(br#"["a","b","",0,null,0,0,false,null]"#), Record::Card(_)));
+    assert!(matches!(parse_line(br#"{"front":"a"}"#), Record::Card(_)));
+    assert!(matches!(parse_line(br#"{"front":"a","due":null}"#), Record::Card(_)));
+    assert!(matches!(parse_line(br#"{"front":"a","level":"high"}"#), Record::Malformed));
+    assert!(matches!(parse_line(br#"{"front":1}"#), Record::Malformed));
+    assert!(matches!(parse_line(br#"{"front":"a","due":"tomorrow"}"#), Record::Malformed));
+    assert!(matches!(parse_line(br#"{"format":"recall"}"#), Record::Malformed));
+    assert!(matches!(parse_line(b"42"), Record::Malformed));
+    assert!(matches!(parse_line(b"[\"a\","), Record::Malformed));
+  }
+
+  #[test]
+  fn checks_values() {
+    assert!(check_value(&json!({"front": "a", "level": 0}), 9).is_empty());
+    assert_eq!(check_value(&json!({"front": "a", "level": 2}), 9).len(), 1);
+    assert_eq!(check_value(&json!({"front": "", "level": 10}), 9).len(), 2);
+    assert_eq!(check_value(&json!({"front": "a", "level": 0, "correct": 2, "total": 1}), 9).len(), 1);
+    assert_eq!(check_value(&json!(["a", "b", "", 0, null, 0, 0, false, null, 1]), 9).len(), 1);
+    assert_eq!(check_value(&json!({"front": "a", "level": 5, "due": "2024-01-01T00:00:00Z"}), 4).len(), 1);
+  }
+}
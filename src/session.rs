@@ -0,0 +1,1059 @@
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::fs::{create_dir_all, remove_file, rename, File};
+use std::path::{Path, PathBuf};
+use std::io::{BufReader, BufRead, BufWriter, Write};
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use colored::Colorize;
+use rand::{seq::SliceRandom, Rng};
+use serde_json::{json, Value};
+use crate::card::{Card, Outcome, locate_cards, update_cards};
+use crate::clock::Clock;
+use crate::config::Config;
+use crate::editor;
+use crate::store::DeckStore;
+use crate::terminal::Terminal;
+use crate::record::{card_from_value, card_to_value};
+use crate::error::{RecallError, Result, WithPath};
+
+fn state_dir<P: AsRef<Path>>(dirname: P) -> PathBuf {
+  dirname.as_ref().join(".recall")
+}
+
+/// Session files of earlier versions refer to decks by the path of their list,
+/// e.g. `./spanish.jsonl`.
+fn deck_name(deck: String) -> String {
+  if deck.ends_with(".jsonl") || deck.ends_with(".sqlite") {
+    Path::new(&deck).file_stem()
+    .and_then(|x| x.to_str())
+    .map(|x| x.to_string())
+    .unwrap_or(deck)
+  } else {
+    deck
+  }
+}
+
+fn undo_path<P: AsRef<Path>>(dirname: P) -> PathBuf {
+  state_dir(dirname).join("undo.jsonl")
+}
+
+/// Remembers the given cards, as they were before the last session, so that
+/// `load_undo` can restore them later.
+pub fn save_undo<'a, P, I>(dirname: P, cards: I) -> Result<()>
+where
+  P: AsRef<Path>,
+  I: IntoIterator<Item = &'a (String, Card)>
+{
+  let path = undo_path(&dirname);
+
+  create_dir_all(state_dir(&dirname)).with_path(state_dir(&dirname))?;
+
+  let file = File::create(&path).with_path(&path)?;
+  let mut buf_writer = BufWriter::new(file);
+
+  for (deck, card) in cards {
+    let line = json!([deck, card.line_number, card_to_value(card)]);
+    writeln!(buf_writer, "{}", serde_json::to_string(&line).unwrap()).with_path(&path)?;
+  }
+
+  buf_writer.flush().with_path(&path)
+}
+
+pub fn load_undo<P: AsRef<Path>>(dirname: P) -> Result<Option<Vec<(String, Card)>>> {
+  let path = undo_path(dirname);
+  if !path.exists() {
+    return Ok(None);
+  }
+
+  let file = File::open(&path).with_path(&path)?;
+  let buf_reader = BufReader::new(file);
+  let mut result = Vec::new();
+
+  for (i, line) in buf_reader.lines().enumerate() {
+    let line = line.with_path(&path)?;
+    let (deck, line_number, value) = serde_json::from_str::<(String, Option<usize>, Value)>(&line)
+      .map_err(|err| RecallError::parse(&path, i + 1, err))?;
+
+    if let Some(mut card) = card_from_value(value) {
+      card.line_number = line_number;
+      result.push((deck_name(deck), card));
+    }
+  }
+
+  Ok(Some(result))
+}
+
+pub fn clear_undo<P: AsRef<Path>>(dirname: P) -> Result<()> {
+  let path = undo_path(dirname);
+
+  if path.exists() {
+    remove_file(&path).with_path(&path)?;
+  }
+
+  Ok(())
+}
+
+fn checkpoint_path<P: AsRef<Path>>(dirname: P, kind: &str) -> PathBuf {
+  state_dir(dirname).join(format!("session-{}.json", kind))
+}
+
+/// An unfinished session, consisting of the cards left to study and the
+/// answers given so far.
+pub struct Checkpoint {
+  pub queue: Vec<(String, Card)>,
+  pub results: Vec<(String, Card, Outcome)>
+}
+
+fn outcome_to_str(outcome: Outcome) -> &'static str {
+  match outcome {
+    Outcome::Remembered => "remembered",
+    Outcome::Forgotten => "forgotten",
+    Outcome::Suspended => "suspended",
+    Outcome::Buried => "buried"
+  }
+}
+
+fn outcome_from_str(outcome: &str) -> Option<Outcome> {
+  match outcome {
+    "remembered" => Some(Outcome::Remembered),
+    "forgotten" => Some(Outcome::Forgotten),
+    "suspended" => Some(Outcome::Suspended),
+    "buried" => Some(Outcome::Buried),
+    _ => None
+  }
+}
+
+pub fn save_checkpoint<P: AsRef<Path>>(
+  dirname: P,
+  kind: &str,
+  queue: &[(String, Card)],
+  results: &[(String, Card, Outcome)]
+) -> Result<()> {
+  create_dir_all(state_dir(&dirname)).with_path(state_dir(&dirname))?;
+
+  let value = json!({
+    "queue": queue.iter()
+      .map(|(deck, card)| json!([deck, card.line_number, card_to_value(card)]))
+      .collect::<Vec<_>>(),
+    "results": results.iter()
+      .map(|(deck, card, outcome)| json!([
+        deck,
+        card.line_number,
+        outcome_to_str(*outcome),
+        card_to_value(card)
+      ]))
+      .collect::<Vec<_>>()
+  });
+
+  // Write to a temporary file first, so an interrupt never leaves a
+  // truncated checkpoint behind.
+
+  let path = checkpoint_path(&dirname, kind);
+  let tmp_path = path.with_extension("json.tmp");
+  let file = File::create(&tmp_path).with_path(&tmp_path)?;
+  let mut buf_writer = BufWriter::new(file);
+
+  writeln!(buf_writer, "{}", serde_json::to_string(&value).unwrap()).with_path(&tmp_path)?;
+  buf_writer.flush().with_path(&tmp_path)?;
+  rename(&tmp_path, &path).with_path(&path)
+}
+
+/// Reads the checkpoint of the given kind. A checkpoint that cannot be read
+/// is an error rather than no checkpoint, so answers are never lost silently.
+pub fn load_checkpoint<P: AsRef<Path>>(dirname: P, kind: &str) -> Result<Option<Checkpoint>> {
+  let path = checkpoint_path(dirname, kind);
+  if !path.exists() {
+    return Ok(None);
+  }
+
+  let file = File::open(&path).with_path(&path)?;
+  let value = serde_json::from_reader::<_, Value>(BufReader::new(file))
+    .map_err(|err| RecallError::parse(&path, 1, err))?;
+
+  let queue = serde_json::from_value::<Vec<(String, Option<usize>, Value)>>(value["queue"].clone())
+    .map_err(|err| RecallError::parse(&path, 1, err))?
+    .into_iter()
+    .filter_map(|(deck, line_number, value)| {
+      let mut card = card_from_value(value)?;
+
+      card.line_number = line_number;
+      Some((deck_name(deck), card))
+    })
+    .collect();
+  let results = serde_json::from_value::<Vec<(String, Option<usize>, String, Value)>>(value["results"].clone())
+    .map_err(|err| RecallError::parse(&path, 1, err))?
+    .into_iter()
+    .filter_map(|(deck, line_number, outcome, value)| {
+      let mut card = card_from_value(value)?;
+
+      card.line_number = line_number;
+      outcome_from_str(&outcome).map(|outcome| (deck_name(deck), card, outcome))
+    })
+    .collect();
+
+  Ok(Some(Checkpoint {queue, results}))
+}
+
+pub fn clear_checkpoint<P: AsRef<Path>>(dirname: P, kind: &str) -> Result<()> {
+  let path = checkpoint_path(dirname, kind);
+
+  if path.exists() {
+    remove_file(&path).with_path(&path)?;
+  }
+
+  Ok(())
+}
+
+fn daily_path<P: AsRef<Path>>(dirname: P) -> PathBuf {
+  state_dir(dirname).join("daily.json")
+}
+
+/// Numbers of cards learned and reviewed on one day, in local time, for the
+/// daily limits.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct DailyCounts {
+  pub learned: usize,
+  pub reviewed: usize
+}
+
+fn local_date(now: DateTime<Utc>) -> NaiveDate {
+  now.with_timezone(&Local).naive_local().date()
+}
+
+/// Returns the counts of the day of `now`, which are zero on a new day.
+pub fn load_daily_counts<P: AsRef<Path>>(dirname: P, now: DateTime<Utc>) -> Result<DailyCounts> {
+  let path = daily_path(dirname);
+
+  if !path.exists() {
+    return Ok(DailyCounts::default());
+  }
+
+  let file = File::open(&path).with_path(&path)?;
+  let value = serde_json::from_reader::<_, Value>(BufReader::new(file))
+    .map_err(|err| RecallError::parse(&path, 1, err))?;
+
+  if value["date"].as_str() != Some(&local_date(now).to_string()) {
+    return Ok(DailyCounts::default());
+  }
+
+  Ok(DailyCounts {
+    learned: value["learned"].as_u64().unwrap_or(0) as usize,
+    reviewed: value["reviewed"].as_u64().unwrap_or(0) as usize
+  })
+}
+
+fn save_daily_counts<P: AsRef<Path>>(dirname: P, counts: DailyCounts, now: DateTime<Utc>) -> Result<()> {
+  let path = daily_path(&dirname);
+  let value = json!({
+    "date": local_date(now).to_string(),
+    "learned": counts.learned,
+    "reviewed": counts.reviewed
+  });
+
+  create_dir_all(state_dir(&dirname)).with_path(state_dir(&dirname))?;
+  std::fs::write(&path, serde_json::to_string(&value).unwrap()).with_path(&path)
+}
+
+/// The number of cards left before reaching a limit, if there is one.
+pub fn remaining(limit: Option<usize>, done: usize) -> Option<usize> {
+  limit.map(|limit| limit.saturating_sub(done))
+}
+
+/// Applies the answers of a finished session to the cards, writes them back to
+/// their decks and remembers their previous state for `recall undo`. Cards in
+/// learning can be answered several times in a session, only their last
+/// answer is applied.
+pub fn commit<S: DeckStore + ?Sized, P: AsRef<Path>>(
+  store: &S,
+  config: &Config,
+  dirname: P,
+  kind: &str,
+  results: Vec<(String, Card, Outcome)>,
+  now: DateTime<Utc>
+) -> Result<()> {
+  let mut counts = load_daily_counts(&dirname, now)?;
+
+  for (deck, card, outcome) in results.iter() {
+    if *outcome == Outcome::Remembered || *outcome == Outcome::Forgotten {
+      if card.is_new(config.ladder(deck)) {
+        counts.learned += 1;
+      } else if !card.is_learning() {
+        counts.reviewed += 1;
+      }
+    }
+  }
+
+  let mut answered = HashSet::new();
+  let mut results = results.into_iter()
+    .rev()
+    .filter(|(deck, card, _)| answered.insert((deck.clone(), card.line_number)))
+    .collect::<Vec<_>>();
+
+  results.reverse();
+
+  for &mut (ref deck, ref mut card, outcome) in results.iter_mut() {
+    card.apply(outcome, config.ladder(deck), now);
+  }
+
+  save_daily_counts(&dirname, counts, now)?;
+
+  let previous = update_cards(store, results.into_iter().map(|(deck, card, _)| (deck, card)))?;
+
+  save_undo(&dirname, &previous)?;
+  clear_checkpoint(&dirname, kind)
+}
+
+/// Finds the cards of a checkpoint in their decks again, see [`locate_cards`],
+/// and returns how many of them are gone.
+fn locate_checkpoint<S: DeckStore + ?Sized>(store: &S, checkpoint: &mut Checkpoint) -> usize {
+  let lines = locate_cards(store, &checkpoint.queue);
+  let mut stale_count = 0;
+
+  checkpoint.queue = std::mem::take(&mut checkpoint.queue).into_iter()
+    .zip(lines)
+    .filter_map(|((deck, mut card), line)| {
+      stale_count += line.is_none() as usize;
+      card.line_number = Some(line?);
+      Some((deck, card))
+    })
+    .collect();
+
+  let results = std::mem::take(&mut checkpoint.results);
+  let cards = results.iter()
+    .map(|(deck, card, _)| (deck.clone(), card.clone()))
+    .collect::<Vec<_>>();
+
+  checkpoint.results = results.into_iter()
+    .zip(locate_cards(store, &cards))
+    .filter_map(|((deck, mut card, outcome), line)| {
+      stale_count += line.is_none() as usize;
+      card.line_number = Some(line?);
+      Some((deck, card, outcome))
+    })
+    .collect();
+
+  stale_count
+}
+
+/// Offers to resume an unfinished session of the given kind, if there is one.
+/// If the input ends before an answer, the session is resumed. Cards that
+/// changed or moved since the session was interrupted are found again by
+/// their front, the ones that are gone are dropped with a warning.
+pub fn prompt_resume<S: DeckStore + ?Sized, P: AsRef<Path>>(
+  term: &mut dyn Terminal,
+  store: &S,
+  config: &Config,
+  dirname: P,
+  kind: &str,
+  now: DateTime<Utc>
+) -> Result<Option<Checkpoint>> {
+  let checkpoint = load_checkpoint(&dirname, kind)?;
+
+  let mut checkpoint = match checkpoint {
+    Some(checkpoint) => checkpoint,
+    None => return Ok(None)
+  };
+  let stale_count = locate_checkpoint(store, &mut checkpoint);
+
+  term.println("");
+  term.println(&format!(
+    "Found an unfinished session with {} answered and {} remaining card(s).",
+    checkpoint.results.len(),
+    checkpoint.queue.len()
+  ));
+  term.println("");
+
+  if stale_count > 0 {
+    term.print_error_strip(&format!(
+      "{} card(s) of the unfinished session were changed or removed since and are left out.",
+      stale_count
+    ));
+    term.println("");
+  }
+
+  let choice = term.prompt_multiple_choice(
+    &format!(
+      "{}esume, {}ave answers and start over, {}iscard",
+      "r".bold().cyan().underline(),
+      "s".bold().cyan().underline(),
+      "d".bold().cyan().underline()
+    ),
+    &['r', 's', 'd']
+  )?;
+
+  match choice {
+    Some('r') | None => Ok(Some(checkpoint)),
+    Some('s') => {
+      commit(store, config, &dirname, kind, checkpoint.results, now)?;
+
+      Ok(None)
+    },
+    Some('d') => {
+      clear_checkpoint(&dirname, kind)?;
+
+      Ok(None)
+    },
+    _ => unreachable!()
+  }
+}
+
+/// The order in which the cards of a session are shown.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Order {
+  Random,
+  /// The longest overdue first.
+  Overdue,
+  /// The lowest level first.
+  Level,
+  /// Critical cards first.
+  Critical,
+  /// The longest overdue compared to their interval first, so a card a day
+  /// late on a 4 hour interval comes before a card a day late on a month.
+  Relative
+}
+
+impl Order {
+  pub fn from_name(name: &str) -> Option<Order> {
+    match name {
+      "random" => Some(Order::Random),
+      "overdue" => Some(Order::Overdue),
+      "level" => Some(Order::Level),
+      "critical" => Some(Order::Critical),
+      "relative" => Some(Order::Relative),
+      _ => None
+    }
+  }
+}
+
+/// Sorts the cards for a session. Cards that are equal in the given order are
+/// shuffled.
+pub fn order_cards<R: Rng>(cards: &mut [(String, Card)], order: Order, config: &Config, now: DateTime<Utc>, rng: &mut R) {
+  cards.shuffle(rng);
+
+  let overdue = |card: &Card| card.due_time.map(|due_time| now - due_time).unwrap_or_else(Duration::zero);
+
+  match order {
+    Order::Random => {},
+    Order::Overdue => cards.sort_by_key(|(_, card)| Reverse(overdue(card))),
+    Order::Level => cards.sort_by_key(|(_, card)| card.level),
+    Order::Critical => cards.sort_by_key(|(deck, card)| !card.critical(config.ladder(deck))),
+    Order::Relative => {
+      let relative = |deck: &str, card: &Card| {
+        let interval = config.ladder(deck).interval(card.level).num_seconds().max(1);
+        overdue(card).num_seconds() as f64 / interval as f64
+      };
+
+      cards.sort_by(|(x_deck, x), (y_deck, y)| relative(y_deck, y).total_cmp(&relative(x_deck, x)));
+    }
+  }
+}
+
+/// Mixes new cards into the reviews, showing one new card after every
+/// `reviews_per_new` reviews. Cards left over when either runs out come last.
+pub fn mix_cards(reviews: Vec<(String, Card)>, new: Vec<(String, Card)>, reviews_per_new: usize) -> Vec<(String, Card)> {
+  let mut result = Vec::with_capacity(reviews.len() + new.len());
+  let mut reviews = reviews.into_iter().peekable();
+  let mut new = new.into_iter();
+
+  while reviews.peek().is_some() {
+    result.extend(reviews.by_ref().take(reviews_per_new));
+
+    match new.next() {
+      Some(card) => result.push(card),
+      None => break
+    }
+  }
+
+  result.extend(reviews);
+  result.extend(new);
+  result
+}
+
+/// Fails if another card of the deck already has the front a card is about to
+/// be changed to.
+fn check_front(store: &dyn DeckStore, deck: &str, card: &Card, front: &str) -> Result<()> {
+  if front == card.front {
+    return Ok(());
+  }
+
+  let is_duplicate = store.cards(deck)?
+    .iter()
+    .any(|other| other.front == front && other.line_number != card.line_number);
+
+  if is_duplicate {
+    return Err(RecallError::DuplicateCard {
+      list: deck.to_string(),
+      front: front.to_string()
+    });
+  }
+
+  Ok(())
+}
+
+/// Shows the given cards one by one, in order, until all of them are answered,
+/// the user finishes the session or the input ends. Unless it is a dry run
+/// without a checkpoint directory and kind, the session is checkpointed after
+/// every answer, so it can be resumed after an interrupt.
+///
+/// Cards still in learning after an answer are queued again and shown once
+/// their step is over. If only such cards are left, the session ends and they
+/// come back in a later session.
+///
+/// An interrupt ends the session with `RecallError::Interrupted`. The answers
+/// given so far are not committed, but stay in the checkpoint to be resumed.
+#[allow(clippy::too_many_arguments)]
+pub fn loop_cards<R: Rng>(
+  term: &mut dyn Terminal,
+  store: &dyn DeckStore,
+  config: &Config,
+  checkpoint: Option<(&Path, &str)>,
+  cards: Vec<(String, Card)>,
+  result: Vec<(String, Card, Outcome)>,
+  clock: &dyn Clock,
+  rng: &mut R
+) -> Result<Vec<(String, Card, Outcome)>> {
+  let result = show_cards(term, store, config, checkpoint, cards, result, clock, rng);
+
+  if let (Err(RecallError::Interrupted), Some(_)) = (&result, checkpoint) {
+    term.println("");
+    term.print_error_strip(&"Session interrupted, run the command again to resume it.");
+  }
+
+  result
+}
+
+#[allow(clippy::too_many_arguments)]
+fn show_cards<R: Rng>(
+  term: &mut dyn Terminal,
+  store: &dyn DeckStore,
+  config: &Config,
+  checkpoint: Option<(&Path, &str)>,
+  mut cards: Vec<(String, Card)>,
+  mut result: Vec<(String, Card, Outcome)>,
+  clock: &dyn Clock,
+  rng: &mut R
+) -> Result<Vec<(String, Card, Outcome)>> {
+  let mut shown_again = HashSet::new();
+  let mut first = true;
+
+  'cards: loop {
+    if let Some((dirname, kind)) = checkpoint {
+      save_checkpoint(dirname, kind, &cards, &result)?;
+    }
+
+    let now = clock.now();
+    let (deck, mut card) = match cards.iter().position(|(_, card)| !card.is_learning() || card.is_due_at(now)) {
+      Some(index) => cards.remove(index),
+      None => break
+    };
+
+    let proficiency = card.proficiency(config.ladder(&deck));
+
+    loop {
+      if first {
+        first = false;
+      } else {
+        term.println("");
+      }
+
+      term.println(&format!(
+        "{} - {}",
+        format!(
+          "{}/{}",
+          result.len() + 1,
+          result.len() + cards.len() + 1
+        ).green(),
+        proficiency.colorize(proficiency.to_string())
+      ));
+      term.println(&format!("List: {}", deck));
+      term.println("");
+      term.println(&format!("  {}", card.front));
+
+      if term.read_line("")?.is_none() {
+        break 'cards;
+      }
+
+      term.println(&format!("{} {}", "Back:".bright_white(), card.back));
+      term.println("");
+      term.println(&format!("{} {}", "Notes:".bright_white(), card.notes));
+      term.println("");
+
+      let choice = term.prompt_multiple_choice(
+        &format!(
+          "show {}gain, {}dit, {}pen in editor, {}ext card, {}uspend, {}ury, {}ndo - {}inish",
+          "a".bold().cyan().underline(),
+          "e".bold().cyan().underline(),
+          "o".bold().cyan().underline(),
+          "n".bold().cyan().underline(),
+          "s".bold().cyan().underline(),
+          "b".bold().cyan().underline(),
+          "u".bold().cyan().underline(),
+          "f".bold().cyan().underline()
+        ),
+        &['a', 'e', 'o', 'n', 's', 'b', 'u', 'f']
+      )?;
+
+      match choice {
+        Some('a') => {
+          // Show it again later, but not right away unless it is the last.
+          let index = if cards.is_empty() { 0 } else { rng.gen_range(1, cards.len() + 1) };
+
+          shown_again.insert((deck.clone(), card.line_number));
+          cards.insert(index, (deck, card));
+
+          break;
+        },
+        Some('n') => {
+          let key = (deck.clone(), card.line_number);
+
+          let outcome = if shown_again.contains(&key) {
+            Outcome::Forgotten
+          } else {
+            Outcome::Remembered
+          };
+
+          let mut next = card.clone();
+          next.apply(outcome, config.ladder(&deck), clock.now());
+
+          if next.is_learning() {
+            shown_again.remove(&key);
+            cards.push((deck.clone(), next));
+          }
+
+          result.push((deck, card, outcome));
+          break;
+        },
+        Some('s') => {
+          result.push((deck, card, Outcome::Suspended));
+          break;
+        },
+        Some('b') => {
+          result.push((deck, card, Outcome::Buried));
+          break;
+        },
+        Some('u') => {
+          if let Some((last_deck, last_card, _)) = result.pop() {
+            let is_last = |(other_deck, other): &(String, Card)| {
+              *other_deck == last_deck && other.line_number == last_card.line_number
+            };
+            let current = (deck, card);
+
+            // Drop the card queued again for learning, it gets the undone
+            // answer again.
+            cards.retain(|entry| !is_last(entry));
+
+            if !is_last(&current) {
+              cards.insert(0, current);
+            }
+
+            cards.insert(0, (last_deck, last_card));
+
+            break;
+          }
+
+          term.println("");
+          term.print_error_strip(&"Nothing to undo.");
+        },
+        Some('f') | None => break 'cards,
+        Some('e') => {
+          let back = term.prompt_multiline("Back", &card.back)?;
+          let notes = term.prompt_multiline("Notes", &card.notes)?;
+
+          card.back = back;
+          card.notes = notes;
+        },
+        Some('o') => {
+          let mut edited = card.clone();
+          let result = match config.editor() {
+            Some(command) => editor::edit_card(&command, &mut edited),
+            None => Err(RecallError::other(
+              "No editor set, see `editor` in the config or $EDITOR."
+            ))
+          }
+          .and_then(|_| check_front(store, &deck, &card, &edited.front));
+
+          match result {
+            Ok(()) => card = edited,
+            Err(err) => {
+              term.println("");
+              term.print_error_strip(&err);
+            }
+          }
+        },
+        _ => unreachable!()
+      }
+    }
+  }
+
+  Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::Cursor;
+  use chrono::TimeZone;
+  use rand::{rngs::StdRng, SeedableRng};
+  use crate::clock::FixedClock;
+  use crate::config::{Layer, Origin};
+  use crate::memory::MemoryStore;
+  use crate::terminal::ScriptedTerminal;
+  use super::*;
+
+  fn temp_dir(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("recall-test-{}-{}", std::process::id(), name))
+  }
+
+  fn card(front: &str, line_number: usize) -> Card {
+    let mut card = Card::new(front.to_string(), "b".to_string(), String::new());
+
+    card.line_number = Some(line_number);
+    card
+  }
+
+  #[test]
+  fn undo_keeps_cards_until_cleared() {
+    let dirname = temp_dir("undo");
+    let mut card = card("a", 3);
+
+    card.level = 2;
+    card.total_count = 4;
+
+    assert!(load_undo(&dirname).unwrap().is_none());
+
+    save_undo(&dirname, &[("words".to_string(), card)]).unwrap();
+    let undo = load_undo(&dirname).unwrap().unwrap();
+
+    assert_eq!(undo.len(), 1);
+    assert_eq!(undo[0].0, "words");
+    assert_eq!((undo[0].1.line_number, undo[0].1.level, undo[0].1.total_count), (Some(3), 2, 4));
+    assert_eq!(undo[0].1.front, "a");
+
+    clear_undo(&dirname).unwrap();
+    assert!(load_undo(&dirname).unwrap().is_none());
+
+    std::fs::remove_dir_all(&dirname).unwrap();
+  }
+
+  #[test]
+  fn checkpoint_keeps_queue_and_answers() {
+    let dirname = temp_dir("checkpoint");
+    let queue = vec![("words".to_string(), card("a", 0)), ("./verbs.jsonl".to_string(), card("b", 1))];
+    let results = vec![("words".to_string(), card("c", 2), Outcome::Forgotten)];
+
+    assert!(load_checkpoint(&dirname, "review").unwrap().is_none());
+
+    save_checkpoint(&dirname, "review", &queue, &results).unwrap();
+    assert!(load_checkpoint(&dirname, "learn").unwrap().is_none());
+    let checkpoint = load_checkpoint(&dirname, "review").unwrap().unwrap();
+
+    let fronts = checkpoint.queue.iter()
+      .map(|(deck, card)| (deck.as_str(), card.front.as_str(), card.line_number))
+      .collect::<Vec<_>>();
+    assert_eq!(fronts, vec![("words", "a", Some(0)), ("verbs", "b", Some(1))]);
+    assert_eq!(checkpoint.results.len(), 1);
+    assert_eq!(checkpoint.results[0].1.front, "c");
+    assert_eq!(checkpoint.results[0].2, Outcome::Forgotten);
+
+    clear_checkpoint(&dirname, "review").unwrap();
+    assert!(load_checkpoint(&dirname, "review").unwrap().is_none());
+
+    std::fs::remove_dir_all(&dirname).unwrap();
+  }
+
+  #[test]
+  fn commit_updates_cards_and_saves_undo() {
+    let dirname = temp_dir("commit");
+    let store = MemoryStore::new();
+
+    store.create_deck("words").unwrap();
+    store.save_cards("words", vec![card("a", 0), card("b", 0)]).unwrap();
+
+    let answered = store.cards("words").unwrap().remove(1);
+
+    save_checkpoint(&dirname, "learn", &[], &[]).unwrap();
+    commit(&store, &Config::default(), &dirname, "learn", vec![("words".to_string(), answered, Outcome::Remembered)], Utc::now()).unwrap();
+
+    let levels = store.cards("words").unwrap().iter().map(|card| card.level).collect::<Vec<_>>();
+    let undo = load_undo(&dirname).unwrap().unwrap();
+    let checkpoint = load_checkpoint(&dirname, "learn").unwrap();
+
+    std::fs::remove_dir_all(&dirname).unwrap();
+    assert_eq!(levels, vec![0, 1]);
+    assert_eq!(undo.len(), 1);
+    assert_eq!((undo[0].1.front.as_str(), undo[0].1.level), ("b", 0));
+    assert!(checkpoint.is_none());
+  }
+
+  /// Runs a session over cards with the given fronts and input lines and
+  /// returns the answers and the output.
+  fn run(name: &str, fronts: &[&str], input: &str) -> (Vec<(String, Card, Outcome)>, String) {
+    run_with(name, &Config::default(), fronts, input)
+  }
+
+  fn run_with(name: &str, config: &Config, fronts: &[&str], input: &str) -> (Vec<(String, Card, Outcome)>, String) {
+    let dirname = temp_dir(name);
+    let store = MemoryStore::new();
+
+    store.create_deck("deck").unwrap();
+    store.save_cards("deck", fronts.iter().map(|front| card(front, 0)).collect()).unwrap();
+
+    let cards = store.cards("deck").unwrap().into_iter()
+      .map(|card| ("deck".to_string(), card))
+      .collect();
+    let mut term = ScriptedTerminal::new(Cursor::new(input.as_bytes().to_vec()), Vec::new());
+    let clock = FixedClock::new(Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap());
+    let mut rng = StdRng::seed_from_u64(0);
+    let result = loop_cards(&mut term, &store, config, Some((&dirname, "review")), cards, Vec::new(), &clock, &mut rng);
+
+    std::fs::remove_dir_all(&dirname).ok();
+    (result.unwrap(), String::from_utf8(term.into_output()).unwrap())
+  }
+
+  fn outcomes(result: &[(String, Card, Outcome)]) -> Vec<(&str, Outcome)> {
+    result.iter().map(|(_, card, outcome)| (card.front.as_str(), *outcome)).collect()
+  }
+
+  #[test]
+  fn answers_cards_in_order() {
+    let (result, _) = run("loop-order", &["a", "b", "c"], "\nn\n\ns\n\nb\n");
+
+    assert_eq!(
+      outcomes(&result),
+      vec![("a", Outcome::Remembered), ("b", Outcome::Suspended), ("c", Outcome::Buried)]
+    );
+  }
+
+  #[test]
+  fn cards_shown_again_are_forgotten() {
+    let (result, _) = run("loop-again", &["a"], "\na\n\nn\n");
+
+    assert_eq!(outcomes(&result), vec![("a", Outcome::Forgotten)]);
+  }
+
+  #[test]
+  fn end_of_input_keeps_answers() {
+    let (result, _) = run("loop-end", &["a", "b"], "\nn\n");
+
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].2, Outcome::Remembered);
+  }
+
+  #[test]
+  fn undo_shows_last_card_again() {
+    let (result, output) = run("loop-undo", &["a", "b"], "\nu\n\nn\n\nu\n\ns\n\nn\n");
+    let outcomes = outcomes(&result).into_iter().map(|(_, outcome)| outcome).collect::<Vec<_>>();
+
+    assert!(output.contains("Nothing to undo."));
+    assert_eq!(outcomes, vec![Outcome::Suspended, Outcome::Remembered]);
+  }
+
+  #[test]
+  fn editing_changes_back_and_notes() {
+    let (result, _) = run("loop-edit", &["a"], "\ne\nx\n\n\nn\n");
+
+    assert_eq!(outcomes(&result), vec![("a", Outcome::Remembered)]);
+    assert_eq!((result[0].1.back.as_str(), result[0].1.notes.as_str()), ("x", ""));
+  }
+
+  #[test]
+  fn orders_cards() {
+    let now = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+    let config = Config::default();
+    let deck_card = |front: &str, level: i8, overdue_hours: i64| {
+      let mut card = card(front, 0);
+
+      card.level = level;
+      card.due_time = Some(now - Duration::hours(overdue_hours));
+      ("deck".to_string(), card)
+    };
+    let fronts = |order: Order| {
+      let mut cards = vec![deck_card("a", 7, 48), deck_card("b", 2, 24), deck_card("c", 1, 1)];
+
+      order_cards(&mut cards, order, &config, now, &mut StdRng::seed_from_u64(0));
+      cards.iter().map(|(_, card)| card.front.clone()).collect::<Vec<_>>()
+    };
+
+    assert_eq!(fronts(Order::Overdue), vec!["a", "b", "c"]);
+    assert_eq!(fronts(Order::Level), vec!["c", "b", "a"]);
+    assert_eq!(fronts(Order::Relative), vec!["b", "c", "a"]);
+    assert_eq!(Order::from_name("critical"), Some(Order::Critical));
+    assert_eq!(Order::from_name("alphabetical"), None);
+  }
+
+  #[test]
+  fn mixes_new_cards_into_reviews() {
+    let deck_cards = |fronts: &[&str]| fronts.iter().map(|front| ("deck".to_string(), card(front, 0))).collect::<Vec<_>>();
+    let fronts = |cards: Vec<(String, Card)>| cards.into_iter().map(|(_, card)| card.front).collect::<Vec<_>>();
+
+    assert_eq!(
+      fronts(mix_cards(deck_cards(&["a", "b", "c", "d", "e"]), deck_cards(&["x", "y"]), 2)),
+      vec!["a", "b", "x", "c", "d", "y", "e"]
+    );
+    assert_eq!(
+      fronts(mix_cards(deck_cards(&["a", "b", "c"]), deck_cards(&["x"]), 1)),
+      vec!["a", "x", "b", "c"]
+    );
+    assert_eq!(
+      fronts(mix_cards(deck_cards(&["a"]), deck_cards(&["x", "y"]), 4)),
+      vec!["a", "x", "y"]
+    );
+    assert_eq!(fronts(mix_cards(Vec::new(), deck_cards(&["x"]), 4)), vec!["x"]);
+  }
+
+  fn learning_config() -> Config {
+    Config::from_layers(vec![Layer::parse("[scheduler]\nlearning_steps = [\"10m\"]", Origin::Default).unwrap()]).unwrap()
+  }
+
+  #[test]
+  fn learning_cards_end_session_until_due() {
+    let (result, _) = run_with("loop-learning", &learning_config(), &["a"], "\nn\n\nn\n");
+
+    assert_eq!(outcomes(&result), vec![("a", Outcome::Remembered)]);
+  }
+
+  #[test]
+  fn commit_applies_last_answer_of_learning_cards() {
+    let dirname = temp_dir("commit-learning");
+    let now = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+    let store = MemoryStore::new();
+
+    store.create_deck("words").unwrap();
+    store.save_cards("words", vec![card("a", 0)]).unwrap();
+
+    let new = store.cards("words").unwrap().remove(0);
+    let mut learning = new.clone();
+
+    learning.learning_step = Some(0);
+
+    let results = vec![
+      ("words".to_string(), new, Outcome::Remembered),
+      ("words".to_string(), learning, Outcome::Remembered)
+    ];
+
+    commit(&store, &learning_config(), &dirname, "learn", results, now).unwrap();
+
+    let card = store.cards("words").unwrap().remove(0);
+    let counts = load_daily_counts(&dirname, now).unwrap();
+
+    std::fs::remove_dir_all(&dirname).unwrap();
+    assert_eq!((card.level, card.learning_step), (1, None));
+    assert_eq!((counts.learned, counts.reviewed), (1, 0));
+  }
+
+  #[test]
+  fn corrupt_checkpoint_is_an_error() {
+    let dirname = temp_dir("checkpoint-corrupt");
+
+    save_checkpoint(&dirname, "review", &[], &[]).unwrap();
+    std::fs::write(checkpoint_path(&dirname, "review"), "{\"queue\": [").unwrap();
+
+    let checkpoint = load_checkpoint(&dirname, "review");
+
+    std::fs::remove_dir_all(&dirname).unwrap();
+    assert!(matches!(checkpoint, Err(RecallError::Parse { .. })));
+  }
+
+  #[test]
+  fn resume_finds_moved_cards_and_drops_removed_ones() {
+    let dirname = temp_dir("resume");
+    let store = MemoryStore::new();
+
+    store.create_deck("words").unwrap();
+    store.save_cards("words", vec![card("x", 0), card("a", 0), card("b", 0)]).unwrap();
+    save_checkpoint(
+      &dirname,
+      "review",
+      &[("words".to_string(), card("a", 1)), ("words".to_string(), card("gone", 2))],
+      &[("words".to_string(), card("b", 3), Outcome::Remembered)]
+    ).unwrap();
+
+    let mut term = ScriptedTerminal::new(Cursor::new(b"r\n".to_vec()), Vec::new());
+    let checkpoint = prompt_resume(&mut term, &store, &Config::default(), &dirname, "review", Utc::now());
+    let output = String::from_utf8(term.into_output()).unwrap();
+
+    std::fs::remove_dir_all(&dirname).unwrap();
+
+    let checkpoint = checkpoint.unwrap().unwrap();
+
+    assert!(output.contains("1 card(s) of the unfinished session were changed or removed"));
+    assert_eq!(
+      checkpoint.queue.iter().map(|(_, card)| (card.front.as_str(), card.line_number)).collect::<Vec<_>>(),
+      vec![("a", Some(2))]
+    );
+    assert_eq!(checkpoint.results[0].1.line_number, Some(3));
+  }
+
+  #[test]
+  fn dry_runs_leave_no_checkpoint() {
+    let dirname = temp_dir("dry-run");
+    let cards = vec![("deck".to_string(), card("a", 1))];
+    let mut term = ScriptedTerminal::new(Cursor::new(b"\nn\n".to_vec()), Vec::new());
+    let clock = FixedClock::new(Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap());
+    let result = loop_cards(&mut term, &MemoryStore::new(), &Config::default(), None, cards, Vec::new(), &clock, &mut StdRng::seed_from_u64(0));
+
+    assert_eq!(outcomes(&result.unwrap()), vec![("a", Outcome::Remembered)]);
+    assert!(!dirname.exists());
+  }
+
+  /// Answers like a scripted terminal, but is interrupted at the end of the
+  /// input.
+  struct InterruptedTerminal(ScriptedTerminal<Cursor<Vec<u8>>, Vec<u8>>);
+
+  impl Terminal for InterruptedTerminal {
+    fn print(&mut self, text: &str) {
+      self.0.print(text);
+    }
+
+    fn read_line(&mut self, prefix: &str) -> Result<Option<String>> {
+      self.0.read_line(prefix)?.map(Some).ok_or(RecallError::Interrupted)
+    }
+
+    fn read_multiline(&mut self, prefix: &str, initial: &str) -> Result<Option<String>> {
+      self.0.read_multiline(prefix, initial)?.map(Some).ok_or(RecallError::Interrupted)
+    }
+  }
+
+  #[test]
+  fn interrupt_keeps_checkpoint() {
+    let dirname = temp_dir("interrupt");
+    let cards = vec![("deck".to_string(), card("a", 1)), ("deck".to_string(), card("b", 2))];
+    let mut term = InterruptedTerminal(ScriptedTerminal::new(Cursor::new(b"\nn\n".to_vec()), Vec::new()));
+    let clock = FixedClock::new(Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap());
+    let mut rng = StdRng::seed_from_u64(0);
+    let result = loop_cards(&mut term, &MemoryStore::new(), &Config::default(), Some((&dirname, "review")), cards, Vec::new(), &clock, &mut rng);
+    let checkpoint = load_checkpoint(&dirname, "review");
+
+    std::fs::remove_dir_all(&dirname).ok();
+    assert!(matches!(result, Err(RecallError::Interrupted)));
+
+    let checkpoint = checkpoint.unwrap().unwrap();
+
+    assert_eq!(checkpoint.queue.iter().map(|(_, card)| card.front.as_str()).collect::<Vec<_>>(), vec!["b"]);
+    assert_eq!(checkpoint.results.len(), 1);
+    assert_eq!(checkpoint.results[0].2, Outcome::Remembered);
+  }
+
+  fn editor_config(command: &str) -> Config {
+    let text = format!("editor = {}", toml::Value::String(command.to_string()));
+
+    Config::from_layers(vec![Layer::parse(&text, Origin::Default).unwrap()]).unwrap()
+  }
+
+  #[test]
+  fn editing_rejects_duplicate_front() {
+    let config = editor_config("sed -i -e 's/^Front: a$/Front: b/'");
+    let (result, output) = run_with("loop-edit-duplicate", &config, &["a", "b"], "\no\n\nn\n");
+
+    assert!(output.contains("Card 'b' already exists in list 'deck'."));
+    assert_eq!(outcomes(&result), vec![("a", Outcome::Remembered)]);
+  }
+
+  #[test]
+  fn editing_changes_card() {
+    let config = editor_config("sed -i -e 's/^Front: a$/Front: c/' -e 's/^Notes:.*$/Notes: x\\n#tag/'");
+    let (result, _) = run_with("loop-edit-editor", &config, &["a", "b"], "\no\n\nn\n");
+
+    assert_eq!(outcomes(&result), vec![("c", Outcome::Remembered)]);
+    assert_eq!(result[0].1.notes, "x\n#tag");
+  }
+}
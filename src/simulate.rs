@@ -0,0 +1,335 @@
+//! Simulated review sessions, to compare level ladders on a deck before
+//! changing the settings.
+
+use std::fmt::Display;
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use crate::card::Card;
+use crate::ladder::{format_interval, Ladder};
+
+/// How likely a card is remembered when it's reviewed.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Model {
+  /// Every review is remembered with the same probability.
+  Constant(f64),
+  /// Memories fade exponentially. The half-life starts at `half_life` for new
+  /// cards, grows by the factor `growth` with every remembered review and
+  /// starts over when a card is forgotten.
+  Decay {
+    half_life: Duration,
+    growth: f64
+  }
+}
+
+impl Default for Model {
+  fn default() -> Model {
+    Model::Decay {
+      half_life: Duration::days(2),
+      growth: 2.5
+    }
+  }
+}
+
+impl Model {
+  /// Parses `constant:<probability>` or `decay:<half-life>:<growth>`.
+  pub fn parse(text: &str) -> Option<Model> {
+    let parts = text.split(':').collect::<Vec<_>>();
+
+    match parts.as_slice() {
+      ["constant", probability] => {
+        probability.parse::<f64>().ok()
+        .filter(|x| (0.0..=1.0).contains(x))
+        .map(Model::Constant)
+      },
+      ["decay", half_life, growth] => {
+        let half_life = humantime::parse_duration(half_life).ok()
+          .and_then(|x| Duration::from_std(x).ok())
+          .filter(|x| *x > Duration::zero())?;
+        let growth = growth.parse::<f64>().ok()
+          .filter(|x| *x >= 1.0)?;
+
+        Some(Model::Decay {half_life, growth})
+      },
+      _ => None
+    }
+  }
+
+  fn initial_half_life(&self) -> f64 {
+    match self {
+      Model::Constant(_) => 0.0,
+      Model::Decay { half_life, .. } => days(*half_life)
+    }
+  }
+
+  fn probability(&self, elapsed: f64, half_life: f64) -> f64 {
+    match self {
+      Model::Constant(probability) => *probability,
+      Model::Decay { .. } => 0.5f64.powf(elapsed / half_life)
+    }
+  }
+
+  fn next_half_life(&self, half_life: f64, remembered: bool) -> f64 {
+    match self {
+      Model::Constant(_) => half_life,
+      Model::Decay { growth, .. } if remembered => half_life * growth,
+      Model::Decay { .. } => self.initial_half_life()
+    }
+  }
+}
+
+impl Display for Model {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Model::Constant(probability) => write!(f, "constant:{}", probability),
+      Model::Decay { half_life, growth } => write!(f, "decay:{}:{}", format_interval(*half_life), growth)
+    }
+  }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Settings {
+  pub days: u32,
+  /// The number of inactive cards learned at the start of every day.
+  pub new_per_day: usize,
+  /// The number of evenly spaced review sessions per day.
+  pub sessions_per_day: u32
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+  pub reviews_per_day: Vec<usize>,
+  pub learned: usize,
+  pub reviewed: usize,
+  pub remembered: usize,
+  /// The number of cards at the top level at the end of the simulation.
+  pub burned: usize,
+  /// The time it took every card learned during the simulation to reach the
+  /// top level, if it did.
+  pub days_to_burned: Vec<f64>
+}
+
+impl Report {
+  pub fn average_reviews_per_day(&self) -> f64 {
+    if self.reviews_per_day.is_empty() {
+      0.0
+    } else {
+      self.reviewed as f64 / self.reviews_per_day.len() as f64
+    }
+  }
+
+  pub fn peak_reviews_per_day(&self) -> usize {
+    self.reviews_per_day.iter().copied().max().unwrap_or(0)
+  }
+
+  pub fn retention(&self) -> Option<f64> {
+    if self.reviewed == 0 {
+      None
+    } else {
+      Some(self.remembered as f64 / self.reviewed as f64)
+    }
+  }
+
+  pub fn average_days_to_burned(&self) -> Option<f64> {
+    if self.days_to_burned.is_empty() {
+      None
+    } else {
+      Some(self.days_to_burned.iter().sum::<f64>() / self.days_to_burned.len() as f64)
+    }
+  }
+}
+
+struct SimulatedCard {
+  card: Card,
+  last_review: DateTime<Utc>,
+  half_life: f64,
+  learned_at: Option<DateTime<Utc>>
+}
+
+fn days(duration: Duration) -> f64 {
+  duration.num_seconds() as f64 / 86400.0
+}
+
+/// Simulates reviewing the given cards with the given ladder, starting at
+/// `start`. Suspended cards are left out. The time of the last review of
+/// learned cards is estimated from their due time and level.
+pub fn simulate<R: Rng>(
+  cards: &[Card],
+  ladder: &Ladder,
+  model: &Model,
+  settings: &Settings,
+  start: DateTime<Utc>,
+  rng: &mut R
+) -> Report {
+  let mut report = Report::default();
+  let mut cards = cards.iter()
+    .filter(|card| !card.suspended)
+    .map(|card| {
+      let mut card = card.clone();
+      card.level = card.level.min(ladder.max_level());
+      card.buried_until = None;
+
+      let last_review = card.due_time
+        .map(|x| x - ladder.interval(card.level))
+        .unwrap_or(start);
+      let half_life = model.initial_half_life()
+        * match model {
+          Model::Decay { growth, .. } => growth.powi((card.level as i32 - 1).max(0)),
+          Model::Constant(_) => 1.0
+        };
+
+      SimulatedCard {
+        card,
+        last_review,
+        half_life,
+        learned_at: None
+      }
+    })
+    .collect::<Vec<_>>();
+
+  let session_interval = Duration::seconds(86400 / settings.sessions_per_day.max(1) as i64);
+
+  for day in 0..settings.days {
+    let mut reviews = 0;
+
+    for session in 0..settings.sessions_per_day.max(1) {
+      let now = start + Duration::days(day as i64) + session_interval * session as i32;
+
+      for entry in cards.iter_mut() {
+        if entry.card.is_new(ladder) || !entry.card.is_due_at(now) {
+          continue;
+        }
+
+        let elapsed = days(now - entry.last_review);
+        let remembered = rng.gen::<f64>() < model.probability(elapsed, entry.half_life);
+        let was_burned = entry.card.level >= ladder.max_level();
+
+        entry.card.review(ladder, remembered, now);
+        entry.half_life = model.next_half_life(entry.half_life, remembered);
+        entry.last_review = now;
+
+        reviews += 1;
+        report.reviewed += 1;
+
+        if remembered {
+          report.remembered += 1;
+        }
+
+        if !was_burned && entry.card.level >= ladder.max_level() {
+          if let Some(learned_at) = entry.learned_at {
+            report.days_to_burned.push(days(now - learned_at));
+          }
+        }
+      }
+
+      if session == 0 {
+        let new_cards = cards.iter_mut()
+          .filter(|entry| entry.card.is_new(ladder))
+          .take(settings.new_per_day);
+
+        for entry in new_cards {
+          entry.card.review(ladder, true, now);
+          entry.half_life = model.initial_half_life();
+          entry.last_review = now;
+          entry.learned_at = Some(now);
+
+          report.learned += 1;
+        }
+      }
+    }
+
+    report.reviews_per_day.push(reviews);
+  }
+
+  report.burned = cards.iter()
+    .filter(|entry| entry.card.level >= ladder.max_level())
+    .count();
+
+  report
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::TimeZone;
+  use rand::{rngs::StdRng, SeedableRng};
+  use super::*;
+
+  fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+  }
+
+  fn cards(count: usize) -> Vec<Card> {
+    (0..count)
+    .map(|i| Card::new(i.to_string(), String::new(), String::new()))
+    .collect()
+  }
+
+  fn run(cards: &[Card], model: Model, seed: u64) -> Report {
+    let settings = Settings {
+      days: 10,
+      new_per_day: 1,
+      sessions_per_day: 1
+    };
+
+    simulate(
+      cards,
+      &Ladder::parse("1d,2d", &Ladder::default()).unwrap(),
+      &model,
+      &settings,
+      start(),
+      &mut StdRng::seed_from_u64(seed)
+    )
+  }
+
+  #[test]
+  fn parses_models() {
+    assert_eq!(Model::parse("constant:0.9"), Some(Model::Constant(0.9)));
+    assert_eq!(
+      Model::parse("decay:3d:2"),
+      Some(Model::Decay {half_life: Duration::days(3), growth: 2.0})
+    );
+    assert_eq!(Model::parse("constant:1.5"), None);
+    assert_eq!(Model::parse("decay:0s:2"), None);
+    assert_eq!(Model::parse("decay:1d:0.5"), None);
+    assert_eq!(Model::parse("random"), None);
+  }
+
+  #[test]
+  fn cards_always_remembered_reach_top_level() {
+    let report = run(&cards(3), Model::Constant(1.0), 0);
+
+    assert_eq!(report.reviews_per_day.len(), 10);
+    assert_eq!(report.learned, 3);
+    assert_eq!(report.burned, 3);
+    assert_eq!(report.retention(), Some(1.0));
+    assert_eq!(report.days_to_burned, vec![1.0, 1.0, 1.0]);
+    assert_eq!(report.average_days_to_burned(), Some(1.0));
+  }
+
+  #[test]
+  fn cards_never_remembered_stay_at_bottom() {
+    let report = run(&cards(3), Model::Constant(0.0), 0);
+
+    assert_eq!(report.learned, 3);
+    assert_eq!(report.burned, 0);
+    assert_eq!(report.retention(), Some(0.0));
+    assert_eq!(report.average_days_to_burned(), None);
+  }
+
+  #[test]
+  fn suspended_cards_are_left_out() {
+    let mut cards = cards(3);
+
+    cards[0].suspended = true;
+
+    assert_eq!(run(&cards, Model::default(), 0).learned, 2);
+  }
+
+  #[test]
+  fn same_seed_gives_same_report() {
+    let first = run(&cards(5), Model::default(), 7);
+    let second = run(&cards(5), Model::default(), 7);
+
+    assert_eq!(first.reviews_per_day, second.reviews_per_day);
+    assert_eq!(first.remembered, second.remembered);
+  }
+}
@@ -0,0 +1,289 @@
+use std::fs::remove_file;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, TimeZone, Utc};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Row};
+use serde_json::{Map, Value};
+use crate::card::Card;
+use crate::storage::Storage;
+use crate::error::{RecallError, Result, WithPath};
+
+const SCHEMA: &str = "
+  CREATE TABLE IF NOT EXISTS cards (
+    id INTEGER PRIMARY KEY,
+    front TEXT NOT NULL,
+    back TEXT NOT NULL,
+    notes TEXT NOT NULL,
+    level INTEGER NOT NULL DEFAULT 0,
+    due INTEGER,
+    correct INTEGER NOT NULL DEFAULT 0,
+    total INTEGER NOT NULL DEFAULT 0,
+    suspended INTEGER NOT NULL DEFAULT 0,
+    buried_until INTEGER,
+    extra TEXT NOT NULL DEFAULT '{}'
+  );
+  CREATE INDEX IF NOT EXISTS cards_due ON cards (suspended, due);
+";
+
+const COLUMNS: &str = "id, front, back, notes, level, due, correct, total, suspended, buried_until, extra";
+
+/// A list stored as an SQLite database with one row per card, for large lists
+/// that benefit from indexed due time queries and transactional updates.
+#[derive(Debug)]
+pub struct SqliteFile {
+  path: PathBuf
+}
+
+fn from_timestamp(timestamp: Option<i64>) -> Option<DateTime<Utc>> {
+  timestamp.and_then(|x| Utc.timestamp_opt(x, 0).single())
+}
+
+fn card_from_row(row: &Row) -> rusqlite::Result<Card> {
+  let mut card = Card::new(row.get(1)?, row.get(2)?, row.get(3)?);
+
+  card.line_number = Some(row.get::<_, i64>(0)? as usize);
+  card.level = row.get(4)?;
+  card.due_time = from_timestamp(row.get(5)?);
+  card.correct_count = row.get(6)?;
+  card.total_count = row.get(7)?;
+  card.suspended = row.get(8)?;
+  card.buried_until = from_timestamp(row.get(9)?);
+  card.extra = serde_json::from_str::<Map<String, Value>>(&row.get::<_, String>(10)?)
+    .unwrap_or_default();
+  card.learning_step = card.extra.remove("step").and_then(|x| x.as_u64()).map(|x| x as usize);
+
+  Ok(card)
+}
+
+impl SqliteFile {
+  pub fn new<P: AsRef<Path>>(path: P) -> SqliteFile {
+    SqliteFile {
+      path: path.as_ref().to_path_buf()
+    }
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  fn error(&self, err: rusqlite::Error) -> RecallError {
+    RecallError::Database {
+      path: self.path.clone(),
+      source: Box::new(err)
+    }
+  }
+
+  fn open(&self, create: bool) -> Result<Connection> {
+    let mut flags = OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+
+    if create {
+      flags |= OpenFlags::SQLITE_OPEN_CREATE;
+    }
+
+    let connection = Connection::open_with_flags(self.path(), flags)
+      .map_err(|err| self.error(err))?;
+
+    connection.execute_batch(SCHEMA).map_err(|err| self.error(err))?;
+    Ok(connection)
+  }
+
+  fn insert_card(&self, connection: &Connection, card: &Card) -> Result<()> {
+    // The learning step is kept with the extra fields, so existing databases
+    // need no new column.
+    let mut extra = card.extra.clone();
+
+    if let Some(step) = card.learning_step {
+      extra.insert("step".to_string(), Value::from(step));
+    }
+
+    connection.execute(
+      &format!("INSERT INTO cards ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)", COLUMNS),
+      params![
+        card.line_number.map(|x| x as i64),
+        card.front,
+        card.back,
+        card.notes,
+        card.level,
+        card.due_time.map(|x| x.timestamp()),
+        card.correct_count,
+        card.total_count,
+        card.suspended,
+        card.buried_until.map(|x| x.timestamp()),
+        Value::Object(extra).to_string()
+      ]
+    )
+    .map_err(|err| self.error(err))?;
+
+    Ok(())
+  }
+}
+
+impl Storage for SqliteFile {
+  fn cards(&self) -> Result<Vec<Card>> {
+    let connection = self.open(false)?;
+    let mut statement = connection.prepare(&format!("SELECT {} FROM cards ORDER BY id", COLUMNS))
+      .map_err(|err| self.error(err))?;
+    let cards = statement.query_map([], card_from_row)
+      .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+      .map_err(|err| self.error(err))?;
+
+    Ok(cards)
+  }
+
+  fn save_cards(&self, cards: Vec<Card>) -> Result<()> {
+    let mut connection = self.open(true)?;
+    let transaction = connection.transaction().map_err(|err| self.error(err))?;
+
+    transaction.execute("DELETE FROM cards", []).map_err(|err| self.error(err))?;
+
+    for card in cards.iter() {
+      self.insert_card(&transaction, card)?;
+    }
+
+    transaction.commit().map_err(|err| self.error(err))
+  }
+
+  fn update_cards(&self, cards: Vec<Card>) -> Result<Vec<Card>> {
+    let mut connection = self.open(false)?;
+    let transaction = connection.transaction().map_err(|err| self.error(err))?;
+    let mut previous = Vec::new();
+
+    for card in cards {
+      let id = match card.line_number {
+        Some(id) => id as i64,
+        None => continue
+      };
+
+      let previous_card = transaction.query_row(
+        &format!("SELECT {} FROM cards WHERE id = ?1", COLUMNS),
+        params![id],
+        card_from_row
+      )
+      .optional()
+      .map_err(|err| self.error(err))?;
+
+      if let Some(previous_card) = previous_card {
+        transaction.execute("DELETE FROM cards WHERE id = ?1", params![id])
+        .map_err(|err| self.error(err))?;
+        self.insert_card(&transaction, &card)?;
+
+        previous.push(previous_card);
+      }
+    }
+
+    transaction.commit().map_err(|err| self.error(err))?;
+    Ok(previous)
+  }
+
+  fn count_due_at(&self, date_time: DateTime<Utc>) -> Result<usize> {
+    let connection = self.open(false)?;
+
+    connection.query_row(
+      "SELECT COUNT(*) FROM cards
+      WHERE suspended = 0 AND due <= ?1 AND (buried_until IS NULL OR buried_until <= ?1)",
+      params![date_time.timestamp()],
+      |row| row.get::<_, i64>(0)
+    )
+    .map(|count| count as usize)
+    .map_err(|err| self.error(err))
+  }
+
+  fn delete(&self) -> Result<()> {
+    remove_file(self.path()).with_path(self.path())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::Duration;
+  use serde_json::json;
+  use crate::jsonl::JsonlFile;
+  use crate::record::card_to_value;
+  use super::*;
+
+  fn temp_path(name: &str, extension: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("recall-test-{}-{}.{}", std::process::id(), name, extension))
+  }
+
+  fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+  }
+
+  fn cards() -> Vec<Card> {
+    let mut learned = Card::new("hola".to_string(), "hello".to_string(), "#greeting".to_string());
+    let mut suspended = Card::new("adiós".to_string(), "goodbye".to_string(), String::new());
+
+    learned.level = 3;
+    learned.due_time = Some(now());
+    learned.correct_count = 4;
+    learned.total_count = 5;
+    learned.buried_until = Some(now() + Duration::days(1));
+    learned.extra.insert("source".to_string(), json!("book"));
+    suspended.level = 1;
+    suspended.due_time = Some(now() - Duration::hours(1));
+    suspended.suspended = true;
+
+    vec![learned, suspended, Card::new("gato".to_string(), "cat".to_string(), String::new())]
+  }
+
+  fn values(cards: &[Card]) -> Vec<Value> {
+    cards.iter().map(card_to_value).collect()
+  }
+
+  #[test]
+  fn cards_round_trip() {
+    let file = SqliteFile::new(temp_path("round-trip", "sqlite"));
+
+    file.save_cards(cards()).unwrap();
+
+    let saved = file.cards().unwrap();
+    let due_counts = (
+      file.count_due_at(now()).unwrap(),
+      file.count_due_at(now() + Duration::days(2)).unwrap()
+    );
+
+    file.delete().unwrap();
+    assert_eq!(values(&saved), values(&cards()));
+    assert!(saved.iter().all(|card| card.line_number.is_some()));
+    assert_eq!(due_counts, (0, 1));
+  }
+
+  #[test]
+  fn updates_cards_by_row_id() {
+    let file = SqliteFile::new(temp_path("update", "sqlite"));
+
+    file.save_cards(cards()).unwrap();
+
+    let mut saved = file.cards().unwrap();
+    let mut missing = Card::new("perro".to_string(), "dog".to_string(), String::new());
+    let mut learned = saved.pop().unwrap();
+
+    learned.level = 1;
+    learned.due_time = Some(now());
+    missing.line_number = Some(100);
+
+    let previous = file.update_cards(vec![learned, missing]).unwrap();
+    let updated = file.cards().unwrap();
+
+    file.delete().unwrap();
+    assert_eq!(previous.len(), 1);
+    assert_eq!((previous[0].front.as_str(), previous[0].level), ("gato", 0));
+    assert_eq!(updated.iter().map(|card| card.level).collect::<Vec<_>>(), vec![3, 1, 1]);
+  }
+
+  #[test]
+  fn converts_from_and_to_jsonl() {
+    let jsonl = JsonlFile::new(temp_path("convert", "jsonl"));
+    let sqlite = SqliteFile::new(temp_path("convert", "sqlite"));
+
+    jsonl.save_cards(cards()).unwrap();
+    sqlite.save_cards(jsonl.cards().unwrap()).unwrap();
+    jsonl.delete().unwrap();
+    jsonl.save_cards(sqlite.cards().unwrap()).unwrap();
+
+    let converted = jsonl.cards().unwrap();
+
+    jsonl.delete().unwrap();
+    sqlite.delete().unwrap();
+    assert_eq!(values(&converted), values(&cards()));
+  }
+}
@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use crate::card::Card;
+use crate::error::Result;
+
+/// The backend of a single list.
+///
+/// The line number of a card identifies it within its list. For JSONL files
+/// it's the line the card is stored on, for SQLite databases the row id.
+pub trait Storage {
+  /// Reads all cards of the list.
+  fn cards(&self) -> Result<Vec<Card>>;
+
+  /// Overwrites the list with the given cards.
+  fn save_cards(&self, cards: Vec<Card>) -> Result<()>;
+
+  /// Writes the given cards back to the list, matching them by line number,
+  /// and returns the replaced cards as they were before the update. Cards
+  /// that are not in the list anymore are ignored.
+  fn update_cards(&self, cards: Vec<Card>) -> Result<Vec<Card>> {
+    let mut stored = self.cards()?
+      .into_iter()
+      .filter_map(|card| card.line_number.map(|x| (x, card)))
+      .collect::<HashMap<_, _>>();
+    let mut previous = Vec::new();
+
+    for card in cards {
+      let entry = card.line_number.and_then(|x| stored.get_mut(&x));
+
+      if let Some(entry) = entry {
+        previous.push(std::mem::replace(entry, card));
+      }
+    }
+
+    let mut stored = stored.into_iter().collect::<Vec<_>>();
+    stored.sort_by_key(|(i, _)| *i);
+
+    self.save_cards(stored.into_iter().map(|(_, card)| card).collect())?;
+    Ok(previous)
+  }
+
+  /// Counts the cards that are due at the given time.
+  fn count_due_at(&self, date_time: DateTime<Utc>) -> Result<usize> {
+    Ok(self.cards()?.iter().filter(|card| card.is_due_at(date_time)).count())
+  }
+
+  /// Upgrades the list to the current storage format. Returns `false` if the
+  /// list is already up to date.
+  fn migrate(&self) -> Result<bool> {
+    Ok(false)
+  }
+
+  /// Removes the list with all of its cards.
+  fn delete(&self) -> Result<()>;
+}
@@ -0,0 +1,382 @@
+//! Collections of decks, independent of where and how they are stored.
+
+use std::collections::HashMap;
+use std::fs::rename;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use crate::card::Card;
+use crate::index::DueIndex;
+use crate::list::{Backend, List, find_list, get_lists};
+use crate::storage::Storage;
+use crate::error::{RecallError, Result, WithPath};
+
+/// A collection of named decks. Commands only access cards through this
+/// trait, so decks can be kept in a directory of lists, in memory or
+/// elsewhere.
+///
+/// Cards are identified by their deck name and line number.
+pub trait DeckStore {
+  /// Returns the names of all decks, sorted by name.
+  fn decks(&self) -> Result<Vec<String>>;
+
+  fn deck_exists(&self, deck: &str) -> bool {
+    self.decks()
+    .map(|decks| decks.iter().any(|x| x == deck))
+    .unwrap_or(false)
+  }
+
+  /// Reads all cards of the deck.
+  fn cards(&self, deck: &str) -> Result<Vec<Card>>;
+
+  /// Overwrites the deck with the given cards.
+  fn save_cards(&self, deck: &str, cards: Vec<Card>) -> Result<()>;
+
+  /// Writes the given cards back to the deck, matching them by line number,
+  /// and returns the replaced cards as they were before the update.
+  fn update_cards(&self, deck: &str, cards: Vec<Card>) -> Result<Vec<Card>>;
+
+  /// Counts the cards of the deck that are due at the given time.
+  fn count_due_at(&self, deck: &str, date_time: DateTime<Utc>) -> Result<usize> {
+    Ok(self.cards(deck)?.iter().filter(|card| card.is_due_at(date_time)).count())
+  }
+
+  /// Counts the due cards of each of the given decks, counting decks that
+  /// cannot be read as having no due cards.
+  fn due_counts(&self, decks: &[String], date_time: DateTime<Utc>) -> Vec<usize> {
+    decks.iter()
+    .map(|deck| self.count_due_at(deck, date_time).unwrap_or(0))
+    .collect()
+  }
+
+  /// Adds a new empty deck.
+  fn create_deck(&self, deck: &str) -> Result<()>;
+
+  /// Removes the deck with all of its cards.
+  fn delete_deck(&self, deck: &str) -> Result<()>;
+
+  /// Renames a deck, keeping its cards as they are.
+  fn rename_deck(&self, deck: &str, new_name: &str) -> Result<()> {
+    if self.deck_exists(new_name) {
+      return Err(RecallError::ListExists(new_name.to_string()));
+    }
+
+    let cards = self.cards(deck)?;
+
+    self.create_deck(new_name)?;
+    self.save_cards(new_name, cards)?;
+    self.delete_deck(deck)
+  }
+
+  /// Describes where the deck is stored, e.g. its path.
+  fn location(&self, _deck: &str) -> Option<String> {
+    None
+  }
+}
+
+/// Fails for names that cannot be the file name of a list in the directory,
+/// like names with path separators or hidden files.
+pub fn check_name(deck: &str) -> Result<()> {
+  if deck.is_empty() || deck.starts_with('.') || deck.contains(&['/', '\\'][..]) {
+    return Err(RecallError::InvalidArgument {
+      name: "name",
+      value: deck.to_string()
+    });
+  }
+
+  Ok(())
+}
+
+/// Decks stored as lists in a directory, one file per deck.
+#[derive(Debug)]
+pub struct DirStore {
+  dirname: PathBuf,
+  backend: Backend
+}
+
+impl DirStore {
+  pub fn new<P: AsRef<Path>>(dirname: P) -> DirStore {
+    DirStore {
+      dirname: dirname.as_ref().to_path_buf(),
+      backend: Backend::Jsonl
+    }
+  }
+
+  /// Sets the backend of newly created decks.
+  pub fn with_backend(mut self, backend: Backend) -> DirStore {
+    self.backend = backend;
+    self
+  }
+
+  pub fn dirname(&self) -> &Path {
+    &self.dirname
+  }
+
+  pub fn list(&self, deck: &str) -> Result<List> {
+    find_list(&self.dirname, deck)
+    .ok_or_else(|| RecallError::ListNotFound(deck.to_string()))
+  }
+}
+
+impl DeckStore for DirStore {
+  fn decks(&self) -> Result<Vec<String>> {
+    let mut decks = get_lists(&self.dirname)?
+      .map(|list| list.name().to_string())
+      .collect::<Vec<_>>();
+
+    decks.sort();
+    Ok(decks)
+  }
+
+  fn deck_exists(&self, deck: &str) -> bool {
+    find_list(&self.dirname, deck).is_some()
+  }
+
+  fn cards(&self, deck: &str) -> Result<Vec<Card>> {
+    self.list(deck)?.cards()
+  }
+
+  fn save_cards(&self, deck: &str, cards: Vec<Card>) -> Result<()> {
+    self.list(deck)?.save_cards(cards)
+  }
+
+  fn update_cards(&self, deck: &str, cards: Vec<Card>) -> Result<Vec<Card>> {
+    self.list(deck)?.update_cards(cards)
+  }
+
+  /// Counts due cards using the due index of the directory, so unchanged
+  /// lists don't have to be read.
+  fn count_due_at(&self, deck: &str, date_time: DateTime<Utc>) -> Result<usize> {
+    let list = self.list(deck)?;
+    let mut index = DueIndex::load(&self.dirname);
+    let count = index.count_due_at(&list, date_time)?;
+
+    // The index is only a cache, failing to save it is not fatal.
+    index.save(&self.dirname).ok();
+
+    Ok(count)
+  }
+
+  /// Reads the directory and the due index only once for all decks.
+  fn due_counts(&self, decks: &[String], date_time: DateTime<Utc>) -> Vec<usize> {
+    let lists = match get_lists(&self.dirname) {
+      Ok(lists) => lists
+        .map(|list| (list.name().to_string(), list))
+        .collect::<HashMap<_, _>>(),
+      Err(_) => return vec![0; decks.len()]
+    };
+    let mut index = DueIndex::load(&self.dirname);
+    let counts = decks.iter()
+      .map(|deck| {
+        lists.get(deck)
+        .and_then(|list| index.count_due_at(list, date_time).ok())
+        .unwrap_or(0)
+      })
+      .collect();
+
+    index.save(&self.dirname).ok();
+
+    counts
+  }
+
+  fn create_deck(&self, deck: &str) -> Result<()> {
+    check_name(deck)?;
+
+    if self.deck_exists(deck) {
+      return Err(RecallError::ListExists(deck.to_string()));
+    }
+
+    if !self.backend.is_supported() {
+      return Err(RecallError::other("SQLite support is not enabled."));
+    }
+
+    let path = self.dirname.join(format!("{}.{}", deck, self.backend.extension()));
+    let list = List::new(&path)
+      .ok_or_else(|| RecallError::other("List initialization failed."))?;
+
+    list.save_cards(vec![])
+  }
+
+  fn delete_deck(&self, deck: &str) -> Result<()> {
+    self.list(deck)?.delete()
+  }
+
+  /// Renames the file of the list, so it keeps its storage format. Existing
+  /// files are never overwritten, even if they are not a readable list.
+  fn rename_deck(&self, deck: &str, new_name: &str) -> Result<()> {
+    check_name(new_name)?;
+
+    let list = self.list(deck)?;
+    let path = self.dirname.join(format!("{}.{}", new_name, list.backend().extension()));
+
+    if self.deck_exists(new_name) || path.exists() {
+      return Err(RecallError::ListExists(new_name.to_string()));
+    }
+
+    rename(list.path(), &path).with_path(list.path())
+  }
+
+  fn location(&self, deck: &str) -> Option<String> {
+    find_list(&self.dirname, deck)
+    .map(|list| list.path().display().to_string())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs::{create_dir_all, remove_dir_all, write};
+  use chrono::{Duration, TimeZone};
+  use crate::memory::MemoryStore;
+  use super::*;
+
+  fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+  }
+
+  fn cards(due: &[Option<i64>]) -> Vec<Card> {
+    due.iter()
+    .enumerate()
+    .map(|(i, hours)| {
+      let mut card = Card::new(i.to_string(), String::new(), String::new());
+
+      card.level = hours.map(|_| 1).unwrap_or(0);
+      card.due_time = hours.map(|x| now() + Duration::hours(x));
+      card
+    })
+    .collect()
+  }
+
+  #[test]
+  fn memory_store_updates_decks() {
+    let store = MemoryStore::new();
+
+    store.create_deck("a").unwrap();
+    store.create_deck("b").unwrap();
+    store.save_cards("a", cards(&[Some(-1), None, Some(1)])).unwrap();
+
+    let mut card = store.cards("a").unwrap().remove(1);
+    card.level = 1;
+
+    let previous = store.update_cards("a", vec![card]).unwrap();
+
+    assert_eq!(previous[0].level, 0);
+    assert_eq!(store.cards("a").unwrap()[1].level, 1);
+    assert_eq!(store.count_due_at("a", now()).unwrap(), 1);
+    assert!(matches!(store.create_deck("a"), Err(RecallError::ListExists(_))));
+
+    store.delete_deck("b").unwrap();
+
+    assert_eq!(store.decks().unwrap(), vec!["a"]);
+    assert!(matches!(store.cards("b"), Err(RecallError::ListNotFound(_))));
+  }
+
+  #[test]
+  fn dir_store_keeps_decks_in_lists() {
+    let dirname = std::env::temp_dir().join(format!("recall-test-{}-store", std::process::id()));
+
+    create_dir_all(&dirname).unwrap();
+
+    let store = DirStore::new(&dirname);
+
+    store.create_deck("a").unwrap();
+    store.save_cards("a", cards(&[Some(-2), Some(-1), Some(1), None])).unwrap();
+    DirStore::new(&dirname).with_backend(Backend::Sqlite).create_deck("b").unwrap();
+
+    let exists = matches!(store.create_deck("b"), Err(RecallError::ListExists(_)));
+    let due_count = store.count_due_at("a", now());
+    let location = store.location("b");
+
+    store.delete_deck("a").unwrap();
+
+    let decks = store.decks();
+
+    remove_dir_all(&dirname).ok();
+    assert!(exists);
+    assert_eq!(due_count.unwrap(), 2);
+    assert!(location.unwrap().ends_with("b.sqlite"));
+    assert_eq!(decks.unwrap(), vec!["b"]);
+  }
+
+  #[test]
+  fn renames_decks() {
+    let memory_store = MemoryStore::new();
+
+    memory_store.create_deck("a").unwrap();
+    memory_store.create_deck("b").unwrap();
+    memory_store.save_cards("a", cards(&[Some(-1), None])).unwrap();
+    memory_store.rename_deck("a", "c").unwrap();
+
+    assert!(matches!(memory_store.rename_deck("c", "b"), Err(RecallError::ListExists(_))));
+    assert_eq!(memory_store.decks().unwrap(), vec!["b", "c"]);
+    assert_eq!(memory_store.cards("c").unwrap().len(), 2);
+
+    let dirname = std::env::temp_dir().join(format!("recall-test-{}-rename", std::process::id()));
+
+    create_dir_all(&dirname).unwrap();
+
+    let store = DirStore::new(&dirname);
+
+    DirStore::new(&dirname).with_backend(Backend::Sqlite).create_deck("a").unwrap();
+    store.create_deck("b").unwrap();
+    store.save_cards("a", cards(&[Some(-1), None])).unwrap();
+
+    let renamed = store.rename_deck("a", "c");
+    let exists = matches!(store.rename_deck("c", "b"), Err(RecallError::ListExists(_)));
+    let location = store.location("c");
+    let card_count = store.cards("c").map(|cards| cards.len());
+
+    remove_dir_all(&dirname).ok();
+    assert!(renamed.is_ok());
+    assert!(exists);
+    assert!(location.unwrap().ends_with("c.sqlite"));
+    assert_eq!(card_count.unwrap(), 2);
+  }
+
+  #[test]
+  fn counts_due_cards_of_decks() {
+    let memory_store = MemoryStore::new();
+
+    memory_store.create_deck("a").unwrap();
+    memory_store.save_cards("a", cards(&[Some(-1), None, Some(1)])).unwrap();
+
+    let dirname = std::env::temp_dir().join(format!("recall-test-{}-due-counts", std::process::id()));
+
+    create_dir_all(&dirname).unwrap();
+
+    let store = DirStore::new(&dirname);
+
+    store.create_deck("a").unwrap();
+    store.create_deck("b").unwrap();
+    store.save_cards("a", cards(&[Some(-2), Some(-1), Some(1), None])).unwrap();
+
+    let due_counts = store.due_counts(&["a".to_string(), "b".to_string(), "missing".to_string()], now());
+
+    remove_dir_all(&dirname).ok();
+    assert_eq!(memory_store.due_counts(&["a".to_string(), "missing".to_string()], now()), vec![1, 0]);
+    assert_eq!(due_counts, vec![2, 0, 0]);
+  }
+
+  #[test]
+  fn dir_store_checks_names() {
+    let dirname = std::env::temp_dir().join(format!("recall-test-{}-names", std::process::id()));
+
+    create_dir_all(&dirname).unwrap();
+    write(dirname.join("taken.jsonl"), "not a list\n").unwrap();
+
+    let store = DirStore::new(&dirname);
+
+    store.create_deck("a").unwrap();
+
+    let invalid = ["", ".hidden", "../a", "x/y", "x\\y"].iter()
+      .all(|name| {
+        matches!(store.rename_deck("a", name), Err(RecallError::InvalidArgument { .. }))
+        && matches!(store.create_deck(name), Err(RecallError::InvalidArgument { .. }))
+      });
+    let exists = matches!(store.rename_deck("a", "taken"), Err(RecallError::ListExists(_)));
+    let taken = std::fs::read_to_string(dirname.join("taken.jsonl"));
+
+    remove_dir_all(&dirname).ok();
+    assert!(invalid);
+    assert!(exists);
+    assert_eq!(taken.unwrap(), "not a list\n");
+  }
+}
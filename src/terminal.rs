@@ -0,0 +1,160 @@
+//! The prompt and print layer of interactive sessions.
+
+use std::fmt::Display;
+use std::io::{BufRead, Write};
+use colored::Colorize;
+use rustyline::{error::ReadlineError, Editor, KeyPress, Cmd};
+use crate::error::{RecallError, Result};
+
+/// Where interactive sessions print to and read answers from. Reads return
+/// `None` at the end of input.
+pub trait Terminal {
+  fn print(&mut self, text: &str);
+
+  fn read_line(&mut self, prefix: &str) -> Result<Option<String>>;
+
+  /// Reads text that may span multiple lines, starting with `initial`.
+  fn read_multiline(&mut self, prefix: &str, initial: &str) -> Result<Option<String>>;
+
+  fn println(&mut self, text: &str) {
+    self.print(&format!("{}\n", text));
+  }
+
+  fn prompt_with_prefix(&mut self, text: &str, prefix: &str) -> Result<String> {
+    if !text.is_empty() {
+      self.println(&format!("{}:", text.bright_white()));
+    }
+
+    Ok(self.read_line(prefix)?.unwrap_or_default())
+  }
+
+  fn prompt(&mut self, text: &str) -> Result<String> {
+    self.prompt_with_prefix(text, "> ")
+  }
+
+  fn prompt_multiline(&mut self, text: &str, initial: &str) -> Result<String> {
+    if !text.is_empty() {
+      self.print(&format!("{}: ", text.bright_white()));
+    }
+
+    self.println(&"(Press ^D to finish)".cyan().to_string());
+
+    Ok(self.read_multiline("> ", initial)?.unwrap_or_default())
+  }
+
+  /// Asks until one of the hotkeys is entered. Returns `None` if the input
+  /// ends before that.
+  fn prompt_multiple_choice(&mut self, text: &str, hotkeys: &[char]) -> Result<Option<char>> {
+    if !text.is_empty() {
+      self.print(&format!("{}: ", text.bright_white()));
+    }
+
+    loop {
+      let input = match self.read_line("> ")? {
+        Some(input) => input,
+        None => break Ok(None)
+      };
+
+      let chars = input.chars().collect::<Vec<_>>();
+
+      if chars.len() == 1 && hotkeys.contains(&chars[0]) {
+        break Ok(Some(chars[0].to_ascii_lowercase()));
+      }
+    }
+  }
+
+  fn print_error_strip(&mut self, text: &dyn Display) {
+    self.println(&format!("{} {}", "!".red().bold(), text));
+  }
+}
+
+fn readline_result(result: rustyline::Result<String>) -> Result<Option<String>> {
+  match result {
+    Ok(line) => Ok(Some(line)),
+    Err(ReadlineError::Eof) => Ok(None),
+    Err(ReadlineError::Interrupted) => Err(RecallError::Interrupted),
+    Err(err) => Err(RecallError::Prompt(Box::new(err)))
+  }
+}
+
+/// The real terminal, read with line editing. An interrupt fails the read with
+/// `RecallError::Interrupted`.
+#[derive(Debug, Default)]
+pub struct TtyTerminal;
+
+impl Terminal for TtyTerminal {
+  fn print(&mut self, text: &str) {
+    print!("{}", text);
+    std::io::stdout().flush().ok();
+  }
+
+  fn read_line(&mut self, prefix: &str) -> Result<Option<String>> {
+    readline_result(Editor::<()>::new().readline(prefix))
+  }
+
+  fn read_multiline(&mut self, prefix: &str, initial: &str) -> Result<Option<String>> {
+    let mut editor = Editor::<()>::new();
+
+    editor.bind_sequence(KeyPress::Enter, Cmd::Insert(1, "\n".to_string()));
+    editor.bind_sequence(KeyPress::Ctrl('D'), Cmd::AcceptLine);
+
+    readline_result(editor.readline_with_initial(prefix, (initial, "")))
+  }
+}
+
+/// Answers every prompt with the next line of the input, e.g. from a script
+/// or a test, and echoes it to the output. Multiline text is read as a single
+/// line with `\n` escapes.
+#[derive(Debug)]
+pub struct ScriptedTerminal<R, W> {
+  input: R,
+  output: W
+}
+
+impl<R: BufRead, W: Write> ScriptedTerminal<R, W> {
+  pub fn new(input: R, output: W) -> ScriptedTerminal<R, W> {
+    ScriptedTerminal {
+      input,
+      output
+    }
+  }
+
+  pub fn into_output(self) -> W {
+    self.output
+  }
+}
+
+impl<R: BufRead, W: Write> Terminal for ScriptedTerminal<R, W> {
+  fn print(&mut self, text: &str) {
+    write!(self.output, "{}", text).ok();
+  }
+
+  fn read_line(&mut self, prefix: &str) -> Result<Option<String>> {
+    let mut line = String::new();
+    let len = self.input.read_line(&mut line)
+      .map_err(|err| RecallError::Prompt(Box::new(err)))?;
+
+    if len == 0 {
+      return Ok(None);
+    }
+
+    let line = line.trim_end_matches(&['\r', '\n'][..]).to_string();
+
+    writeln!(self.output, "{}{}", prefix, line).ok();
+    Ok(Some(line))
+  }
+
+  /// Reads one line and unescapes `\n`. An empty line keeps `initial`.
+  fn read_multiline(&mut self, prefix: &str, initial: &str) -> Result<Option<String>> {
+    Ok(
+      self.read_line(prefix)?
+      .map(|line| {
+        if line.is_empty() {
+          initial.to_string()
+        } else {
+          line.replace("\\n", "\n")
+        }
+      })
+    )
+  }
+}
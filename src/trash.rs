@@ -0,0 +1,149 @@
+//! Removed lists, which are kept in `.trash` in the directory of the lists
+//! until the trash is emptied. Every removal gets a subdirectory named after
+//! its time, so a list can be removed and restored several times.
+
+use std::fs::{create_dir_all, read_dir, remove_dir, remove_dir_all, rename};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use crate::list::{get_lists, list_exists, List};
+use crate::error::{RecallError, Result, WithPath};
+
+const TIME_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+#[derive(Debug)]
+pub struct TrashedList {
+  pub list: List,
+  pub removed_at: DateTime<Utc>
+}
+
+fn trash_dir<P: AsRef<Path>>(dirname: P) -> PathBuf {
+  dirname.as_ref().join(".trash")
+}
+
+/// Moves the list into the trash and returns its new path.
+pub fn move_to_trash<P: AsRef<Path>>(dirname: P, list: &List, now: DateTime<Utc>) -> Result<PathBuf> {
+  let dir = trash_dir(dirname).join(now.format(TIME_FORMAT).to_string());
+  let path = dir.join(list.path().file_name().unwrap());
+
+  create_dir_all(&dir).with_path(&dir)?;
+  rename(list.path(), &path).with_path(list.path())?;
+  Ok(path)
+}
+
+/// Returns the lists in the trash, the most recently removed last.
+pub fn trashed_lists<P: AsRef<Path>>(dirname: P) -> Result<Vec<TrashedList>> {
+  let dir = trash_dir(dirname);
+  let mut result = Vec::new();
+
+  if !dir.exists() {
+    return Ok(result);
+  }
+
+  for entry in read_dir(&dir).with_path(&dir)? {
+    let path = entry.with_path(&dir)?.path();
+    let removed_at = path.file_name()
+      .and_then(|x| x.to_str())
+      .and_then(|x| NaiveDateTime::parse_from_str(x, TIME_FORMAT).ok());
+
+    if let (true, Some(removed_at)) = (path.is_dir(), removed_at) {
+      for list in get_lists(&path)? {
+        result.push(TrashedList {
+          list,
+          removed_at: DateTime::from_naive_utc_and_offset(removed_at, Utc)
+        });
+      }
+    }
+  }
+
+  result.sort_by(|x, y| (x.removed_at, x.list.name()).cmp(&(y.removed_at, y.list.name())));
+  Ok(result)
+}
+
+/// Moves the most recently removed list with the given name back out of the
+/// trash and returns its path.
+pub fn restore<P: AsRef<Path>>(dirname: P, name: &str) -> Result<PathBuf> {
+  let dirname = dirname.as_ref();
+
+  if list_exists(dirname, name) {
+    return Err(RecallError::ListExists(name.to_string()));
+  }
+
+  let trashed = trashed_lists(dirname)?
+    .into_iter()
+    .rfind(|trashed| trashed.list.name() == name)
+    .ok_or_else(|| RecallError::ListNotFound(name.to_string()))?;
+
+  let from = trashed.list.path();
+  let path = dirname.join(from.file_name().unwrap());
+
+  rename(from, &path).with_path(from)?;
+
+  if let Some(dir) = from.parent() {
+    remove_dir(dir).ok();
+  }
+
+  Ok(path)
+}
+
+/// Deletes all lists in the trash and returns how many there were.
+pub fn empty<P: AsRef<Path>>(dirname: P) -> Result<usize> {
+  let count = trashed_lists(&dirname)?.len();
+  let dir = trash_dir(dirname);
+
+  if dir.exists() {
+    remove_dir_all(&dir).with_path(&dir)?;
+  }
+
+  Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs::write;
+  use chrono::{Duration, TimeZone};
+  use super::*;
+
+  fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+  }
+
+  fn names(lists: &[TrashedList]) -> Vec<(&str, DateTime<Utc>)> {
+    lists.iter().map(|trashed| (trashed.list.name(), trashed.removed_at)).collect()
+  }
+
+  #[test]
+  fn removes_and_restores_lists() {
+    let dirname = std::env::temp_dir().join(format!("recall-test-{}-trash", std::process::id()));
+    let later = now() + Duration::hours(1);
+
+    create_dir_all(&dirname).unwrap();
+    write(dirname.join("a.jsonl"), "").unwrap();
+    write(dirname.join("b.jsonl"), "").unwrap();
+
+    move_to_trash(&dirname, &List::new(dirname.join("a.jsonl")).unwrap(), now()).unwrap();
+    move_to_trash(&dirname, &List::new(dirname.join("b.jsonl")).unwrap(), now()).unwrap();
+    write(dirname.join("a.jsonl"), "{\"front\":\"x\"}\n").unwrap();
+    move_to_trash(&dirname, &List::new(dirname.join("a.jsonl")).unwrap(), later).unwrap();
+
+    let trashed = trashed_lists(&dirname).unwrap();
+    let remaining = get_lists(&dirname).map(|lists| lists.count());
+    let restored = restore(&dirname, "a");
+    let restored_text = std::fs::read_to_string(dirname.join("a.jsonl"));
+    let exists = restore(&dirname, "a");
+    let missing = restore(&dirname, "c");
+    let trashed_after_restore = trashed_lists(&dirname).map(|lists| lists.len());
+    let emptied = empty(&dirname);
+    let trashed_after_empty = trashed_lists(&dirname).map(|lists| lists.len());
+
+    remove_dir_all(&dirname).ok();
+    assert_eq!(names(&trashed), vec![("a", now()), ("b", now()), ("a", later)]);
+    assert_eq!(remaining.unwrap(), 0);
+    assert!(restored.unwrap().ends_with("a.jsonl"));
+    assert_eq!(restored_text.unwrap(), "{\"front\":\"x\"}\n");
+    assert!(matches!(exists, Err(RecallError::ListExists(_))));
+    assert!(matches!(missing, Err(RecallError::ListNotFound(_))));
+    assert_eq!(trashed_after_restore.unwrap(), 2);
+    assert_eq!(emptied.unwrap(), 2);
+    assert_eq!(trashed_after_empty.unwrap(), 0);
+  }
+}